@@ -0,0 +1,13 @@
+use std::fs;
+
+use rusty_self_stabilizing_abstractions::ping_check::{ping_all_nodes, write_link_latencies};
+use rusty_self_stabilizing_abstractions::settings::socket_addrs_from_string;
+
+// Pings every host in the hosts file and writes the round trip times to
+// the latency file, for the nodes to record in their run results.
+fn main() {
+    let hosts_file_path = "../application/hosts.txt";
+    let string = fs::read_to_string(hosts_file_path).expect("Unable to read file");
+    let link_latencies = ping_all_nodes(socket_addrs_from_string(string));
+    write_link_latencies(&link_latencies);
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use colored::Color;
+
+use commons::constants::WINDOW_SIZE;
+use commons::encoding::Encoding;
+use commons::failure_detector_kind::FailureDetectorKind;
+use commons::transport_kind::TransportKind;
+use commons::types::{Int, NodeId};
+use commons::variant::Variant;
+use commons::workload::Workload;
+
+use crate::merge::application::ScdApplication;
+use crate::merge::clock::{self, Clock};
+use crate::merge::counter::Counter;
+use crate::merge::faulty_transport::{FaultyTransport, LinkFaults};
+use crate::merge::mergednode::{MergedNode, NodeHandle};
+use crate::merge::payload::{AppMessage, Payload};
+use crate::merge::snapshot::Snapshot;
+use crate::merge::transport::Transport;
+
+// Everything a node is configured with. The binary fills it in from the
+// command line, programs embedding a node start from NodeConfig::new,
+// which has the defaults of the command line arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeConfig {
+    pub node_id: NodeId,
+    pub socket_addrs: HashMap<NodeId, SocketAddr>,
+    // Empty if datagrams are not authenticated.
+    pub link_keys: HashMap<NodeId, Vec<u8>>,
+    pub terminal_color: Color,
+    pub print_client_operations: bool,
+    // Zero runs the node until it is stopped.
+    pub run_length: Duration,
+    pub window_size: Int,
+    pub record_evaluation_info: bool,
+    pub is_writer: bool,
+    pub is_failing_node: bool,
+    pub is_crashing_node: bool,
+    pub delta: Int,
+    pub variant: Variant,
+    pub encoding: Encoding,
+    pub transport: TransportKind,
+    pub faults: LinkFaults,
+    pub link_faults: HashMap<NodeId, LinkFaults>,
+    pub fault_seed: u64,
+    pub tick_interval: Duration,
+    pub failure_detector: FailureDetectorKind,
+    pub thetafd_w: Int,
+    pub thetafd_hysteresis: Int,
+    pub hbfd_w: Int,
+    pub phi_threshold: f64,
+    pub phi_window: usize,
+    pub theta_hb_timeout: Duration,
+    pub hbfd_hb_timeout: Duration,
+    pub max_int: Int,
+    pub rpc_port: Option<u16>,
+    pub workload: Workload,
+}
+
+impl NodeConfig {
+    pub fn new(node_id: NodeId, socket_addrs: HashMap<NodeId, SocketAddr>) -> NodeConfig {
+        NodeConfig {
+            node_id,
+            socket_addrs,
+            link_keys: HashMap::new(),
+            terminal_color: Color::Black,
+            print_client_operations: false,
+            run_length: Duration::from_secs(0),
+            window_size: WINDOW_SIZE,
+            record_evaluation_info: false,
+            is_writer: false,
+            is_failing_node: false,
+            is_crashing_node: false,
+            delta: 1,
+            variant: Variant::SCD,
+            encoding: Encoding::JSON,
+            transport: TransportKind::UDP,
+            faults: LinkFaults::default(),
+            link_faults: HashMap::new(),
+            fault_seed: 0,
+            tick_interval: Duration::from_micros(1000),
+            failure_detector: FailureDetectorKind::THETA,
+            thetafd_w: 5000,
+            thetafd_hysteresis: 10,
+            hbfd_w: 5000,
+            phi_threshold: 8.0,
+            phi_window: 1000,
+            theta_hb_timeout: Duration::from_millis(1000),
+            hbfd_hb_timeout: Duration::from_millis(1000),
+            max_int: 2147483647,
+            rpc_port: None,
+            workload: Workload::default(),
+        }
+    }
+
+    pub fn number_of_nodes(&self) -> Int {
+        self.socket_addrs.len() as Int
+    }
+
+    // The nodes started with --crashing are the ones reading the counter
+    // or taking snapshots.
+    pub fn is_reader(&self) -> bool {
+        self.is_crashing_node
+    }
+
+    pub fn has_faults(&self) -> bool {
+        !self.faults.is_none() || self.link_faults.values().any(|faults| !faults.is_none())
+    }
+}
+
+// Builds a MergedNode from a NodeConfig. Every node has its own config,
+// so nodes configured differently can run in the same process.
+pub struct NodeBuilder<V: Payload = AppMessage> {
+    config: NodeConfig,
+    application: Option<Box<dyn ScdApplication<V>>>,
+    default_application: fn(&NodeConfig) -> Option<Box<dyn ScdApplication<V>>>,
+    link_latencies: HashMap<NodeId, f64>,
+}
+
+impl NodeBuilder<AppMessage> {
+    // Without an application, COUNTER and SNAPSHOT nodes get a Counter and
+    // a Snapshot.
+    pub fn new(config: NodeConfig) -> NodeBuilder<AppMessage> {
+        NodeBuilder {
+            default_application: built_in_application,
+            ..NodeBuilder::for_payload(config)
+        }
+    }
+}
+
+fn built_in_application(config: &NodeConfig) -> Option<Box<dyn ScdApplication<AppMessage>>> {
+    match config.variant {
+        Variant::COUNTER => Some(Box::new(Counter::new())),
+        Variant::SNAPSHOT => Some(Box::new(Snapshot::new(config.node_id))),
+        _ => None,
+    }
+}
+
+impl<V: Payload> NodeBuilder<V> {
+    // For nodes broadcasting their own payload type. The built-in
+    // applications broadcast AppMessages, so these nodes only run the
+    // application they are given.
+    pub fn for_payload(config: NodeConfig) -> NodeBuilder<V> {
+        NodeBuilder {
+            config,
+            application: None,
+            default_application: |_| None,
+            link_latencies: HashMap::new(),
+        }
+    }
+
+    pub fn application(mut self, application: Box<dyn ScdApplication<V>>) -> NodeBuilder<V> {
+        self.application = Some(application);
+        self
+    }
+
+    // The round trip times from ping_check, recorded in the run result.
+    pub fn link_latencies(mut self, link_latencies: HashMap<NodeId, f64>) -> NodeBuilder<V> {
+        self.link_latencies = link_latencies;
+        self
+    }
+
+    // Binds the sockets of the configured transport.
+    pub fn build(mut self) -> MergedNode<FaultyTransport<Box<dyn Transport>>, V> {
+        let application = self.application_or_default();
+        let mut node = MergedNode::new(self.config, self.link_latencies);
+        if let Some(application) = application {
+            node.set_application(application);
+        }
+        node
+    }
+
+    pub fn build_with_transport<T: Transport>(self, transport: T) -> MergedNode<T, V> {
+        self.build_with_clock(transport, clock::system_clock())
+    }
+
+    pub fn build_with_clock<T: Transport>(mut self, transport: T, clock: Arc<dyn Clock>) -> MergedNode<T, V> {
+        let application = self.application_or_default();
+        let mut node = MergedNode::with_clock(self.config, transport, clock, self.link_latencies);
+        if let Some(application) = application {
+            node.set_application(application);
+        }
+        node
+    }
+
+    // Builds the node and starts its do forever loop.
+    pub fn start(self) -> NodeHandle<V> {
+        MergedNode::start_the_do_forever_loop(self.build())
+    }
+
+    fn application_or_default(&mut self) -> Option<Box<dyn ScdApplication<V>>> {
+        match self.application.take() {
+            Some(application) => Some(application),
+            None => (self.default_application)(&self.config),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use serde::{Serialize, Deserialize};
+    use commons::types::Tag;
+    use crate::merge::transport::ChannelTransport;
+
+    #[test]
+    fn test_differently_configured_nodes_run_side_by_side() {
+        let socket_addrs: HashMap<NodeId, SocketAddr> = (1..=3)
+            .map(|node_id| (node_id, format!("127.0.0.1:{}", 62000 + node_id).parse().unwrap()))
+            .collect();
+        let node_ids: HashSet<NodeId> = socket_addrs.keys().cloned().collect();
+        let mut network = ChannelTransport::network(&node_ids);
+        let mut nodes: Vec<MergedNode<ChannelTransport, AppMessage>> = (1..=3).map(|node_id| {
+            let mut config = NodeConfig::new(node_id, socket_addrs.clone());
+            config.delta = node_id;
+            if node_id == 3 {
+                config.failure_detector = FailureDetectorKind::PHI;
+            }
+            let mut node = NodeBuilder::new(config).build_with_transport(network.remove(&node_id).unwrap());
+            node.open_message_buffers();
+            node
+        }).collect();
+        assert_eq!(nodes.iter().map(|node| node.config.delta).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let tags: Vec<Tag> = (0..3)
+            .map(|i| nodes[0].scd_broadcast(AppMessage::BROADCAST(i.to_string())).unwrap())
+            .collect();
+        for iter in 0..2000 {
+            for node in nodes.iter_mut() {
+                let delta = node.config.delta;
+                node.bare_bone_loop_iter(iter % delta == 0);
+            }
+            if nodes.iter().all(|node| tags.iter().all(|tag| node.delivered_tags.contains(tag))) {
+                return;
+            }
+        }
+        panic!("Not every node delivered {:?}", tags);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct Reading {
+        sensor: String,
+        value: i64,
+    }
+
+    #[test]
+    fn test_nodes_broadcast_their_own_payload() {
+        let socket_addrs: HashMap<NodeId, SocketAddr> = (1..=3)
+            .map(|node_id| (node_id, format!("127.0.0.1:{}", 62100 + node_id).parse().unwrap()))
+            .collect();
+        let node_ids: HashSet<NodeId> = socket_addrs.keys().cloned().collect();
+        let mut network = ChannelTransport::network(&node_ids);
+        let mut nodes: Vec<MergedNode<ChannelTransport, Reading>> = (1..=3).map(|node_id| {
+            let config = NodeConfig::new(node_id, socket_addrs.clone());
+            let mut node = NodeBuilder::for_payload(config).build_with_transport(network.remove(&node_id).unwrap());
+            node.open_message_buffers();
+            node
+        }).collect();
+        let deliveries: Vec<_> = nodes.iter_mut().map(|node| node.subscribe_deliveries().1).collect();
+
+        let reading = Reading { sensor: "t1".to_string(), value: -3 };
+        let tag = nodes[0].scd_broadcast(reading.clone()).unwrap();
+        let mut delivered = vec![Vec::new(); nodes.len()];
+        for _ in 0..2000 {
+            for node in nodes.iter_mut() {
+                node.bare_bone_loop_iter(true);
+            }
+            for (batches, delivered) in deliveries.iter().zip(delivered.iter_mut()) {
+                delivered.extend(batches.try_iter().flatten());
+            }
+            if delivered.iter().all(|delivered| delivered.contains(&(tag.clone(), reading.clone()))) {
+                return;
+            }
+        }
+        panic!("Not every node delivered {:?}", reading);
+    }
+}
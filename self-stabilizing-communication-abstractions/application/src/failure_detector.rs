@@ -0,0 +1,112 @@
+pub mod theta;
+pub mod hb;
+pub mod phi;
+pub mod events;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use bit_vec::BitVec;
+
+use commons::failure_detector_kind::FailureDetectorKind;
+use commons::types::{Int, NodeId};
+use crate::config::NodeConfig;
+use crate::merge::clock::Clock;
+
+pub use theta::ThetaDetector;
+pub use hb::HbDetector;
+pub use phi::PhiAccrualDetector;
+pub use events::{Detector, FdEvent, FdSubscribers};
+
+// A failure detector is fed the heartbeats a node receives and decides
+// which nodes it trusts. Bit i of trusted() and entry i of heartbeats()
+// are about node i + 1.
+pub trait FailureDetector: Send {
+    fn on_heartbeat(&mut self, sender_id: NodeId);
+
+    fn trusted(&mut self) -> BitVec;
+
+    // The counters the detector bases its decisions on.
+    fn heartbeats(&self) -> Vec<Int>;
+}
+
+// The detector that decides which nodes are trusted, as chosen in the
+// config. It is fed the Θ heartbeats whichever detector it is, the phi
+// detector times them on the given clock.
+pub fn trust_detector(config: &NodeConfig, clock: Arc<dyn Clock>, node_id: NodeId, node_ids: &HashSet<NodeId>) -> Box<dyn FailureDetector> {
+    match config.failure_detector {
+        FailureDetectorKind::THETA => Box::new(ThetaDetector::new(node_id, node_ids, config.thetafd_w, config.thetafd_hysteresis)),
+        FailureDetectorKind::PHI => Box::new(PhiAccrualDetector::new(node_id, node_ids, config.phi_threshold, config.phi_window).with_clock(clock)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silent_node_is_suspected() {
+        let node_ids: HashSet<NodeId> = vec![1, 2, 3].into_iter().collect();
+        let mut theta = ThetaDetector::new(1, &node_ids, 3, 2);
+        let mut hb = HbDetector::new(1, &node_ids, 3);
+
+        for _ in 0..3 {
+            theta.on_heartbeat(2);
+            hb.on_heartbeat(1);
+            hb.on_heartbeat(2);
+        }
+        assert_eq!(theta.heartbeats(), vec![0, 0, 3]);
+        assert_eq!(theta.trusted(), BitVec::from_fn(3, |i| i < 2));
+        assert_eq!(hb.heartbeats(), vec![3, 3, 0]);
+        assert_eq!(hb.trusted(), BitVec::from_fn(3, |i| i < 2));
+    }
+
+    #[test]
+    fn test_theta_trusts_recovered_node_after_hysteresis() {
+        let node_ids: HashSet<NodeId> = vec![1, 2, 3].into_iter().collect();
+        let mut theta = ThetaDetector::new(1, &node_ids, 3, 2);
+        for _ in 0..3 {
+            theta.on_heartbeat(2);
+        }
+        assert!(!theta.trusted()[2]);
+
+        theta.on_heartbeat(3);
+        assert!(!theta.trusted()[2]);
+        // Silent for long enough again, which starts the hysteresis over.
+        for _ in 0..3 {
+            theta.on_heartbeat(2);
+        }
+        theta.on_heartbeat(3);
+        assert!(!theta.trusted()[2]);
+
+        theta.on_heartbeat(3);
+        assert!(theta.trusted()[2]);
+    }
+
+    #[test]
+    fn test_theta_counters_stop_at_w() {
+        let node_ids: HashSet<NodeId> = vec![1, 2, 3].into_iter().collect();
+        let mut theta = ThetaDetector::new(1, &node_ids, 3, 2);
+        for _ in 0..100 {
+            theta.on_heartbeat(2);
+        }
+        assert_eq!(theta.heartbeats(), vec![0, 0, 3]);
+        assert!(!theta.trusted()[2]);
+    }
+
+    #[test]
+    fn test_heartbeats_from_unknown_senders_are_ignored() {
+        let node_ids: HashSet<NodeId> = vec![1, 2, 3].into_iter().collect();
+        let mut theta = ThetaDetector::new(1, &node_ids, 3, 2);
+        let mut hb = HbDetector::new(1, &node_ids, 3);
+
+        for sender_id in vec![0, 4, -1, NodeId::max_value()] {
+            theta.on_heartbeat(sender_id);
+            hb.on_heartbeat(sender_id);
+        }
+        assert_eq!(theta.heartbeats(), vec![0, 0, 0]);
+        assert_eq!(theta.trusted(), BitVec::from_elem(3, true));
+        assert_eq!(hb.heartbeats(), vec![0, 0, 0]);
+        assert_eq!(hb.trusted(), BitVec::from_elem(3, true));
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use bit_vec::BitVec;
+
+use commons::types::{Int, NodeId};
+
+// The detector an event comes from. TRUST is the one chosen with
+// --failure-detector, HEARTBEAT is the heartbeat failure detector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Detector {
+    TRUST,
+    HEARTBEAT,
+}
+
+// A change in what a failure detector trusts, with the heartbeats of the
+// detector at the time of the change.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FdEvent {
+    Suspected { node_id: NodeId, detector: Detector, heartbeats: Vec<Int> },
+    Restored { node_id: NodeId, detector: Detector, heartbeats: Vec<Int> },
+}
+
+impl FdEvent {
+    // The events that take the trust from previous to current.
+    pub fn changes(previous: &BitVec, current: &BitVec, detector: Detector, heartbeats: &[Int]) -> Vec<FdEvent> {
+        previous.iter().zip(current.iter()).enumerate()
+            .filter(|(_, (was_trusted, is_trusted))| was_trusted != is_trusted)
+            .map(|(idx, (_, is_trusted))| {
+                let node_id = idx as NodeId + 1;
+                let heartbeats = heartbeats.to_vec();
+                if is_trusted {
+                    FdEvent::Restored { node_id, detector, heartbeats }
+                } else {
+                    FdEvent::Suspected { node_id, detector, heartbeats }
+                }
+            })
+            .collect()
+    }
+}
+
+// Everyone who asked for the failure detector events. Subscribers that
+// have dropped their receiver are forgotten on the next publish.
+pub struct FdSubscribers {
+    senders: Vec<Sender<FdEvent>>,
+}
+
+impl FdSubscribers {
+    pub fn new() -> FdSubscribers {
+        FdSubscribers {
+            senders: Vec::new(),
+        }
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<FdEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.senders.push(tx);
+        rx
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    pub fn publish(&mut self, events: Vec<FdEvent>) {
+        for event in events {
+            self.senders.retain(|sender| sender.send(event.clone()).is_ok());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscribers_receive_changes() {
+        let mut subscribers = FdSubscribers::new();
+        let rx = subscribers.subscribe();
+        drop(subscribers.subscribe());
+
+        let previous = BitVec::from_fn(3, |i| i != 1);
+        let current = BitVec::from_fn(3, |i| i != 2);
+        subscribers.publish(FdEvent::changes(&previous, &current, Detector::HEARTBEAT, &[4, 4, 1]));
+
+        assert_eq!(rx.try_iter().collect::<Vec<FdEvent>>(), vec![
+            FdEvent::Restored { node_id: 2, detector: Detector::HEARTBEAT, heartbeats: vec![4, 4, 1] },
+            FdEvent::Suspected { node_id: 3, detector: Detector::HEARTBEAT, heartbeats: vec![4, 4, 1] },
+        ]);
+        assert_eq!(subscribers.senders.len(), 1);
+    }
+}
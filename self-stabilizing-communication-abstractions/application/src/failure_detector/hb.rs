@@ -0,0 +1,58 @@
+use std::collections::{HashMap, HashSet};
+
+use bit_vec::BitVec;
+
+use commons::types::{Int, NodeId};
+use super::FailureDetector;
+
+// The heartbeat failure detector counts the heartbeats received from every
+// node, the node's own count goes up once per round of heartbeats it
+// sends. A node is trusted while it has been heard from within the last w
+// rounds.
+pub struct HbDetector {
+    node_id: NodeId,
+    hb: HashMap<NodeId, Int>,
+    // The own count when a heartbeat from the node last arrived.
+    last_heard: HashMap<NodeId, Int>,
+    w: Int,
+}
+
+impl HbDetector {
+    pub fn new(node_id: NodeId, node_ids: &HashSet<NodeId>, w: Int) -> HbDetector {
+        HbDetector {
+            node_id,
+            hb: node_ids.iter().map(|node_id| (*node_id, 0)).collect(),
+            last_heard: node_ids.iter().map(|node_id| (*node_id, 0)).collect(),
+            w,
+        }
+    }
+
+    // Starts counting from zero, as if every node had just been heard from.
+    pub fn reset(&mut self) {
+        for count in self.hb.values_mut().chain(self.last_heard.values_mut()) {
+            *count = 0;
+        }
+    }
+}
+
+impl FailureDetector for HbDetector {
+    fn on_heartbeat(&mut self, sender_id: NodeId) {
+        if !self.hb.contains_key(&sender_id) {
+            return;
+        }
+        *self.hb.get_mut(&sender_id).unwrap() += 1;
+        let own = self.hb[&self.node_id];
+        *self.last_heard.get_mut(&sender_id).unwrap() = own;
+    }
+
+    fn trusted(&mut self) -> BitVec {
+        let own = self.hb[&self.node_id];
+        (1..=self.hb.len() as NodeId)
+            .map(|node_id| own - self.last_heard[&node_id] < self.w)
+            .collect()
+    }
+
+    fn heartbeats(&self) -> Vec<Int> {
+        (1..=self.hb.len() as NodeId).map(|node_id| self.hb[&node_id]).collect()
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+
+use bit_vec::BitVec;
+
+use commons::types::{Int, NodeId};
+use super::FailureDetector;
+use crate::merge::clock::{self, Clock};
+
+// The inter-arrival times are never assumed to vary less than this.
+// Otherwise a node whose heartbeats have arrived like clockwork would be
+// suspected at the slightest delay.
+const MIN_STD_DEV_MILLIS: f64 = 10.0;
+
+// The last inter-arrival times of the heartbeats from one node.
+struct ArrivalWindow {
+    last_arrival: Option<Instant>,
+    intervals: VecDeque<f64>,
+    sum: f64,
+    squared_sum: f64,
+}
+
+impl ArrivalWindow {
+    fn new() -> ArrivalWindow {
+        ArrivalWindow {
+            last_arrival: None,
+            intervals: VecDeque::new(),
+            sum: 0.0,
+            squared_sum: 0.0,
+        }
+    }
+
+    fn add(&mut self, now: Instant, window_size: usize) {
+        if let Some(last_arrival) = self.last_arrival {
+            let interval = now.duration_since(last_arrival).as_secs_f64() * 1000.0;
+            self.intervals.push_back(interval);
+            self.sum += interval;
+            self.squared_sum += interval * interval;
+            if self.intervals.len() > window_size {
+                let oldest = self.intervals.pop_front().unwrap();
+                self.sum -= oldest;
+                self.squared_sum -= oldest * oldest;
+            }
+        }
+        self.last_arrival = Some(now);
+    }
+
+    // How unlikely it is, as -log10 of the probability, that the next
+    // heartbeat is still on its way given a normal distribution of the
+    // inter-arrival times. The logistic approximation of the normal CDF is
+    // the one used by Akka.
+    fn phi(&self, now: Instant) -> f64 {
+        let last_arrival = match self.last_arrival {
+            Some(last_arrival) if !self.intervals.is_empty() => last_arrival,
+            _ => return 0.0,
+        };
+        let n = self.intervals.len() as f64;
+        let mean = self.sum / n;
+        let variance = (self.squared_sum / n - mean * mean).max(0.0);
+        let std_dev = variance.sqrt().max(MIN_STD_DEV_MILLIS);
+
+        let elapsed = now.duration_since(last_arrival).as_secs_f64() * 1000.0;
+        let y = (elapsed - mean) / std_dev;
+        let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+        if elapsed > mean {
+            -(e / (1.0 + e)).log10()
+        } else {
+            -(1.0 - 1.0 / (1.0 + e)).log10()
+        }
+    }
+}
+
+// The phi accrual failure detector of Hayashibara et al. Instead of a
+// fixed bound, every node gets a suspicion level phi that grows with the
+// time since its last heartbeat, scaled by how regularly its heartbeats
+// have arrived so far. A node is suspected while phi is above the
+// threshold, and trusted again as soon as a heartbeat brings it down.
+pub struct PhiAccrualDetector {
+    node_id: NodeId,
+    windows: HashMap<NodeId, ArrivalWindow>,
+    heartbeats: HashMap<NodeId, Int>,
+    threshold: f64,
+    window_size: usize,
+    clock: Arc<dyn Clock>,
+}
+
+impl PhiAccrualDetector {
+    pub fn new(node_id: NodeId, node_ids: &HashSet<NodeId>, threshold: f64, window_size: usize) -> PhiAccrualDetector {
+        PhiAccrualDetector {
+            node_id,
+            windows: node_ids.iter().map(|node_id| (*node_id, ArrivalWindow::new())).collect(),
+            heartbeats: node_ids.iter().map(|node_id| (*node_id, 0)).collect(),
+            threshold,
+            window_size,
+            clock: clock::system_clock(),
+        }
+    }
+
+    // Heartbeats are timed on this clock instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> PhiAccrualDetector {
+        self.clock = clock;
+        self
+    }
+
+    // Heartbeats from nodes we don't know are dropped.
+    fn on_heartbeat_at(&mut self, sender_id: NodeId, now: Instant) {
+        if let (Some(window), Some(heartbeats)) = (self.windows.get_mut(&sender_id), self.heartbeats.get_mut(&sender_id)) {
+            window.add(now, self.window_size);
+            *heartbeats += 1;
+        }
+    }
+
+    fn trusted_at(&self, now: Instant) -> BitVec {
+        (1..=self.windows.len() as NodeId)
+            .map(|node_id| node_id == self.node_id || self.windows[&node_id].phi(now) < self.threshold)
+            .collect()
+    }
+}
+
+impl FailureDetector for PhiAccrualDetector {
+    fn on_heartbeat(&mut self, sender_id: NodeId) {
+        let now = self.clock.now();
+        self.on_heartbeat_at(sender_id, now);
+    }
+
+    fn trusted(&mut self) -> BitVec {
+        self.trusted_at(self.clock.now())
+    }
+
+    fn heartbeats(&self) -> Vec<Int> {
+        (1..=self.heartbeats.len() as NodeId).map(|node_id| self.heartbeats[&node_id]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_suspicion_grows_with_the_delay() {
+        let node_ids: HashSet<NodeId> = vec![1, 2].into_iter().collect();
+        let mut phi = PhiAccrualDetector::new(1, &node_ids, 8.0, 100);
+        let start = Instant::now();
+        for i in 0..10 {
+            phi.on_heartbeat_at(2, start + Duration::from_millis(100 * i));
+        }
+        let last = start + Duration::from_millis(900);
+
+        assert!(phi.windows[&2].phi(last + Duration::from_millis(100)) < 1.0);
+        assert!(phi.trusted_at(last + Duration::from_millis(100))[1]);
+        assert!(!phi.trusted_at(last + Duration::from_millis(1000))[1]);
+
+        phi.on_heartbeat_at(2, last + Duration::from_millis(1000));
+        assert!(phi.trusted_at(last + Duration::from_millis(1000))[1]);
+    }
+
+    #[test]
+    fn test_heartbeats_from_unknown_senders_are_dropped() {
+        let node_ids: HashSet<NodeId> = vec![1, 2].into_iter().collect();
+        let mut phi = PhiAccrualDetector::new(1, &node_ids, 8.0, 100);
+        let start = Instant::now();
+        for sender_id in vec![0, 3, -1] {
+            phi.on_heartbeat_at(sender_id, start);
+        }
+        assert_eq!(phi.heartbeats(), vec![0, 0]);
+        assert!(phi.trusted_at(start).all());
+    }
+}
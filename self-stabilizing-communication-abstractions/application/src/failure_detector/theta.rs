@@ -0,0 +1,85 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+
+use bit_vec::BitVec;
+
+use commons::types::{Int, NodeId};
+use super::FailureDetector;
+
+// The Θ failure detector. Every heartbeat resets the counter of its sender
+// and increases the counters of all others, so a counter tells how many
+// heartbeats from others arrived since the node was last heard from. A
+// node is suspected once its counter reaches w. A suspected node is
+// trusted again after sending hysteresis heartbeats without its counter
+// reaching w in between, so that a node that was only slow for a while
+// (a GC pause, a burst of loss) is not excluded forever, while one that
+// keeps flapping stays suspected.
+pub struct ThetaDetector {
+    node_id: NodeId,
+    vector: HashMap<NodeId, Int>,
+    current_trusted: BitVec,
+    // Heartbeats from each suspected node since it was last suspected.
+    readmission_heartbeats: HashMap<NodeId, Int>,
+    w: Int,
+    hysteresis: Int,
+}
+
+impl ThetaDetector {
+    pub fn new(node_id: NodeId, node_ids: &HashSet<NodeId>, w: Int, hysteresis: Int) -> ThetaDetector {
+        ThetaDetector {
+            node_id,
+            vector: node_ids.iter().map(|node_id| (*node_id, 0)).collect(),
+            current_trusted: BitVec::from_elem(node_ids.len(), true),
+            readmission_heartbeats: node_ids.iter().map(|node_id| (*node_id, 0)).collect(),
+            w,
+            hysteresis,
+        }
+    }
+
+    fn is_trusted(&self, node_id: NodeId) -> bool {
+        self.current_trusted.get(node_id as usize - 1).unwrap()
+    }
+}
+
+impl FailureDetector for ThetaDetector {
+    fn on_heartbeat(&mut self, sender_id: NodeId) {
+        // The sender id comes from the wire, heartbeats from nodes we don't
+        // know are dropped.
+        if !self.vector.contains_key(&sender_id) {
+            return;
+        }
+        if !self.is_trusted(sender_id) {
+            *self.readmission_heartbeats.get_mut(&sender_id).unwrap() += 1;
+        }
+        for (idx, val) in self.vector.iter_mut() {
+            // Counting stops at w, beyond it the node is suspected all the
+            // same, and the counter of a crashed node would grow forever.
+            *val = if *idx == sender_id || *idx == self.node_id {
+                0
+            } else {
+                cmp::min(*val + 1, self.w)
+            };
+            if *val >= self.w {
+                *self.readmission_heartbeats.get_mut(idx).unwrap() = 0;
+            }
+        }
+    }
+
+    fn trusted(&mut self) -> BitVec {
+        for (idx, x) in self.vector.iter() {
+            let readmission_heartbeats = self.readmission_heartbeats.get_mut(idx).unwrap();
+            if *x >= self.w {
+                self.current_trusted.set(*idx as usize - 1, false);
+                *readmission_heartbeats = 0;
+            } else if *readmission_heartbeats >= self.hysteresis {
+                self.current_trusted.set(*idx as usize - 1, true);
+                *readmission_heartbeats = 0;
+            }
+        }
+        self.current_trusted.clone()
+    }
+
+    fn heartbeats(&self) -> Vec<Int> {
+        (1..=self.vector.len() as NodeId).map(|node_id| self.vector[&node_id]).collect()
+    }
+}
@@ -0,0 +1,26 @@
+#![allow(dead_code, unused_variables, unused_imports, unused_mut)]
+#![allow(non_snake_case)]
+
+// The node stack, for the application and ping_check binaries and for
+// programs that embed a node. Configure it with a NodeConfig, build it
+// with a NodeBuilder and talk to it with a Client.
+
+mod communicator;
+mod configuration_manager;
+mod mediator;
+mod responsible_cell;
+mod urb;
+mod scd;
+mod failure_detector;
+pub mod config;
+pub mod settings;
+pub mod terminal_output;
+pub mod merge;
+pub mod ping_check;
+pub mod workload;
+
+pub use crate::config::{NodeConfig, NodeBuilder};
+pub use crate::merge::mergednode::{MergedNode, NodeHandle};
+pub use crate::merge::client::{Client, ClientRequest, ClientResponse, ClientError, RequestId};
+pub use crate::merge::application::{ScdApplication, ScdContext};
+pub use crate::merge::payload::{AppMessage, Payload};
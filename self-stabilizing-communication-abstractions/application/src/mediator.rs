@@ -12,10 +12,11 @@ use crate::communicator::{udp_communicator::UDPCommunicator, CommunicatorDelegat
 //use crate::communicator::dccp_communicator::DCCPCommunicator;
 use crate::configuration_manager::ConfigurationManager;
 use crate::responsible_cell::ResponsibleCell;
-use crate::settings::SETTINGS;
+use crate::config::NodeConfig;
 use crate::urb::{NodeDelegate, UrbBroadcaster, urb_node::UrbNode};
 
 pub struct Mediator {
+    config: NodeConfig,
     communicator: ResponsibleCell<Option<Arc<UDPCommunicator<Mediator>>>>,
     configuration_manager: ConfigurationManager,
     run_result: Mutex<RunResult>,
@@ -27,11 +28,12 @@ pub struct Mediator {
 }
 
 impl Mediator {
-    pub fn new(deliver_end: Sender<String>, broadcast_end: Receiver<String>) -> Arc<Mediator> {
-        let node_id = SETTINGS.node_id();
-        let socket_addrs = SETTINGS.socket_addrs().clone();
+    pub fn new(config: NodeConfig, deliver_end: Sender<String>, broadcast_end: Receiver<String>) -> Arc<Mediator> {
+        let node_id = config.node_id;
+        let socket_addrs = config.socket_addrs.clone();
         let node_ids = socket_addrs.keys().map(|node_id| *node_id).collect();
         let mediator = Mediator {
+            config,
             communicator: ResponsibleCell::new(None),
             configuration_manager: ConfigurationManager::new(node_id, node_ids),
             run_result: Mutex::new(RunResult::new()),
@@ -137,12 +139,16 @@ impl NodeDelegate for Mediator {
         self.configuration_manager().node_ids()
     }
 
+    fn config(&self) -> &NodeConfig {
+        &self.config
+    }
+
     fn number_of_nodes(&self) -> Int {
         self.configuration_manager().number_of_nodes()
     }
 
     fn record_evaluation_info(&self) -> bool {
-        SETTINGS.record_evaluation_info()
+        self.config.record_evaluation_info
     }
 
     fn run_result(&self) -> MutexGuard<RunResult> {
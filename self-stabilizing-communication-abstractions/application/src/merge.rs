@@ -1,9 +1,25 @@
 pub mod mergednode;
 pub mod communicator;
+pub mod transport;
+pub mod tcp_transport;
+pub mod faulty_transport;
+pub mod readiness;
+pub mod wire;
+pub mod batching;
+pub mod fragmentation;
+pub mod auth;
+pub mod payload;
+pub mod buffer;
+pub mod reset;
 pub mod hbfd;
 pub mod thetafd;
 pub mod urb;
 pub mod scd;
 pub mod utils;
+pub mod client;
+pub mod rpc;
+pub mod application;
 pub mod counter;
-pub mod snapshot;
\ No newline at end of file
+pub mod snapshot;
+pub mod clock;
+pub mod simulator;
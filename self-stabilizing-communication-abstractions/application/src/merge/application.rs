@@ -0,0 +1,159 @@
+use std::fmt::Debug;
+
+use commons::run_result::RunResult;
+use commons::types::{NodeId, Tag};
+use crate::config::NodeConfig;
+use super::client::{RequestId, ClientRequest, ClientResponse, ClientError};
+use super::mergednode::MergedNode;
+use super::transport::Transport;
+use super::payload::Payload;
+use super::clock::Clock;
+
+// An application on top of SCD, like the counter or the snapshot object.
+//
+// An operation may wait for broadcasts to terminate, the node keeps
+// running its loop meanwhile. What SCD delivers during the wait is handed
+// to the application when the wait is over, so waits should go through
+// wait_for and wait_for_all.
+pub trait ScdApplication<V: Payload>: Debug + Send {
+    // The response is sent to the client.
+    fn execute(&mut self, request: ClientRequest<V>, scd: &mut dyn ScdContext<V>) -> ClientResponse;
+
+    fn on_scd_deliver(&mut self, batch: &[V]);
+
+    // Printed when the node stops.
+    fn report(&self) -> Option<String> {
+        None
+    }
+
+    // hasTerminated(tag)
+    fn wait_for(&mut self, scd: &mut dyn ScdContext<V>, tag: &Tag) {
+        scd.wait_until_terminated(tag);
+        for batch in scd.take_deliveries() {
+            self.on_scd_deliver(&batch);
+        }
+    }
+
+    // allHaveTerminated()
+    fn wait_for_all(&mut self, scd: &mut dyn ScdContext<V>) {
+        scd.wait_until_all_terminated();
+        for batch in scd.take_deliveries() {
+            self.on_scd_deliver(&batch);
+        }
+    }
+}
+
+// What an application can do with the node while it runs an operation.
+pub trait ScdContext<V: Payload> {
+    fn node_id(&self) -> NodeId;
+
+    fn config(&self) -> &NodeConfig;
+
+    // None if there is no space in the buffer.
+    fn broadcast(&mut self, msg: V) -> Option<Tag>;
+
+    // Like broadcast, but may run the loop until there is space.
+    fn broadcast_eventually(&mut self, msg: V) -> Option<Tag>;
+
+    fn wait_until_terminated(&mut self, tag: &Tag);
+
+    fn wait_until_all_terminated(&mut self);
+
+    // The batches delivered since the operation started.
+    fn take_deliveries(&mut self) -> Vec<Vec<V>>;
+
+    fn run_result(&mut self) -> &mut RunResult;
+
+    // The clock latencies are measured on.
+    fn clock(&self) -> &dyn Clock;
+
+    fn log(&mut self, message: String);
+}
+
+impl<T: Transport, V: Payload> ScdContext<V> for MergedNode<T, V> {
+    fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+
+    fn config(&self) -> &NodeConfig {
+        &self.config
+    }
+
+    fn broadcast(&mut self, msg: V) -> Option<Tag> {
+        self.scd_broadcast(msg)
+    }
+
+    fn broadcast_eventually(&mut self, msg: V) -> Option<Tag> {
+        self.scd_broadcast_eventually(msg)
+    }
+
+    fn wait_until_terminated(&mut self, tag: &Tag) {
+        let mut iter = 0;
+        while !self.scd_has_terminated(tag) {
+            self.readiness.wait();
+            self.bare_bone_loop_iter(iter % self.config.delta == 0);
+            iter += 1;
+        }
+    }
+
+    fn wait_until_all_terminated(&mut self) {
+        let mut iter = 0;
+        loop {
+            self.readiness.wait();
+            self.bare_bone_loop_iter(iter % self.config.delta == 0);
+            if self.scd_all_have_terminated() {
+                break;
+            }
+            iter += 1;
+        }
+    }
+
+    fn take_deliveries(&mut self) -> Vec<Vec<V>> {
+        self.pending_deliveries.as_mut().map(|batches| batches.drain(..).collect()).unwrap_or_default()
+    }
+
+    fn run_result(&mut self) -> &mut RunResult {
+        &mut self.run_result
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        &*self.clock
+    }
+
+    fn log(&mut self, message: String) {
+        MergedNode::log(self, message)
+    }
+}
+
+impl<T: Transport, V: Payload> MergedNode<T, V> {
+    pub fn set_application(&mut self, application: Box<dyn ScdApplication<V>>) {
+        self.application = Some(application);
+    }
+
+    pub(crate) fn execute_request(&mut self, id: RequestId, request: ClientRequest<V>) {
+        let mut application = match self.application.take() {
+            Some(application) => application,
+            None => {
+                self.respond(id, ClientResponse::Error(ClientError::Unsupported));
+                return;
+            }
+        };
+        self.pending_deliveries = Some(Vec::new());
+        let response = application.execute(request, self);
+        for batch in self.take_deliveries() {
+            application.on_scd_deliver(&batch);
+        }
+        self.pending_deliveries = None;
+        self.application = Some(application);
+
+        self.respond(id, response);
+    }
+
+    pub(crate) fn deliver_to_scd_application(&mut self, batch: Vec<V>) {
+        if let Some(application) = self.application.as_mut() {
+            application.on_scd_deliver(&batch);
+        } else if let Some(batches) = self.pending_deliveries.as_mut() {
+            batches.push(batch);
+        }
+    }
+}
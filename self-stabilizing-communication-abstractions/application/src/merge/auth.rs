@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+
+use commons::types::NodeId;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const TAG_LEN: usize = 32;
+
+// A sealed datagram is [sender: i32][datagram][tag], where the tag is an
+// HMAC-SHA256 over sender, receiver and datagram keyed by the secret of
+// the link. Including the receiver stops a datagram from being reflected
+// back to its sender.
+pub const AUTH_OVERHEAD: usize = 4 + TAG_LEN;
+
+pub struct LinkAuthenticator {
+    node_id: NodeId,
+    link_keys: HashMap<NodeId, Vec<u8>>,
+}
+
+impl LinkAuthenticator {
+    pub fn new<R: Rng>(node_id: NodeId, mut link_keys: HashMap<NodeId, Vec<u8>>, rng: &mut R) -> LinkAuthenticator {
+        // Nobody else needs the key of the link to ourselves, so it doesn't
+        // have to be listed in the hosts file.
+        link_keys.entry(node_id).or_insert_with(|| {
+            (0..TAG_LEN).map(|_| rng.gen()).collect()
+        });
+
+        LinkAuthenticator {
+            node_id,
+            link_keys,
+        }
+    }
+
+    // None if there is no key for the link to the receiver, which only a
+    // config that lists keys for some of the nodes has.
+    pub fn seal(&self, datagram: &[u8], receiver_id: NodeId) -> Option<Vec<u8>> {
+        let key = self.link_keys.get(&receiver_id)?;
+        let mut sealed = Vec::with_capacity(AUTH_OVERHEAD + datagram.len());
+        sealed.extend_from_slice(&self.node_id.to_be_bytes());
+        sealed.extend_from_slice(datagram);
+        let tag = Self::mac(key, self.node_id, receiver_id, datagram).result().code();
+        sealed.extend_from_slice(&tag);
+        Some(sealed)
+    }
+
+    // Returns the datagram inside a sealed one, or None if it doesn't come
+    // from a node we share a key with or has been tampered with.
+    pub fn open<'a>(&self, sealed: &'a [u8]) -> Option<&'a [u8]> {
+        if sealed.len() < AUTH_OVERHEAD {
+            return None;
+        }
+        let sender_id = NodeId::from_be_bytes(sealed[0..4].try_into().unwrap());
+        let key = self.link_keys.get(&sender_id)?;
+        let datagram = &sealed[4..sealed.len() - TAG_LEN];
+        let tag = &sealed[sealed.len() - TAG_LEN..];
+
+        match Self::mac(key, sender_id, self.node_id, datagram).verify(tag) {
+            Ok(()) => Some(datagram),
+            Err(_) => None,
+        }
+    }
+
+    fn mac(key: &[u8], sender_id: NodeId, receiver_id: NodeId, datagram: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_varkey(key).expect("HMAC accepts keys of any length");
+        mac.input(&sender_id.to_be_bytes());
+        mac.input(&receiver_id.to_be_bytes());
+        mac.input(datagram);
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn keys(pairs: &[(NodeId, &[u8])]) -> HashMap<NodeId, Vec<u8>> {
+        pairs.iter().map(|(id, key)| (*id, key.to_vec())).collect()
+    }
+
+    #[test]
+    fn test_only_untampered_datagrams_from_known_nodes_open() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let node1 = LinkAuthenticator::new(1, keys(&[(2, b"secret12"), (3, b"secret13")]), &mut rng);
+        let node2 = LinkAuthenticator::new(2, keys(&[(1, b"secret12"), (3, b"secret23")]), &mut rng);
+        let node3 = LinkAuthenticator::new(3, keys(&[(1, b"secret13"), (2, b"secret23")]), &mut rng);
+
+        let sealed = node1.seal(b"GOSSIP", 2).unwrap();
+        assert_eq!(node2.open(&sealed), Some(&b"GOSSIP"[..]));
+        // Not meant for node 3, and node 1 must not accept it as coming from 2.
+        assert_eq!(node3.open(&sealed), None);
+        assert_eq!(node1.open(&sealed), None);
+
+        let mut tampered = sealed.clone();
+        tampered[5] ^= 1;
+        assert_eq!(node2.open(&tampered), None);
+
+        let mut spoofed = sealed.clone();
+        spoofed[0..4].copy_from_slice(&3i32.to_be_bytes());
+        assert_eq!(node2.open(&spoofed), None);
+
+        let to_self = node1.seal(b"HB", 1).unwrap();
+        assert_eq!(node1.open(&to_self), Some(&b"HB"[..]));
+    }
+
+    #[test]
+    fn test_datagrams_to_nodes_without_a_key_are_not_sealed() {
+        let node1 = LinkAuthenticator::new(1, keys(&[(2, b"secret12")]), &mut StdRng::seed_from_u64(0));
+        assert!(node1.seal(b"GOSSIP", 2).is_some());
+        assert_eq!(node1.seal(b"GOSSIP", 3), None);
+    }
+}
@@ -0,0 +1,67 @@
+use std::convert::TryInto;
+use std::mem;
+
+// A batch is a sequence of [len: u32][message], big endian, holding the
+// messages produced for one destination during one loop iteration.
+const LEN_PREFIX: usize = 4;
+
+// Packs the messages, in order, into as few batches of at most
+// max_batch_size bytes as possible. A message that doesn't fit in a batch
+// of its own still gets one, it's up to the layer below to fragment it.
+pub fn pack(messages: &[Vec<u8>], max_batch_size: usize) -> Vec<Vec<u8>> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::new();
+    for message in messages {
+        if !batch.is_empty() && batch.len() + LEN_PREFIX + message.len() > max_batch_size {
+            batches.push(mem::replace(&mut batch, Vec::new()));
+        }
+        batch.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        batch.extend_from_slice(message);
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+// Returns None if the batch is malformed, in which case all of it is
+// dropped like a lost datagram.
+pub fn unpack(batch: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    while offset < batch.len() {
+        if batch.len() - offset < LEN_PREFIX {
+            return None;
+        }
+        let len = u32::from_be_bytes(batch[offset..offset + LEN_PREFIX].try_into().unwrap()) as usize;
+        offset += LEN_PREFIX;
+        if batch.len() - offset < len {
+            return None;
+        }
+        messages.push(&batch[offset..offset + len]);
+        offset += len;
+    }
+    Some(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack_keeps_order_and_respects_size() {
+        let messages: Vec<Vec<u8>> = (0..10).map(|i| vec![i; 30]).collect();
+        let batches = pack(&messages, 100);
+        assert_eq!(batches.len(), 5);
+        assert!(batches.iter().all(|batch| batch.len() <= 100));
+
+        let unpacked: Vec<Vec<u8>> = batches.iter()
+            .flat_map(|batch| unpack(batch).unwrap())
+            .map(|message| message.to_vec())
+            .collect();
+        assert_eq!(unpacked, messages);
+
+        assert_eq!(pack(&[vec![1; 500]], 100).len(), 1);
+        assert_eq!(unpack(&batches[0][..40]), None);
+    }
+}
@@ -0,0 +1,371 @@
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::iter;
+use std::ops::{Deref, DerefMut};
+use std::slice;
+
+use commons::types::{Int, NodeId, Tag};
+use crate::urb::types::BufferRecord;
+use super::payload::{Payload, UrbPayload};
+
+pub type Record<V> = BufferRecord<UrbPayload<V>>;
+
+// The buffer shared by URB and SCD. Next to the records it keeps them
+// indexed by URB tag and by SCD tag, per sender and ordered by seq.
+//
+// The index is derived from the records, never the other way around.
+// Records are changed through a RecordMut, which updates the index with
+// the tags and the clock of the record when it is dropped. A clock entry
+// that goes down, or records that are removed, drop the index instead and
+// the next lookup rebuilds it. So it is right whatever the
+// self-stabilization routines do to the records, duplicate tags included.
+pub struct MessageBuffer<V: Payload> {
+    records: Vec<Record<V>>,
+    index: RefCell<Option<BufferIndex>>,
+}
+
+#[derive(Default)]
+struct BufferIndex {
+    // Per sender, the positions of its records by URB seq and by SCD seq,
+    // that is the sender's own entry in the clock. In buffer order, so the
+    // first one is the record a scan would find.
+    urb_seqs: HashMap<NodeId, BTreeMap<Int, Vec<usize>>>,
+    scd_seqs: HashMap<NodeId, BTreeMap<Int, Vec<usize>>>,
+    // Per node, the largest clock entry below Int::max_value() over all
+    // SCD records.
+    max_clocks: HashMap<NodeId, Int>,
+}
+
+impl BufferIndex {
+    // Moves the record at the position to its new keys. False if a clock
+    // entry went down, then the index has to be rebuilt.
+    fn update(&mut self, position: usize, old: &RecordKeys, new: &RecordKeys) -> bool {
+        if old.urb_tag != new.urb_tag {
+            Self::remove_position(&mut self.urb_seqs, &old.urb_tag, position);
+            Self::insert_position(&mut self.urb_seqs, &new.urb_tag, position);
+        }
+        if old.scd_tag != new.scd_tag {
+            Self::remove_position(&mut self.scd_seqs, &old.scd_tag, position);
+            Self::insert_position(&mut self.scd_seqs, &new.scd_tag, position);
+        }
+        self.raise_max_clocks(&old.cl, &new.cl)
+    }
+
+    fn insert_position(seqs: &mut HashMap<NodeId, BTreeMap<Int, Vec<usize>>>, tag: &Option<Tag>, position: usize) {
+        if let Some(tag) = tag {
+            let positions = seqs.entry(tag.id).or_default().entry(tag.seq).or_default();
+            if let Err(at) = positions.binary_search(&position) {
+                positions.insert(at, position);
+            }
+        }
+    }
+
+    fn remove_position(seqs: &mut HashMap<NodeId, BTreeMap<Int, Vec<usize>>>, tag: &Option<Tag>, position: usize) {
+        if let Some(tag) = tag {
+            if let Some(by_seq) = seqs.get_mut(&tag.id) {
+                if let Some(positions) = by_seq.get_mut(&tag.seq) {
+                    positions.retain(|&p| p != position);
+                    if positions.is_empty() {
+                        by_seq.remove(&tag.seq);
+                    }
+                }
+            }
+        }
+    }
+
+    // Entries missing from a clock count as Int::max_value(), that is a
+    // record without SCD metadata has no clock entries.
+    fn raise_max_clocks(&mut self, old_cl: &[Int], new_cl: &[Int]) -> bool {
+        let entry = |cl: &[Int], idx: usize| cl.get(idx).cloned().unwrap_or(Int::max_value());
+        for idx in 0..cmp::max(old_cl.len(), new_cl.len()) {
+            let (old, new) = (entry(old_cl, idx), entry(new_cl, idx));
+            if new == Int::max_value() {
+                if old != Int::max_value() {
+                    return false;
+                }
+            } else {
+                if old != Int::max_value() && new < old {
+                    return false;
+                }
+                let max_clock = self.max_clocks.entry(idx as NodeId + 1).or_insert(0);
+                *max_clock = cmp::max(*max_clock, new);
+            }
+        }
+        true
+    }
+
+    fn add<V: Payload>(&mut self, position: usize, record: &Record<V>) {
+        if let Some(urb_tag) = &record.urb_tag {
+            self.urb_seqs.entry(urb_tag.id).or_default().entry(urb_tag.seq).or_default().push(position);
+        }
+        if let Some(meta) = &record.meta {
+            let sender = meta.tag.id;
+            self.scd_seqs.entry(sender).or_default().entry(meta.cl.get(sender)).or_default().push(position);
+            for (idx, &clock) in meta.cl.inner().iter().enumerate() {
+                if clock < Int::max_value() {
+                    let max_clock = self.max_clocks.entry(idx as NodeId + 1).or_insert(0);
+                    *max_clock = cmp::max(*max_clock, clock);
+                }
+            }
+        }
+    }
+}
+
+impl<V: Payload> MessageBuffer<V> {
+    pub fn new() -> MessageBuffer<V> {
+        MessageBuffer {
+            records: Vec::new(),
+            index: RefCell::new(None),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn get(&self, position: usize) -> Option<&Record<V>> {
+        self.records.get(position)
+    }
+
+    pub fn get_mut(&mut self, position: usize) -> Option<RecordMut<V>> {
+        let index = &self.index;
+        self.records.get_mut(position).map(|record| RecordMut::new(record, position, index))
+    }
+
+    pub fn iter(&self) -> slice::Iter<Record<V>> {
+        self.records.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<V> {
+        IterMut {
+            records: self.records.iter_mut().enumerate(),
+            index: &self.index,
+        }
+    }
+
+    pub fn push(&mut self, record: Record<V>) {
+        let position = self.records.len();
+        if let Some(index) = self.index.get_mut() {
+            index.add(position, &record);
+        }
+        self.records.push(record);
+    }
+
+    pub fn retain<F: FnMut(&Record<V>) -> bool>(&mut self, f: F) {
+        let len = self.records.len();
+        self.records.retain(f);
+        if self.records.len() != len {
+            *self.index.get_mut() = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self.index.get_mut() = None;
+        self.records.clear();
+    }
+
+    pub fn position_of_urb_tag(&self, urb_tag: &Tag) -> Option<usize> {
+        self.positions_of_urb_tag(urb_tag).first().cloned()
+    }
+
+    // All the records with the tag, there is more than one only if the
+    // buffer is corrupted.
+    pub fn positions_of_urb_tag(&self, urb_tag: &Tag) -> Vec<usize> {
+        self.with_index(|index| {
+            index.urb_seqs.get(&urb_tag.id).and_then(|seqs| seqs.get(&urb_tag.seq)).cloned().unwrap_or_default()
+        })
+    }
+
+    pub fn position_of_scd_tag(&self, scd_tag: &Tag) -> Option<usize> {
+        self.with_index(|index| {
+            index.scd_seqs.get(&scd_tag.id).and_then(|seqs| seqs.get(&scd_tag.seq)).and_then(|positions| positions.first().cloned())
+        })
+    }
+
+    // The largest URB seq sent by the node, 0 if there is none.
+    pub fn urb_max_seq(&self, node_id: NodeId) -> Int {
+        self.with_index(|index| {
+            let max_seq = index.urb_seqs.get(&node_id).and_then(|seqs| seqs.keys().next_back().cloned());
+            cmp::max(max_seq.unwrap_or(0), 0)
+        })
+    }
+
+    // The largest entry of the node in the clocks of the SCD records, 0 if
+    // there is none.
+    pub fn scd_max_clock(&self, node_id: NodeId) -> Int {
+        self.with_index(|index| index.max_clocks.get(&node_id).cloned().unwrap_or(0))
+    }
+
+    // The SCD seqs of the records sent by the node.
+    pub fn scd_seqs_of(&self, node_id: NodeId) -> HashSet<Int> {
+        self.with_index(|index| {
+            index.scd_seqs.get(&node_id).map(|seqs| seqs.keys().cloned().collect()).unwrap_or_default()
+        })
+    }
+
+    fn with_index<R, F: FnOnce(&BufferIndex) -> R>(&self, f: F) -> R {
+        let mut index = self.index.borrow_mut();
+        if index.is_none() {
+            let mut rebuilt = BufferIndex::default();
+            for (position, record) in self.records.iter().enumerate() {
+                rebuilt.add(position, record);
+            }
+            *index = Some(rebuilt);
+        }
+        f(index.as_ref().unwrap())
+    }
+}
+
+// What the index knows about a record.
+#[derive(PartialEq)]
+struct RecordKeys {
+    urb_tag: Option<Tag>,
+    scd_tag: Option<Tag>,
+    cl: Vec<Int>,
+}
+
+impl RecordKeys {
+    fn of<V: Payload>(record: &Record<V>) -> RecordKeys {
+        RecordKeys {
+            urb_tag: record.urb_tag.clone(),
+            scd_tag: record.meta.as_ref().map(|meta| Tag { id: meta.tag.id, seq: meta.cl.get(meta.tag.id) }),
+            cl: record.meta.as_ref().map(|meta| meta.cl.inner().clone()).unwrap_or_default(),
+        }
+    }
+}
+
+pub struct RecordMut<'a, V: Payload> {
+    record: &'a mut Record<V>,
+    position: usize,
+    index: &'a RefCell<Option<BufferIndex>>,
+    keys: RecordKeys,
+}
+
+impl<'a, V: Payload> RecordMut<'a, V> {
+    fn new(record: &'a mut Record<V>, position: usize, index: &'a RefCell<Option<BufferIndex>>) -> RecordMut<'a, V> {
+        let keys = RecordKeys::of(record);
+        RecordMut { record, position, index, keys }
+    }
+}
+
+impl<'a, V: Payload> Deref for RecordMut<'a, V> {
+    type Target = Record<V>;
+
+    fn deref(&self) -> &Record<V> {
+        self.record
+    }
+}
+
+impl<'a, V: Payload> DerefMut for RecordMut<'a, V> {
+    fn deref_mut(&mut self) -> &mut Record<V> {
+        self.record
+    }
+}
+
+impl<'a, V: Payload> Drop for RecordMut<'a, V> {
+    fn drop(&mut self) {
+        let keys = RecordKeys::of(self.record);
+        if keys == self.keys {
+            return;
+        }
+        let mut index = self.index.borrow_mut();
+        if let Some(buffer_index) = index.as_mut() {
+            if buffer_index.update(self.position, &self.keys, &keys) {
+                return;
+            }
+        }
+        *index = None;
+    }
+}
+
+pub struct IterMut<'a, V: Payload> {
+    records: iter::Enumerate<slice::IterMut<'a, Record<V>>>,
+    index: &'a RefCell<Option<BufferIndex>>,
+}
+
+impl<'a, V: Payload> Iterator for IterMut<'a, V> {
+    type Item = RecordMut<'a, V>;
+
+    fn next(&mut self) -> Option<RecordMut<'a, V>> {
+        let index = self.index;
+        self.records.next().map(|(position, record)| RecordMut::new(record, position, index))
+    }
+}
+
+impl<V: Payload> fmt::Debug for MessageBuffer<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.records.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bit_vec::BitVec;
+    use crate::scd::messages::SCDMETA;
+    use crate::scd::types::VectorClock;
+    use crate::merge::payload::AppMessage;
+
+    fn record(urb_tag: Option<Tag>, scd_tag: Option<Tag>) -> Record<AppMessage> {
+        BufferRecord {
+            urb_tag,
+            msg: Some(UrbPayload::MESSAGE(AppMessage::BROADCAST(String::from("m")))),
+            delivered: false,
+            recBy: BitVec::from_elem(3, false),
+            recBy_trusted: BitVec::from_elem(3, false),
+            prevHB: vec![-1; 3],
+            urb_needed: true,
+            scd_needed: true,
+            meta: scd_tag.map(|tag| {
+                let mut cl = VectorClock::new(3, Int::max_value());
+                cl.set(tag.id, tag.seq);
+                cl.set(3, 9);
+                SCDMETA { tag, cl, delivered: false, txDes: None, transmission_counter: 0 }
+            }),
+            creation_instant: None,
+        }
+    }
+
+    #[test]
+    fn test_index_follows_the_records() {
+        let mut buffer = MessageBuffer::new();
+        buffer.push(record(Some(Tag { id: 1, seq: 4 }), None));
+        buffer.push(record(None, Some(Tag { id: 2, seq: 7 })));
+        assert_eq!(buffer.position_of_urb_tag(&Tag { id: 1, seq: 4 }), Some(0));
+        assert_eq!(buffer.position_of_scd_tag(&Tag { id: 2, seq: 7 }), Some(1));
+        assert_eq!(buffer.scd_max_clock(3), 9);
+
+        // Pushed while the index is up to date, a duplicate keeps the first.
+        buffer.push(record(Some(Tag { id: 1, seq: 6 }), None));
+        buffer.push(record(Some(Tag { id: 1, seq: 4 }), None));
+        assert_eq!(buffer.urb_max_seq(1), 6);
+        assert_eq!(buffer.position_of_urb_tag(&Tag { id: 1, seq: 4 }), Some(0));
+        assert_eq!(buffer.positions_of_urb_tag(&Tag { id: 1, seq: 4 }), vec![0, 3]);
+
+        buffer.get_mut(1).unwrap().urb_tag = Some(Tag { id: 2, seq: 1 });
+        assert_eq!(buffer.position_of_urb_tag(&Tag { id: 2, seq: 1 }), Some(1));
+        buffer.get_mut(3).unwrap().urb_tag = None;
+        assert_eq!(buffer.positions_of_urb_tag(&Tag { id: 1, seq: 4 }), vec![0]);
+
+        for mut record in buffer.iter_mut() {
+            if let Some(meta) = record.meta.as_mut() {
+                meta.cl.set(1, 12);
+            }
+        }
+        assert_eq!(buffer.scd_max_clock(1), 12);
+        buffer.get_mut(1).unwrap().meta.as_mut().unwrap().cl.set(1, 3);
+        assert_eq!(buffer.scd_max_clock(1), 3);
+
+        buffer.retain(|record| record.urb_tag != Some(Tag { id: 1, seq: 4 }));
+        assert_eq!(buffer.position_of_urb_tag(&Tag { id: 1, seq: 4 }), None);
+        assert_eq!(buffer.position_of_scd_tag(&Tag { id: 2, seq: 7 }), Some(0));
+        assert_eq!(buffer.scd_seqs_of(2), vec![7].into_iter().collect());
+        assert_eq!(buffer.urb_max_seq(3), 0);
+    }
+}
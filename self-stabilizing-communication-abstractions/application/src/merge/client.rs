@@ -0,0 +1,137 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SendError};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use commons::types::{Int, Tag};
+use super::payload::{Payload, AppMessage};
+use super::readiness::WakingSender;
+
+pub type RequestId = u64;
+
+// What the client asks the node to do. BROADCAST is handled by the node
+// itself, with URB or SCD depending on the variant, everything else is
+// handed to the application.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(bound = "V: Payload")]
+pub enum ClientRequest<V = AppMessage> {
+    BROADCAST(V),
+    INCREASE,
+    DECREASE,
+    READ,
+    SNAPSHOT,
+    WRITE { r: Int, v: Int },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum ClientResponse {
+    // The tag the broadcast got, for INCREASE and DECREASE as well.
+    Broadcast(Tag),
+    Counter(Int),
+    Snapshot(HashMap<Int, Int>),
+    Written,
+    Error(ClientError),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum ClientError {
+    NoSpace,
+    Timeout,
+    // The application running on the node does not have this operation.
+    Unsupported,
+}
+
+// Numbers the requests sent to a node and hands back the responses with
+// the id of the request they answer.
+pub struct Client<V: Payload = AppMessage> {
+    requests: WakingSender<(RequestId, ClientRequest<V>)>,
+    responses: Receiver<(RequestId, ClientResponse)>,
+    next_id: RequestId,
+    // Responses received while waiting for another one.
+    pending: VecDeque<(RequestId, ClientResponse)>,
+}
+
+impl<V: Payload> Client<V> {
+    pub fn new(requests: WakingSender<(RequestId, ClientRequest<V>)>, responses: Receiver<(RequestId, ClientResponse)>) -> Self {
+        Client {
+            requests,
+            responses,
+            next_id: 1,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn send(&mut self, request: ClientRequest<V>) -> Result<RequestId, SendError<(RequestId, ClientRequest<V>)>> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.requests.send((id, request))?;
+        Ok(id)
+    }
+
+    // The next response, whichever request it answers.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<(RequestId, ClientResponse), ClientError> {
+        if let Some(response) = self.pending.pop_front() {
+            return Ok(response);
+        }
+        self.responses.recv_timeout(timeout).map_err(|_| ClientError::Timeout)
+    }
+
+    // Sends the request and waits for its response, the responses to
+    // other requests are kept for recv_timeout.
+    pub fn call(&mut self, request: ClientRequest<V>, timeout: Duration) -> (RequestId, ClientResponse) {
+        let id = match self.send(request) {
+            Ok(id) => id,
+            Err(_) => return (self.next_id - 1, ClientResponse::Error(ClientError::Timeout)),
+        };
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.responses.recv_timeout(left) {
+                Ok((response_id, response)) if response_id == id => return (id, response),
+                Ok(other) => self.pending.push_back(other),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                    return (id, ClientResponse::Error(ClientError::Timeout));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use crate::merge::readiness::Readiness;
+
+    #[test]
+    fn test_call_returns_the_response_to_its_own_request() {
+        let readiness = Readiness::new(Duration::from_millis(1));
+        let (request_send, request_recv) = mpsc::channel();
+        let (response_send, response_recv) = mpsc::channel();
+        let mut client = Client::new(readiness.waking_sender(request_send), response_recv);
+
+        let broadcast = client.send(ClientRequest::BROADCAST(AppMessage::BROADCAST("1".to_string()))).unwrap();
+        let node = thread::spawn(move || {
+            let mut answered = 0;
+            while let Ok((id, request)) = request_recv.recv() {
+                let response = match request {
+                    ClientRequest::READ => ClientResponse::Counter(3),
+                    _ => ClientResponse::Error(ClientError::NoSpace),
+                };
+                response_send.send((id, response)).unwrap();
+                answered += 1;
+                if answered == 2 {
+                    break;
+                }
+            }
+        });
+
+        let (read, response) = client.call(ClientRequest::READ, Duration::from_secs(5));
+        assert_eq!(response, ClientResponse::Counter(3));
+        assert_ne!(read, broadcast);
+        assert_eq!(client.recv_timeout(Duration::from_secs(5)), Ok((broadcast, ClientResponse::Error(ClientError::NoSpace))));
+        node.join().unwrap();
+    }
+}
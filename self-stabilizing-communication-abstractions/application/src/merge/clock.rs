@@ -0,0 +1,73 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+// Where a node reads the time for its latencies, throughputs, fail and
+// recovery times and for the timeouts of the phi detector and the
+// reassembly of fragments. Nodes run on the system clock, the simulator
+// gives them a virtual one.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    fn system_now(&self) -> SystemTime;
+
+    fn elapsed(&self, since: Instant) -> Duration {
+        self.now().saturating_duration_since(since)
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+// A clock that only moves when it is advanced. Its instants are offsets
+// from the instant it was created at and its system time starts at the
+// epoch, so that durations measured on it are the same in every run.
+#[derive(Clone)]
+pub struct VirtualClock {
+    origin: Instant,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl VirtualClock {
+    pub fn new() -> VirtualClock {
+        VirtualClock {
+            origin: Instant::now(),
+            elapsed: Arc::new(Mutex::new(Duration::from_secs(0))),
+        }
+    }
+
+    // The virtual time since the clock was created.
+    pub fn time(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+
+    // Time never goes backwards, earlier times are ignored.
+    pub fn advance_to(&self, time: Duration) {
+        let mut elapsed = self.elapsed.lock().unwrap();
+        if time > *elapsed {
+            *elapsed = time;
+        }
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.origin + self.time()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + self.time()
+    }
+}
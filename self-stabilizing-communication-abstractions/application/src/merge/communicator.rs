@@ -1,42 +1,82 @@
 use super::mergednode::MergedNode;
+use super::transport::Transport;
+use super::payload::Payload;
+use super::wire::WireMessage;
+use super::fragmentation::{MAX_DATAGRAM_SIZE, MAX_FRAGMENT_PAYLOAD, MAX_MESSAGE_SIZE, MessageTooLarge};
+use super::batching;
+use std::collections::BTreeMap;
+use std::mem;
 use commons::types::NodeId;
-use crate::urb::thetafd::json_is_ThetafdMessage;
-use crate::urb::messages::{Message, json_is_MSG_message, MSG, json_is_MSGAck_message, json_is_GOSSIP_message, GOSSIP, MSGAck};
-use crate::urb::hbfd::json_is_HbfdMessage;
+use crate::urb::messages::Message;
 use crate::merge::mergednode::MessageType;
-use crate::scd::messages::{json_is_SCDGOSSIP_message, SCDGOSSIP, json_is_FORWARD_message, FORWARD};
-use std::str;
 use crate::terminal_output::printlnu;
-use crate::settings::SETTINGS;
+use super::reset::ResetPhase;
 
-impl MergedNode {
+impl<T: Transport, V: Payload> MergedNode<T, V> {
     //Communicator
-    pub fn send_json_to(&self, json: &str, receiver_id: NodeId) {
-        let bytes = json.as_bytes();
-        let dst_socket_addr = self
-            .socket_addrs
-            .get(&receiver_id)
-            .expect("Could not find receiver among the socket addresses");
-        while let Err(e) = self.socket.send_to(bytes, dst_socket_addr) {
-            if SETTINGS.print_client_operations() {
-                printlnu(format!("Unable to send on socket, trying again. Err: {:?}",e));
+    // Messages are queued per destination and sent in batches at the end of
+    // each loop iteration by flush_outbox.
+    pub fn send_bytes_to(&mut self, bytes: Vec<u8>, receiver_id: NodeId) {
+        self.outbox.entry(receiver_id).or_insert_with(Vec::new).push(bytes);
+    }
+
+    pub(crate) fn flush_outbox(&mut self) {
+        let outbox = mem::replace(&mut self.outbox, BTreeMap::new());
+        for (receiver_id, messages) in outbox {
+            for batch in batching::pack(&messages, MAX_FRAGMENT_PAYLOAD) {
+                self.send_datagrams_to(&batch, receiver_id);
+            }
+        }
+    }
+
+    fn send_datagrams_to(&mut self, bytes: &[u8], receiver_id: NodeId) {
+        // Dropped like a lost datagram, resending it won't make it fit.
+        let datagrams = match self.fragmentation.split(self.node_id, bytes) {
+            Ok(datagrams) => datagrams,
+            Err(MessageTooLarge { size }) => {
+                printlnu(format!("Dropping a message of {} bytes to node {}, at most {} fit in the datagrams.", size, receiver_id, MAX_MESSAGE_SIZE));
+                return;
             }
+        };
+        for datagram in datagrams {
+            let datagram = match &self.authenticator {
+                Some(authenticator) => match authenticator.seal(&datagram, receiver_id) {
+                    Some(sealed) => sealed,
+                    None => {
+                        if self.config.print_client_operations {
+                            printlnu(format!("No link key for node {}, dropping datagram.", receiver_id));
+                        }
+                        continue;
+                    }
+                },
+                None => datagram,
+            };
+            self.run_result.bytes_sent += datagram.len() as u64;
+            self.transport.send_to(&datagram, receiver_id);
         }
     }
 
     pub fn recv_messages(&mut self) {
-        let mut buf = [0;100000];
-        loop {
-            match self.socket.recv(&mut buf) {
-                Ok(amt) => {
-                    let json_string = str::from_utf8(&buf[0..amt]).expect("Error converting bytes to utf8");
-                    self.json_received(json_string);
-                }
-                Err(e)=> {
-                    break;
+        let mut buf = [0;MAX_DATAGRAM_SIZE];
+        while let Some(amt) = self.transport.recv(&mut buf) {
+            self.run_result.bytes_received += amt as u64;
+            let datagram = match &self.authenticator {
+                Some(authenticator) => match authenticator.open(&buf[0..amt]) {
+                    Some(datagram) => datagram,
+                    None => {
+                        self.run_result.rejected_datagrams += 1;
+                        continue;
+                    }
+                },
+                None => &buf[0..amt],
+            };
+            if let Some(batch) = self.fragmentation.reassemble(datagram) {
+                for bytes in batching::unpack(&batch).unwrap_or_default() {
+                    self.wire_message_received(bytes);
                 }
             }
         }
+        self.fragmentation.age_out();
     }
 
     //
@@ -47,53 +87,44 @@ impl MergedNode {
         serde_json::to_string(message).expect("Could not serialize a message")
     }
 
-    pub fn send_json_message_to(&self, json: &str, receiver_id: NodeId) {
-        self.send_json_to(json, receiver_id);
+    pub fn send_wire_message_to(&mut self, message: &WireMessage<V>, receiver_id: NodeId) {
+        self.send_bytes_to(message.encode(self.config.encoding), receiver_id);
     }
 
-    pub fn json_received(&mut self, json: &str) {
-        if json_is_HbfdMessage(&json) {
-            if let Ok(hbfd_message) = serde_json::from_str(&json) {
-                return self.on_hb_hb(hbfd_message);
+    pub fn wire_message_received(&mut self, bytes: &[u8]) {
+        let message = match WireMessage::decode(bytes) {
+            Ok(message) => message,
+            Err(e) => {
+                if self.config.print_client_operations {
+                    printlnu(format!("Dropping undecodable message. Err: {:?}", e));
+                }
+                return;
             }
-        }
+        };
 
-        if json_is_ThetafdMessage(&json) {
-            if let Ok(thetafd_message) = serde_json::from_str(&json) {
-                return self.on_theta_hb(thetafd_message);
+        // Whatever was sent before the reset is dropped while resetting,
+        // except the gossip that tells how far the others are.
+        if self.reset_phase() == ResetPhase::RESET {
+            match &message {
+                WireMessage::MSG(_) | WireMessage::MSGAck(_) | WireMessage::FORWARD(_) | WireMessage::SCDGOSSIP(_) => return,
+                _ => {}
             }
         }
 
-        let mut msgs_buffer_txs = self.msgs_buffer_txs.as_mut();
-        match &mut msgs_buffer_txs {
-            Some(buffer_txs) => {
-                if json_is_MSG_message(&json) {
-                    if let Ok(MSG_message) = serde_json::from_str::<MSG<String>>(&json) {
-                        let _ = buffer_txs.get_mut(&MessageType::MSG).unwrap().get_mut(&MSG_message.sender).unwrap().send(json.to_owned());
-                        return;
-                    }
-                } else if json_is_MSGAck_message(&json) {
-                    if let Ok(MSGAck_message) = serde_json::from_str::<MSGAck>(&json) {
-                        let _ = buffer_txs.get_mut(&MessageType::MSGAck).unwrap().get_mut(&MSGAck_message.sender).unwrap().send(json.to_owned());
-                        return;
-                    }
-                } else if json_is_GOSSIP_message(&json) {
-                    if let Ok(GOSSIP_message) = serde_json::from_str::<GOSSIP>(&json) {
-                        let _ = buffer_txs.get_mut(&MessageType::GOSSIP).unwrap().get_mut(&GOSSIP_message.sender).unwrap().send(json.to_owned());
-                        return;
-                    }
-                } else if json_is_FORWARD_message(&json) {
-                    if let Ok(Forward_message) = serde_json::from_str::<FORWARD>(&json) {
-                        let _ = buffer_txs.get_mut(&MessageType::FORWARD).unwrap().get_mut(&Forward_message.msg_tag.id).unwrap().send(json.to_owned());
-                    }
-                } else if json_is_SCDGOSSIP_message(&json) {
-                    if let Ok(SCDGOSSIP_message) = serde_json::from_str::<SCDGOSSIP>(&json) {
-                        let _ = buffer_txs.get_mut(&MessageType::SCDGOSSIP).unwrap().get_mut(&SCDGOSSIP_message.sender).unwrap().send(json.to_owned());
-                        return;
-                    }
-                }
-            },
-            None => return,
+        let (msg_type, node_id) = match &message {
+            WireMessage::HbfdMessage(hbfd_message) => return self.on_hb_hb(hbfd_message),
+            WireMessage::ThetafdMessage(thetafd_message) => return self.on_theta_hb(thetafd_message),
+            WireMessage::MSG(MSG_message) => (MessageType::MSG, MSG_message.sender),
+            WireMessage::MSGAck(MSGAck_message) => (MessageType::MSGAck, MSGAck_message.sender),
+            WireMessage::GOSSIP(GOSSIP_message) => (MessageType::GOSSIP, GOSSIP_message.sender),
+            WireMessage::FORWARD(Forward_message) => (MessageType::FORWARD, Forward_message.msg_tag.id),
+            WireMessage::SCDGOSSIP(SCDGOSSIP_message) => (MessageType::SCDGOSSIP, SCDGOSSIP_message.sender),
+        };
+
+        if let Some(buffer_txs) = self.msgs_buffer_txs.as_mut() {
+            if let Some(tx) = buffer_txs.get_mut(&msg_type).unwrap().get_mut(&node_id) {
+                let _ = tx.send(message);
+            }
         }
     }
-}
\ No newline at end of file
+}
@@ -1,79 +1,93 @@
-use super::mergednode::MergedNode;
-use crate::urb::messages::Message;
-use crate::scd::algorithm6::messages::{PLUS, MINUS, json_is_MINUS_Message, json_is_PLUS_Message};
+use super::application::{ScdApplication, ScdContext};
+use super::client::{ClientRequest, ClientResponse, ClientError};
+use super::payload::AppMessage;
+use crate::scd::algorithm6::messages::{PLUS, MINUS};
 use crate::terminal_output::printlnu;
-use commons::types::Int;
-use crate::settings::SETTINGS;
-use std::time::Instant;
+use commons::types::{Int, Tag};
 
-impl MergedNode {
-    pub fn increase(&mut self) {
-//        scdBroadcast PLUS(i)
-        let message = PLUS { i: self.node_id };
+// The sequentially consistent counter.
+#[derive(Debug, Default)]
+pub struct Counter {
+    pub value: Int,
+}
 
-        let json_message = self.jsonify_message(&message);
+impl Counter {
+    pub fn new() -> Self {
+        Counter { value: 0 }
+    }
 
-        self.scd_broadcast(json_message);
+    pub fn increase(&mut self, scd: &mut dyn ScdContext<AppMessage>) -> Option<Tag> {
+//        scdBroadcast PLUS(i)
+        let message = AppMessage::PLUS(PLUS { i: scd.node_id() });
+
+        scd.broadcast(message)
     }
 
-    pub fn decrease(&mut self) {
+    pub fn decrease(&mut self, scd: &mut dyn ScdContext<AppMessage>) -> Option<Tag> {
 //        scdBroadcast MINUS(i)
-        let message = MINUS { i: self.node_id };
-
-        let json_message = self.jsonify_message(&message);
+        let message = AppMessage::MINUS(MINUS { i: scd.node_id() });
 
-        self.scd_broadcast(json_message);
+        scd.broadcast(message)
     }
 
-    pub fn read(&mut self) -> Option<Int> {
-//        allHaveTerminated();
-        //TODO change to condvar
-//        while !self.all_have_terminated(){
-//
-//        }
-//
-        let now = Instant::now();
+    pub fn read(&mut self, scd: &mut dyn ScdContext<AppMessage>) -> Int {
+        let now = scd.clock().now();
         printlnu(format!("-------------    Initiating counter read"));
-////        return (counter);
-//        if self.scd_all_have_terminated() {
-//            Some(self.counter)
-//        } else {
-//            self.is_reading = true;
-//            None
-//        }
-        self.wait_until_all_terminate();
-        self.run_result.read_latencies.as_mut().unwrap().push(now.elapsed().as_micros());
-        printlnu(format!("-------------    counter: {}", self.counter));
-        Some(self.counter)
+//        allHaveTerminated();
+        self.wait_for_all(scd);
+        let latency = scd.clock().elapsed(now).as_micros();
+        scd.run_result().read_latencies.as_mut().unwrap().push(latency);
+        printlnu(format!("-------------    counter: {}", self.value));
+//        return (counter);
+        self.value
+    }
+}
+
+impl ScdApplication<AppMessage> for Counter {
+    fn execute(&mut self, request: ClientRequest<AppMessage>, scd: &mut dyn ScdContext<AppMessage>) -> ClientResponse {
+        let tag = match request {
+            ClientRequest::INCREASE => self.increase(scd),
+            ClientRequest::DECREASE => self.decrease(scd),
+            ClientRequest::READ => return ClientResponse::Counter(self.read(scd)),
+            _ => return ClientResponse::Error(ClientError::Unsupported)
+        };
+        match tag {
+            Some(tag) => ClientResponse::Broadcast(tag),
+            None => ClientResponse::Error(ClientError::NoSpace)
+        }
     }
 
-    pub fn counter_received(&mut self, message: Vec<String>) {
+    fn on_scd_deliver(&mut self, batch: &[AppMessage]) {
         let mut k = 0;
         let mut l = 0;
-        for msg in message {
-            if json_is_PLUS_Message(&msg) {
-                if let Ok(plus_message) = serde_json::from_str::<PLUS>(&msg) {
-                    k += 1;
-                }
+        for msg in batch {
+            match msg {
+                AppMessage::PLUS(_) => k += 1,
+                AppMessage::MINUS(_) => l += 1,
+                _ => {}
             }
-
-            if json_is_MINUS_Message(&msg) {
-                if let Ok(minus_message) = serde_json::from_str::<MINUS>(&msg) {
-                    l += 1;
-                }
-            }
-
         }
-        self.counter = self.counter + k - l;
+        self.value = self.value + k - l;
     }
 
-    pub fn wait_until_all_terminate(&mut self) {
-        let mut has_terminated = false;
-        let mut iter = 0;
-        while !has_terminated {
-            self.bare_bone_loop_iter(iter % SETTINGS.delta() == 0);
-            has_terminated = self.scd_all_have_terminated();
-            iter += 1;
-        }
+    fn report(&self) -> Option<String> {
+        Some(format!("counter: {}", self.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delivered_batches_move_the_counter() {
+        let mut counter = Counter::new();
+        let plus = AppMessage::PLUS(PLUS { i: 1 });
+        let minus = AppMessage::MINUS(MINUS { i: 2 });
+
+        counter.on_scd_deliver(&[plus.clone(), plus.clone(), minus]);
+        counter.on_scd_deliver(&[plus, AppMessage::BROADCAST("1".to_string())]);
+
+        assert_eq!(counter.value, 2);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use mio::{Poll, Token};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use commons::types::NodeId;
+use super::transport::Transport;
+use super::clock::{self, Clock};
+
+// Datagrams held back for delaying or reordering beyond this are dropped,
+// just like a full queue in a router would.
+const MAX_HELD_DATAGRAMS: usize = 4096;
+
+// What can go wrong on a link. Delays are applied per datagram, the
+// jitter is drawn uniformly from [0, jitter] on top of the fixed delay.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LinkFaults {
+    pub drop_probability: f64,
+    pub duplicate_probability: f64,
+    pub reorder_window: usize,
+    pub delay: Duration,
+    pub jitter: Duration,
+}
+
+impl LinkFaults {
+    pub fn is_none(&self) -> bool {
+        *self == LinkFaults::default()
+    }
+
+    // Applies a spec such as "drop=0.1,duplicate=0.05,reorder=4,delay=20,jitter=5"
+    // on top of self. Delays are in milliseconds, keys that are left out keep
+    // their current value.
+    pub fn with_spec(mut self, spec: &str) -> Result<LinkFaults, String> {
+        for pair in spec.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let mut key_value = pair.splitn(2, '=');
+            let key = key_value.next().unwrap();
+            let value = key_value.next().ok_or(format!("Missing value for fault '{}'", key))?;
+            let invalid = || format!("Invalid value '{}' for fault '{}'", value, key);
+
+            match key {
+                "drop" => self.drop_probability = probability(value.parse().map_err(|_| invalid())?, key)?,
+                "duplicate" => self.duplicate_probability = probability(value.parse().map_err(|_| invalid())?, key)?,
+                "reorder" => self.reorder_window = value.parse().map_err(|_| invalid())?,
+                "delay" => self.delay = Duration::from_millis(value.parse().map_err(|_| invalid())?),
+                "jitter" => self.jitter = Duration::from_millis(value.parse().map_err(|_| invalid())?),
+                _ => return Err(format!("Unknown fault '{}'", key)),
+            }
+        }
+        Ok(self)
+    }
+}
+
+fn probability(value: f64, key: &str) -> Result<f64, String> {
+    if value >= 0.0 && value <= 1.0 {
+        Ok(value)
+    } else {
+        Err(format!("The probability for fault '{}' must be between 0 and 1", key))
+    }
+}
+
+impl FromStr for LinkFaults {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<LinkFaults, String> {
+        LinkFaults::default().with_spec(s)
+    }
+}
+
+struct HeldDatagram {
+    release_at: Instant,
+    receiver_id: NodeId,
+    bytes: Vec<u8>,
+}
+
+// Wraps another transport and drops, duplicates, delays and reorders the
+// datagrams it sends, so that the retransmission paths of URB and SCD can
+// be exercised on a single machine. All randomness comes from one seeded
+// generator which makes a run reproducible for a given seed.
+pub struct FaultyTransport<T: Transport> {
+    inner: T,
+    faults: LinkFaults,
+    link_faults: HashMap<NodeId, LinkFaults>,
+    rng: StdRng,
+    held: Vec<HeldDatagram>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<T: Transport> FaultyTransport<T> {
+    pub fn new(inner: T, faults: LinkFaults, link_faults: HashMap<NodeId, LinkFaults>, seed: u64) -> FaultyTransport<T> {
+        FaultyTransport {
+            inner,
+            faults,
+            link_faults,
+            rng: StdRng::seed_from_u64(seed),
+            held: Vec::new(),
+            clock: clock::system_clock(),
+        }
+    }
+
+    // Datagrams are held back on this clock instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> FaultyTransport<T> {
+        self.clock = clock;
+        self
+    }
+
+    fn faults_for(&self, receiver_id: NodeId) -> LinkFaults {
+        *self.link_faults.get(&receiver_id).unwrap_or(&self.faults)
+    }
+
+    fn hold(&mut self, bytes: &[u8], receiver_id: NodeId, faults: LinkFaults, now: Instant) {
+        if self.rng.gen::<f64>() < faults.drop_probability {
+            return;
+        }
+        let copies = if self.rng.gen::<f64>() < faults.duplicate_probability { 2 } else { 1 };
+
+        for _ in 0..copies {
+            if self.held.len() >= MAX_HELD_DATAGRAMS {
+                return;
+            }
+            let jitter = faults.jitter.mul_f64(self.rng.gen::<f64>());
+            self.held.push(HeldDatagram {
+                release_at: now + faults.delay + jitter,
+                receiver_id,
+                bytes: bytes.to_vec(),
+            });
+        }
+    }
+
+    // Sends every held datagram whose delay has passed. Datagrams to the
+    // same receiver are shuffled in groups of the reorder window, so none
+    // of them ends up further than that from its original position.
+    fn release_due(&mut self, now: Instant) {
+        if self.held.is_empty() {
+            return;
+        }
+        let (due, held): (Vec<HeldDatagram>, Vec<HeldDatagram>) = self.held
+            .drain(..)
+            .partition(|datagram| datagram.release_at <= now);
+        self.held = held;
+
+        // Ordered, so that the links draw from the generator in the same
+        // order in every run.
+        let mut due_per_link: BTreeMap<NodeId, Vec<HeldDatagram>> = BTreeMap::new();
+        for datagram in due {
+            due_per_link.entry(datagram.receiver_id).or_insert_with(Vec::new).push(datagram);
+        }
+        for (receiver_id, mut datagrams) in due_per_link {
+            let reorder_window = self.faults_for(receiver_id).reorder_window;
+            if reorder_window > 1 {
+                for window in datagrams.chunks_mut(reorder_window) {
+                    window.shuffle(&mut self.rng);
+                }
+            }
+            for datagram in datagrams {
+                self.inner.send_to(&datagram.bytes, receiver_id);
+            }
+        }
+    }
+}
+
+impl<T: Transport> Transport for FaultyTransport<T> {
+    fn send_to(&mut self, bytes: &[u8], receiver_id: NodeId) {
+        let faults = self.faults_for(receiver_id);
+        if faults.is_none() {
+            self.inner.send_to(bytes, receiver_id);
+        } else {
+            // Held datagrams are only released when receiving, which
+            // happens every iteration of the node loop. That way the whole
+            // batch sent in an iteration can be reordered.
+            let now = self.clock.now();
+            self.hold(bytes, receiver_id, faults, now);
+        }
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let now = self.clock.now();
+        self.release_due(now);
+        self.inner.recv(buf)
+    }
+
+    fn register(&self, poll: &Poll, token: Token) -> io::Result<()> {
+        self.inner.register(poll, token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::merge::transport::ChannelTransport;
+
+    fn received(transport: &mut ChannelTransport) -> Vec<Vec<u8>> {
+        let mut buf = [0; 16];
+        let mut datagrams = Vec::new();
+        while let Some(amt) = transport.recv(&mut buf) {
+            datagrams.push(buf[..amt].to_vec());
+        }
+        datagrams
+    }
+
+    #[test]
+    fn test_faults_are_applied_per_link() {
+        let node_ids: HashSet<NodeId> = vec![1, 2, 3].into_iter().collect();
+        let mut network = ChannelTransport::network(&node_ids);
+        let node1 = network.remove(&1).unwrap();
+        let mut node2 = network.remove(&2).unwrap();
+        let mut node3 = network.remove(&3).unwrap();
+
+        let faults: LinkFaults = "duplicate=1,delay=100".parse().unwrap();
+        let mut link_faults = HashMap::new();
+        link_faults.insert(3, faults.with_spec("drop=1").unwrap());
+        let mut faulty = FaultyTransport::new(node1, faults, link_faults, 7);
+
+        let now = Instant::now();
+        faulty.hold(b"to 2", 2, faulty.faults_for(2), now);
+        faulty.hold(b"to 3", 3, faulty.faults_for(3), now);
+
+        faulty.release_due(now);
+        assert!(received(&mut node2).is_empty());
+
+        faulty.release_due(now + Duration::from_millis(100));
+        assert_eq!(received(&mut node2), vec![b"to 2".to_vec(), b"to 2".to_vec()]);
+        assert!(received(&mut node3).is_empty());
+        assert!(faulty.held.is_empty());
+
+        assert!("drop=2".parse::<LinkFaults>().is_err());
+        assert!("lose=0.5".parse::<LinkFaults>().is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use commons::types::NodeId;
+use super::auth::AUTH_OVERHEAD;
+use super::clock::{self, Clock};
+
+// Largest datagram put on the wire, fragment header included.
+pub const MAX_DATAGRAM_SIZE: usize = 1400;
+
+// [sender: i32][msg_id: u32][index: u16][count: u16], big endian.
+const HEADER_LEN: usize = 12;
+// Room is always left for the authentication, whether it's used or not.
+pub const MAX_FRAGMENT_PAYLOAD: usize = MAX_DATAGRAM_SIZE - HEADER_LEN - AUTH_OVERHEAD;
+
+// Bounds that keep the reassembly table finite whatever headers we receive,
+// 1024 fragments is a bit more than 1.3 MB per message.
+const MAX_FRAGMENTS_PER_MESSAGE: u16 = 1024;
+const MAX_PARTIAL_MESSAGES: usize = 64;
+
+// Largest message that can be split into datagrams.
+pub const MAX_MESSAGE_SIZE: usize = MAX_FRAGMENTS_PER_MESSAGE as usize * MAX_FRAGMENT_PAYLOAD;
+
+#[derive(Debug, PartialEq)]
+pub struct MessageTooLarge {
+    pub size: usize,
+}
+
+// A partial message that has not received a new fragment for this long is
+// dropped. This is what removes corrupted or never completed entries.
+const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct PartialMessage {
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+    last_progress: Instant,
+}
+
+impl PartialMessage {
+    fn is_consistent(&self) -> bool {
+        self.fragments.len() > 1
+            && self.fragments.len() <= MAX_FRAGMENTS_PER_MESSAGE as usize
+            && self.received < self.fragments.len()
+            && self.received == self.fragments.iter().filter(|f| f.is_some()).count()
+    }
+}
+
+// Splits outgoing messages into datagrams and puts incoming ones back
+// together. Messages that fit in one datagram never touch the table.
+pub struct Fragmentation {
+    next_msg_id: u32,
+    partial_messages: HashMap<(NodeId, u32), PartialMessage>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Fragmentation {
+    pub fn new() -> Fragmentation {
+        Fragmentation::with_clock(clock::system_clock())
+    }
+
+    // Partial messages age on this clock.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Fragmentation {
+        Fragmentation {
+            next_msg_id: 0,
+            partial_messages: HashMap::new(),
+            clock,
+        }
+    }
+
+    pub fn split(&mut self, sender: NodeId, bytes: &[u8]) -> Result<Vec<Vec<u8>>, MessageTooLarge> {
+        if bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(MessageTooLarge { size: bytes.len() });
+        }
+        let msg_id = self.next_msg_id;
+        self.next_msg_id = self.next_msg_id.wrapping_add(1);
+
+        let chunks: Vec<&[u8]> = if bytes.is_empty() {
+            vec![bytes]
+        } else {
+            bytes.chunks(MAX_FRAGMENT_PAYLOAD).collect()
+        };
+
+        let count = chunks.len() as u16;
+        Ok(chunks.into_iter().enumerate().map(|(index, chunk)| {
+            let mut datagram = Vec::with_capacity(HEADER_LEN + chunk.len());
+            datagram.extend_from_slice(&sender.to_be_bytes());
+            datagram.extend_from_slice(&msg_id.to_be_bytes());
+            datagram.extend_from_slice(&(index as u16).to_be_bytes());
+            datagram.extend_from_slice(&count.to_be_bytes());
+            datagram.extend_from_slice(chunk);
+            datagram
+        }).collect())
+    }
+
+    // Returns the whole message once the last of its fragments is received.
+    pub fn reassemble<'a>(&mut self, datagram: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        if datagram.len() < HEADER_LEN {
+            return None;
+        }
+        let sender = NodeId::from_be_bytes(datagram[0..4].try_into().unwrap());
+        let msg_id = u32::from_be_bytes(datagram[4..8].try_into().unwrap());
+        let index = u16::from_be_bytes(datagram[8..10].try_into().unwrap()) as usize;
+        let count = u16::from_be_bytes(datagram[10..12].try_into().unwrap());
+        let payload = &datagram[HEADER_LEN..];
+
+        if count == 0 || count > MAX_FRAGMENTS_PER_MESSAGE || index >= count as usize {
+            return None;
+        }
+        if count == 1 {
+            return Some(Cow::Borrowed(payload));
+        }
+
+        let key = (sender, msg_id);
+        let is_stale = match self.partial_messages.get(&key) {
+            Some(partial) => partial.fragments.len() != count as usize || !partial.is_consistent(),
+            None => false,
+        };
+        if is_stale {
+            self.partial_messages.remove(&key);
+        }
+        if !self.partial_messages.contains_key(&key) {
+            self.make_room();
+            self.partial_messages.insert(key, PartialMessage {
+                fragments: vec![None; count as usize],
+                received: 0,
+                last_progress: self.clock.now(),
+            });
+        }
+
+        let partial = self.partial_messages.get_mut(&key).unwrap();
+        if partial.fragments[index].is_none() {
+            partial.fragments[index] = Some(payload.to_vec());
+            partial.received += 1;
+            partial.last_progress = self.clock.now();
+        }
+
+        if partial.received == partial.fragments.len() {
+            let partial = self.partial_messages.remove(&key).unwrap();
+            let message = partial.fragments.into_iter().flat_map(|f| f.unwrap()).collect();
+            return Some(Cow::Owned(message));
+        }
+        None
+    }
+
+    pub fn age_out(&mut self) {
+        self.age_out_at(self.clock.now());
+    }
+
+    fn age_out_at(&mut self, now: Instant) {
+        self.partial_messages.retain(|_, partial| {
+            partial.is_consistent()
+                && now.duration_since(partial.last_progress) < REASSEMBLY_TIMEOUT
+        });
+        while self.partial_messages.len() > MAX_PARTIAL_MESSAGES {
+            self.evict_oldest();
+        }
+    }
+
+    fn make_room(&mut self) {
+        while self.partial_messages.len() >= MAX_PARTIAL_MESSAGES {
+            self.evict_oldest();
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self.partial_messages.iter()
+            // On a virtual clock several entries can have the same age.
+            .min_by_key(|(key, partial)| (partial.last_progress, **key))
+            .map(|(key, _)| *key);
+        if let Some(key) = oldest {
+            self.partial_messages.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reassembles_out_of_order_fragments() {
+        let mut sender = Fragmentation::new();
+        let mut receiver = Fragmentation::new();
+        let message: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+
+        let mut fragments = sender.split(1, &message).unwrap();
+        assert_eq!(fragments.len(), 4);
+        assert!(fragments.iter().all(|f| f.len() + AUTH_OVERHEAD <= MAX_DATAGRAM_SIZE));
+
+        let last = fragments.remove(3);
+        fragments.reverse();
+        for fragment in fragments.iter() {
+            assert_eq!(receiver.reassemble(fragment), None);
+        }
+        // A duplicate must not complete the message early.
+        assert_eq!(receiver.reassemble(&fragments[0]), None);
+        assert_eq!(receiver.reassemble(&last).unwrap().into_owned(), message);
+        assert!(receiver.partial_messages.is_empty());
+    }
+
+    #[test]
+    fn test_partial_and_corrupted_entries_age_out() {
+        let mut sender = Fragmentation::new();
+        let mut receiver = Fragmentation::new();
+        let fragments = sender.split(1, &vec![7; 3000]).unwrap();
+        assert_eq!(receiver.reassemble(&fragments[0]), None);
+
+        receiver.partial_messages.insert((3, 9), PartialMessage {
+            fragments: vec![None; 2],
+            received: 5,
+            last_progress: Instant::now(),
+        });
+        receiver.age_out();
+        assert_eq!(receiver.partial_messages.len(), 1);
+
+        receiver.age_out_at(Instant::now() + REASSEMBLY_TIMEOUT);
+        assert!(receiver.partial_messages.is_empty());
+    }
+
+    #[test]
+    fn test_table_is_bounded() {
+        let mut sender = Fragmentation::new();
+        let mut receiver = Fragmentation::new();
+        for _ in 0..(MAX_PARTIAL_MESSAGES * 2) {
+            let fragments = sender.split(1, &vec![0; 2000]).unwrap();
+            receiver.reassemble(&fragments[0]);
+        }
+        assert_eq!(receiver.partial_messages.len(), MAX_PARTIAL_MESSAGES);
+    }
+
+    #[test]
+    fn test_too_large_message_is_rejected() {
+        let mut sender = Fragmentation::new();
+        assert_eq!(sender.split(1, &vec![0; MAX_MESSAGE_SIZE]).unwrap().len(), MAX_FRAGMENTS_PER_MESSAGE as usize);
+        assert_eq!(sender.split(1, &vec![0; MAX_MESSAGE_SIZE + 1]), Err(MessageTooLarge { size: MAX_MESSAGE_SIZE + 1 }));
+    }
+}
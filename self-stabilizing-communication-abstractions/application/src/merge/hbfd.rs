@@ -1,9 +1,12 @@
 use super::mergednode::MergedNode;
-use crate::terminal_output::printlnu;
+use super::transport::Transport;
+use super::payload::Payload;
+use super::wire::WireMessage;
 use commons::types::Int;
+use crate::failure_detector::{FailureDetector, Detector, FdEvent};
 use crate::urb::hbfd::HbfdMessage;
 
-impl MergedNode {
+impl<T: Transport, V: Payload> MergedNode<T, V> {
     //Hbfd
     pub(crate) fn hbfd_iter(&mut self) {
         for id in self.node_ids.clone() {
@@ -11,27 +14,31 @@ impl MergedNode {
         }
     }
 
-    pub fn get_hb(&self) -> Vec<Int> {
-        let hb = &self.hb;
-        let mut hb_vec = Vec::new();
-        for node_id in 1..(self.node_ids.len() + 1) {
-            hb_vec.push(*hb.get(&(node_id as Int)).unwrap())
+    // The heartbeat failure detector is only asked what it trusts when
+    // somebody listens to its events, nothing else depends on it.
+    pub(crate) fn publish_hb_events(&mut self) {
+        let trusted = self.hbfd.trusted();
+        if trusted != self.hb_trusted {
+            let events = FdEvent::changes(&self.hb_trusted, &trusted, Detector::HEARTBEAT, &self.hbfd.heartbeats());
+            self.fd_subscribers.publish(events);
+            self.hb_trusted = trusted;
         }
-        hb_vec
     }
 
-    pub fn on_hb_hb(&mut self, msg: HbfdMessage) {
-        let sender_id = msg.sender;
-        *self.hb.get_mut(&sender_id).unwrap() += 1;
+    pub fn get_hb(&self) -> Vec<Int> {
+        self.hbfd.heartbeats()
+    }
+
+    pub fn on_hb_hb(&mut self, msg: &HbfdMessage) {
+        self.hbfd.on_heartbeat(msg.sender);
     }
 
     fn send_hb_hb(&mut self, receiver_id: Int) {
         if receiver_id == self.node_id {
-            *self.hb.get_mut(&receiver_id).unwrap() += 1;
+            self.hbfd.on_heartbeat(receiver_id);
         } else {
-            let msg = HbfdMessage {sender:self.node_id};
-            let json_msg = serde_json::to_string(&msg).expect("Could not serialize a hb message");
-            self.send_json_to(&json_msg, receiver_id);
+            let msg = WireMessage::HbfdMessage(HbfdMessage {sender:self.node_id});
+            self.send_wire_message_to(&msg, receiver_id);
         }
     }
-}
\ No newline at end of file
+}
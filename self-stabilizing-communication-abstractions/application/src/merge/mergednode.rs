@@ -1,46 +1,59 @@
-use std::net::{UdpSocket, SocketAddr, IpAddr, Ipv4Addr};
-use std::collections::{HashMap, HashSet, VecDeque, BTreeSet};
+use std::collections::{HashMap, HashSet, VecDeque, BTreeSet, BTreeMap};
 use commons::types::{NodeId, Int, Tag};
-use crate::settings::SETTINGS;
 use std::sync::{Mutex, Condvar, MutexGuard, mpsc, Arc};
 use ring_channel::{RingSender, RingReceiver};
 use commons::{constants, arguments};
 use commons::run_result::RunResult;
-use crate::urb::thetafd::{ThetafdMessage, json_is_ThetafdMessage};
-use crate::urb::hbfd::{HbfdMessage, json_is_HbfdMessage};
+use crate::urb::thetafd::ThetafdMessage;
+use crate::urb::hbfd::HbfdMessage;
 use nix::sys::socket::send;
 use std::cmp::max;
 use std::{cmp, thread, fs};
-use crate::terminal_output::printlnu;
+use crate::terminal_output::{self, printlnu};
+use crate::config::NodeConfig;
 use commons::constants::WINDOW_SIZE;
 use std::sync::mpsc::{Sender, Receiver, TryRecvError};
 use std::time::{Duration, Instant, SystemTime};
 use crate::urb::types::BufferRecord;
-use crate::urb::messages::{MSG, GOSSIP, json_is_MSG_message, json_is_MSGAck_message, json_is_GOSSIP_message, MSGAck, Message};
+use crate::urb::messages::{MSG, GOSSIP, MSGAck, Message};
 use std::borrow::{Cow, Borrow};
 use ring_channel::*;
 use std::num::NonZeroUsize;
 use std::str;
 use crate::scd::types::{VectorClock, Entry};
-use crate::scd::messages::{SCDGOSSIP, SCDMETA, json_is_SCDGOSSIP_message, FORWARD, json_is_FORWARD_message};
+use crate::scd::messages::{SCDGOSSIP, SCDMETA, FORWARD};
 use std::rc::Rc;
 use std::ops::Deref;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hasher, Hash};
 use crate::scd::scd::SCD;
-use crate::merge::mergednode::StatusCode::ErrNoSpace;
 use std::process::id;
 use std::iter::FromIterator;
-use rand::{Rng, thread_rng};
+use rand::{Rng, SeedableRng, thread_rng};
 use rand::prelude::ThreadRng;
+use rand::rngs::StdRng;
 use std::path::Prefix::Verbatim;
 use commons::arguments::record_evaluation_info;
 use crate::scd::algorithm6::messages::{PLUS, MINUS};
 use commons::variant::Variant;
-use crate::merge::snapshot::Timestamp;
 use bit_vec::BitVec;
-
-#[derive(Hash, Eq, PartialEq)]
+use crate::merge::transport::{Transport, UdpTransport};
+use crate::merge::tcp_transport::TcpTransport;
+use crate::merge::faulty_transport::FaultyTransport;
+use commons::transport_kind::TransportKind;
+use crate::merge::readiness::{Readiness, WakingSender};
+use crate::merge::wire::WireMessage;
+use crate::failure_detector::{self, FailureDetector, HbDetector, FdEvent, FdSubscribers};
+use crate::merge::fragmentation::Fragmentation;
+use crate::merge::auth::LinkAuthenticator;
+use crate::merge::reset::GlobalReset;
+use crate::merge::payload::{Payload, AppMessage, UrbPayload};
+use crate::merge::buffer::MessageBuffer;
+use crate::merge::application::ScdApplication;
+use crate::merge::client::{RequestId, ClientRequest, ClientResponse, ClientError};
+use crate::merge::clock::{self, Clock};
+
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub enum MessageType {
     MSG,
     MSGAck,
@@ -49,29 +62,40 @@ pub enum MessageType {
     SCDGOSSIP,
 }
 
-pub struct MergedNode {
+pub struct MergedNode<T: Transport = UdpTransport, V: Payload = AppMessage> {
     pub node_id: NodeId,
-    pub node_ids: HashSet<NodeId>,
+    // The nodes, messages and outgoing datagrams are kept ordered, so that
+    // the same inputs make a node do the same thing in every run.
+    pub node_ids: BTreeSet<NodeId>,
+    pub config: NodeConfig,
+    pub clock: Arc<dyn Clock>,
+    // For the corruption of the self-stabilization test.
+    pub rng: StdRng,
 
     //Communicator
-    pub socket: UdpSocket,
-    pub socket_addrs: HashMap<NodeId, SocketAddr>,
+    pub transport: T,
+    pub outbox: BTreeMap<NodeId, Vec<Vec<u8>>>,
+    pub fragmentation: Fragmentation,
+    pub authenticator: Option<LinkAuthenticator>,
 
     // Theta
-    pub theta_vector: HashMap<NodeId, Int>,
+    pub failure_detector: Box<dyn FailureDetector>,
     pub current_trusted: BitVec,
 
     //Hbfd
-    pub hb: HashMap<NodeId, Int>,
+    pub hbfd: HbDetector,
+    pub hb_trusted: BitVec,
+
+    pub(crate) fd_subscribers: FdSubscribers,
 
     //Urb
     pub seq: Int,
-    pub buffer: Vec<BufferRecord<String>>,
+    pub buffer: MessageBuffer<V>,
     pub urb_rxObsS: Vec<Int>,
     pub urb_txObsS: Vec<Int>,
 
-    pub msgs_buffer_txs: Option<HashMap<MessageType, HashMap<NodeId, RingSender<String>>>>,
-    pub msgs_buffer_rxs: Option<HashMap<MessageType, HashMap<NodeId, RingReceiver<String>>>>,
+    pub msgs_buffer_txs: Option<BTreeMap<MessageType, BTreeMap<NodeId, RingSender<WireMessage<'static, V>>>>>,
+    pub msgs_buffer_rxs: Option<BTreeMap<MessageType, BTreeMap<NodeId, RingReceiver<WireMessage<'static, V>>>>>,
 
     pub next_to_deliver: Vec<Int>,
 
@@ -85,21 +109,23 @@ pub struct MergedNode {
     // Gossip
     pub gossip_sent: Vec<bool>,
 
+    // Reset
+    pub global_reset: GlobalReset,
+
     // Application
-    pub counter: Int,
-    pub is_reading: bool,
-    pub reg: HashMap<Int, Int>,
-    pub tsa: HashMap<Int,Timestamp>,
-    last_r: Int,
-    last_v: Int,
+    pub application: Option<Box<dyn ScdApplication<V>>>,
+    // Set while the application runs an operation, it gets these
+    // deliveries when it is back.
+    pub(crate) pending_deliveries: Option<Vec<Vec<V>>>,
 
     // Operations
-    from_application: Option<Receiver<String>>,
+    from_application: Option<Receiver<(RequestId, ClientRequest<V>)>>,
+    pub(crate) readiness: Readiness,
 
-    pub bcast_status: Option<Sender<StatusCode>>,
-
-    to_application: Mutex<Sender<String>>,
-    application_recv: Mutex<Receiver<String>>,
+    responses: Option<Sender<(RequestId, ClientResponse)>>,
+    finished: Option<Sender<()>>,
+    urb_deliveries: Option<Sender<(Tag, V)>>,
+    scd_deliveries: Option<Sender<Vec<(Tag, V)>>>,
 
     // Self-stabilization test
     pub(crate) has_failed: bool,
@@ -126,76 +152,115 @@ pub struct MergedNode {
 
 const ITERATIONS_UNTIL_FAIL: Int = 100;
 
-pub enum StatusCode {
-    Ok,
-    ResultReady,
-    ResultNotReady,
-    ErrNoSpace,
-    Finished,
+// The ends of the channels the application uses to talk to a running
+// node. Dropping a delivery receiver stops the node from sending to it.
+pub struct NodeHandle<V: Payload = AppMessage> {
+    pub stop: WakingSender<()>,
+    pub requests: WakingSender<(RequestId, ClientRequest<V>)>,
+    // The response to every request, tagged with the request id.
+    pub responses: Receiver<(RequestId, ClientResponse)>,
+    // Sent once the node has written its results and stopped.
+    pub finished: Receiver<()>,
+    // Every URB delivery, in delivery order.
+    pub urb_deliveries: Receiver<(Tag, V)>,
+    // The messages SCD delivers together, one batch per delivery.
+    pub scd_deliveries: Receiver<Vec<(Tag, V)>>,
 }
 
 
-impl MergedNode {
-    pub fn new(link_latencies: HashMap<i32, f64>) -> MergedNode {
+impl<V: Payload> MergedNode<FaultyTransport<Box<dyn Transport>>, V> {
+    pub fn new(config: NodeConfig, link_latencies: HashMap<i32, f64>) -> MergedNode<FaultyTransport<Box<dyn Transport>>, V> {
         // Setup sockets
-        let node_id = SETTINGS.node_id();
-        let socket_addrs = SETTINGS.socket_addrs().clone();
-        let node_ids : HashSet<NodeId> = socket_addrs.keys().map(|node_id| *node_id).collect();
+        let socket_addrs = config.socket_addrs.clone();
         printlnu(format!("socket addresses: {:?}", socket_addrs));
-        let port = socket_addrs.get(&node_id).expect("Could not found own socket address.").port();
-        let own_socket_addr = SocketAddr::new(
-            IpAddr::V4(Ipv4Addr::new(0,0,0,0)),
-            port,
-        );
-        let socket = UdpSocket::bind(own_socket_addr).expect("Could not create socket.");
-        let _ = socket.set_read_timeout(Some(Duration::from_millis(50)));
-        let _ = socket.set_write_timeout(Some(Duration::from_millis(200)));
-        let _ = socket.set_nonblocking(true);
-        let mut hashmap = HashMap::new();
-        let (app_send,app_recv) = mpsc::channel();
-        for node_id in node_ids.clone() {
-            hashmap.insert(node_id, 0);
+        let transport: Box<dyn Transport> = match config.transport {
+            TransportKind::UDP => Box::new(UdpTransport::bind(config.node_id, socket_addrs, config.print_client_operations)),
+            TransportKind::TCP => Box::new(TcpTransport::bind(config.node_id, socket_addrs)),
+        };
+        if config.has_faults() {
+            printlnu(format!("Injecting faults: {:?}, per link: {:?}", config.faults, config.link_faults));
         }
-        let vector = hashmap.clone();
-        let hb = hashmap;
+        // Every node gets its own stream of faults from the shared seed.
+        let seed = config.fault_seed.wrapping_add(config.node_id as u64);
+        let transport = FaultyTransport::new(transport, config.faults, config.link_faults.clone(), seed);
+        MergedNode::with_transport(config, transport, link_latencies)
+    }
+}
+
+impl<T: Transport, V: Payload> MergedNode<T, V> {
+    pub fn with_transport(config: NodeConfig, transport: T, link_latencies: HashMap<i32, f64>) -> MergedNode<T, V> {
+        MergedNode::with_clock(config, transport, clock::system_clock(), link_latencies)
+    }
+
+    // A node that reads the time from the given clock, like the ones of
+    // the simulator.
+    pub fn with_clock(config: NodeConfig, transport: T, clock: Arc<dyn Clock>, link_latencies: HashMap<i32, f64>) -> MergedNode<T, V> {
+        let node_id = config.node_id;
+        let node_ids : HashSet<NodeId> = config.socket_addrs.keys().map(|node_id| *node_id).collect();
+        let failure_detector = failure_detector::trust_detector(&config, clock.clone(), node_id, &node_ids);
         let current_trusted = BitVec::from_elem(node_ids.len(), true);
+        let hbfd = HbDetector::new(node_id, &node_ids, config.hbfd_w);
         let mut run_result = RunResult::new();
         run_result.link_latency = link_latencies;
         let number_of_nodes = node_ids.len();
+        let mut rng = StdRng::seed_from_u64(config.fault_seed.wrapping_add(node_id as u64));
+        let authenticator = if config.link_keys.is_empty() {
+            None
+        } else {
+            let mut without_key: Vec<NodeId> = node_ids.iter()
+                .filter(|id| **id != node_id && !config.link_keys.contains_key(id))
+                .cloned()
+                .collect();
+            if !without_key.is_empty() {
+                without_key.sort();
+                printlnu(format!("No link keys for nodes {:?}, nothing is sent to them.", without_key));
+            }
+            Some(LinkAuthenticator::new(node_id, config.link_keys.clone(), &mut rng))
+        };
+        let readiness = Readiness::new(config.tick_interval);
+        let global_reset = GlobalReset::new(number_of_nodes, config.max_int);
+        let start_time = clock.system_now();
+        readiness.register_transport(&transport);
         let mut node = MergedNode{
             node_id,
-            node_ids,
-            socket,
-            socket_addrs,
+            node_ids: node_ids.into_iter().collect(),
+            config,
+            clock: clock.clone(),
+            rng,
+            transport,
+            outbox: BTreeMap::new(),
+            fragmentation: Fragmentation::with_clock(clock),
+            authenticator,
             seq: 0,
-            buffer: Vec::new(),
+            buffer: MessageBuffer::new(),
             urb_rxObsS: vec![0; number_of_nodes],
             urb_txObsS: vec![0; number_of_nodes],
             msgs_buffer_txs: None,
             msgs_buffer_rxs: None,
             next_to_deliver: vec![1; number_of_nodes],
-            theta_vector: vector,
+            failure_detector,
             current_trusted,
-            hb,
+            hb_trusted: BitVec::from_elem(number_of_nodes, true),
+            fd_subscribers: FdSubscribers::new(),
+            hbfd,
             sn: 1,
             scd_rxObsS: vec![0; number_of_nodes],
             scd_txObsS: vec![0; number_of_nodes],
             scd_rxSpace: vec![None; number_of_nodes],
             scd_txSpace: vec![None; number_of_nodes],
             gossip_sent: vec![false; number_of_nodes],
-            counter: 0,
-            is_reading: false,
-            reg: HashMap::new(),
-            tsa: HashMap::new(),
-            last_r: 0,
-            last_v: 0,
+            global_reset,
+            application: None,
+            pending_deliveries: None,
             from_application: None,
-            bcast_status: None,
-            to_application: Mutex::new(app_send),
-            application_recv: Mutex::new(app_recv), // should probably just be passed along not a field
+            readiness,
+            responses: None,
+            finished: None,
+            urb_deliveries: None,
+            scd_deliveries: None,
             run_result: run_result,
             delivered_tags: BTreeSet::new(),
-            start_time: SystemTime::now(),
+            start_time,
             has_failed: false,
             has_seen_bot: false,
             sn_seen: HashSet::new(),
@@ -222,32 +287,33 @@ impl MergedNode {
         node
     }
 
-    pub fn start_the_do_forever_loop(mut node: Self) -> (Sender<()>, Sender<String>, Receiver<StatusCode>) {
-        let (stop_thread_tx, stop_thread_rx) = mpsc::channel();
-
-        let mut msgs_buffer_txs = HashMap::new();
-        let mut msgs_buffer_rxs = HashMap::new();
-
-        let mut MSG_txs = HashMap::new();
-        let mut MSGAck_txs = HashMap::new();
-        let mut Forward_txs = HashMap::new();
-        let mut GOSSIP_txs = HashMap::new();
-        let mut SCDGOSSIP_txs = HashMap::new();
-
-        let mut MSG_rxs = HashMap::new();
-        let mut MSGAck_rxs = HashMap::new();
-        let mut Forward_rxs = HashMap::new();
-        let mut GOSSIP_rxs = HashMap::new();
-        let mut SCDGOSSIP_rxs = HashMap::new();
-
-        for node_id in node.node_ids.clone() {
-            let (mut MSG_tx, mut MSG_rx) = ring_channel(NonZeroUsize::new(2 * node.urb_buffer_unit_size() as usize + 1).unwrap());
+    // The buffers the received messages wait in until the loop handles
+    // them, one per message type and sender. The do forever loop opens
+    // them, nodes driven by hand must do it before the first iteration.
+    pub fn open_message_buffers(&mut self) {
+        let mut msgs_buffer_txs = BTreeMap::new();
+        let mut msgs_buffer_rxs = BTreeMap::new();
+
+        let mut MSG_txs = BTreeMap::new();
+        let mut MSGAck_txs = BTreeMap::new();
+        let mut Forward_txs = BTreeMap::new();
+        let mut GOSSIP_txs = BTreeMap::new();
+        let mut SCDGOSSIP_txs = BTreeMap::new();
+
+        let mut MSG_rxs = BTreeMap::new();
+        let mut MSGAck_rxs = BTreeMap::new();
+        let mut Forward_rxs = BTreeMap::new();
+        let mut GOSSIP_rxs = BTreeMap::new();
+        let mut SCDGOSSIP_rxs = BTreeMap::new();
+
+        for node_id in self.node_ids.clone() {
+            let (mut MSG_tx, mut MSG_rx) = ring_channel(NonZeroUsize::new(2 * self.urb_buffer_unit_size() as usize + 1).unwrap());
             MSG_txs.insert(node_id, MSG_tx);
             MSG_rxs.insert(node_id, MSG_rx);
-            let (mut MSGAck_tx, mut MSGAck_rx) = ring_channel(NonZeroUsize::new(2 * node.urb_buffer_unit_size() as usize + 1).unwrap());
+            let (mut MSGAck_tx, mut MSGAck_rx) = ring_channel(NonZeroUsize::new(2 * self.urb_buffer_unit_size() as usize + 1).unwrap());
             MSGAck_txs.insert(node_id, MSGAck_tx);
             MSGAck_rxs.insert(node_id, MSGAck_rx);
-            let (mut Forward_tx, mut Forward_rx) = ring_channel(NonZeroUsize::new(2 * node.scd_buffer_unit_size() as usize + 1).unwrap());
+            let (mut Forward_tx, mut Forward_rx) = ring_channel(NonZeroUsize::new(2 * self.scd_buffer_unit_size() as usize + 1).unwrap());
             Forward_txs.insert(node_id, Forward_tx);
             Forward_rxs.insert(node_id, Forward_rx);
             let (mut GOSSIP_tx, mut GOSSIP_rx) = ring_channel(NonZeroUsize::new(1).unwrap());
@@ -270,20 +336,70 @@ impl MergedNode {
         msgs_buffer_rxs.insert(MessageType::GOSSIP, GOSSIP_rxs);
         msgs_buffer_rxs.insert(MessageType::SCDGOSSIP, SCDGOSSIP_rxs);
 
-        node.msgs_buffer_txs = Some(msgs_buffer_txs);
-        node.msgs_buffer_rxs = Some(msgs_buffer_rxs);
+        self.msgs_buffer_txs = Some(msgs_buffer_txs);
+        self.msgs_buffer_rxs = Some(msgs_buffer_rxs);
+    }
+
+    pub fn start_the_do_forever_loop(mut node: Self) -> NodeHandle<V>
+        where T: 'static {
+        let (stop_thread_tx, stop_thread_rx) = mpsc::channel();
+
+        node.open_message_buffers();
 
-        let (status_send, status_recv) = mpsc::channel();
+        let (responses_send, responses_recv) = mpsc::channel();
+        let (finished_send, finished_recv) = mpsc::channel();
         let (msg_send, msg_recv) = mpsc::channel();
+        let (urb_deliveries_send, urb_deliveries_recv) = mpsc::channel();
+        let (scd_deliveries_send, scd_deliveries_recv) = mpsc::channel();
 
         node.from_application = Some(msg_recv);
-        node.bcast_status = Some(status_send);
-        node.start_time = SystemTime::now();
+        node.responses = Some(responses_send);
+        node.finished = Some(finished_send);
+        node.urb_deliveries = Some(urb_deliveries_send);
+        node.scd_deliveries = Some(scd_deliveries_send);
+        node.start_time = node.clock.system_now();
+        let stop_thread_tx = node.readiness.waking_sender(stop_thread_tx);
+        let msg_send = node.readiness.waking_sender(msg_send);
+        let color = node.config.terminal_color;
         thread::spawn(move || {
+            terminal_output::set_node(node.node_id, color);
             node.do_forever_loop(stop_thread_rx);
         });
 
-        (stop_thread_tx, msg_send, status_recv)
+        NodeHandle {
+            stop: stop_thread_tx,
+            requests: msg_send,
+            responses: responses_recv,
+            finished: finished_recv,
+            urb_deliveries: urb_deliveries_recv,
+            scd_deliveries: scd_deliveries_recv,
+        }
+    }
+
+    // Every delivery is also sent to the returned receivers, like to the
+    // ones in the NodeHandle. For nodes driven by hand.
+    pub(crate) fn subscribe_deliveries(&mut self) -> (Receiver<(Tag, V)>, Receiver<Vec<(Tag, V)>>) {
+        let (urb_deliveries_send, urb_deliveries_recv) = mpsc::channel();
+        let (scd_deliveries_send, scd_deliveries_recv) = mpsc::channel();
+        self.urb_deliveries = Some(urb_deliveries_send);
+        self.scd_deliveries = Some(scd_deliveries_send);
+        (urb_deliveries_recv, scd_deliveries_recv)
+    }
+
+    pub(crate) fn deliver_urb_to_application(&mut self, tag: Tag, msg: V) {
+        if let Some(sender) = &self.urb_deliveries {
+            if sender.send((tag, msg)).is_err() {
+                self.urb_deliveries = None;
+            }
+        }
+    }
+
+    pub(crate) fn deliver_scd_to_application(&mut self, batch: Vec<(Tag, V)>) {
+        if let Some(sender) = &self.scd_deliveries {
+            if sender.send(batch).is_err() {
+                self.scd_deliveries = None;
+            }
+        }
     }
 
     // forever loop
@@ -332,17 +448,10 @@ impl MergedNode {
         let mut iterations = 0;
         let mut should_execute_self_stab_statement;
         loop {
-            if iterations > ITERATIONS_UNTIL_FAIL * self.node_id
-                && SETTINGS.is_failing_node() && !self.has_failed {
-                self.has_failed = true;
-                self.corrupt_variables();
-                self.fail_time = Some(Instant::now());
-//                self.duplicate_records();
-//                self.modify_records();
-//                self.modify_clocks();
-            }
+            self.fail_if_due(iterations);
 
-            should_execute_self_stab_statement = iterations % SETTINGS.delta() == 0;
+            self.readiness.wait();
+            should_execute_self_stab_statement = iterations % self.config.delta == 0;
             self.recv_operations();
             self.bare_bone_loop_iter(should_execute_self_stab_statement);
 //            self.handle_gossip_messages();
@@ -350,7 +459,7 @@ impl MergedNode {
             match rx.try_recv() {
                 Err(TryRecvError::Empty) => {}
                 _ => {
-                    let now = SystemTime::now();
+                    let now = self.clock.now();
                     let mut iter=0;
                     let mut init_num_of_tags = self.delivered_tags.len();
                     let min_iter = 200;
@@ -367,15 +476,16 @@ impl MergedNode {
                     'inner: loop {
                         let mut num_of_tags = cmp::max(self.delivered_tags.len(), 1);
                         init_num_of_tags = cmp::max(init_num_of_tags, num_of_tags);
-                        if SETTINGS.print_client_operations() || iter % 10 == 0 {
+                        if self.config.print_client_operations || iter % 10 == 0 {
                             printlnu(format!("Iter: {}, bufferlen: {}", iter, self.buffer.len()));
                         }
                         self.log(format!("Iter: {}, bufferlen: {}", iter, self.buffer.len()));
-                        should_execute_self_stab_statement = iter % SETTINGS.delta() == 0;
+                        self.readiness.wait();
+                        should_execute_self_stab_statement = iter % self.config.delta == 0;
                         self.bare_bone_loop_iter(should_execute_self_stab_statement);
                         buffer_lens[(iter % len_size) as usize] = self.buffer.len();
                         iter +=1;
-                        if is_zero(buffer_lens.clone()) && iter > min_iter || iter > cmp::max(5000, SETTINGS.delta() * 4) || now.elapsed().unwrap().as_secs() as Int >= 10 * 60 {
+                        if is_zero(buffer_lens.clone()) && iter > min_iter || iter > cmp::max(5000, self.config.delta * 4) || self.clock.elapsed(now).as_secs() as Int >= 10 * 60 {
                             printlnu(format!("Stopping ...  iter={}", iter));
                             break 'inner;
                         }
@@ -386,8 +496,8 @@ impl MergedNode {
                             }
                         }
                     }
-                    self.run_result.metadata.run_length = self.start_time.elapsed().unwrap().as_secs() as Int;//SETTINGS.run_length().as_secs() as Int;
-                    self.run_result.metadata.node_id = SETTINGS.node_id();
+                    self.run_result.metadata.run_length = self.clock.system_now().duration_since(self.start_time).unwrap_or_default().as_secs() as Int;//self.config.run_length.as_secs() as Int;
+                    self.run_result.metadata.node_id = self.config.node_id;
                     let mut hasher = DefaultHasher::new();
                     self.delivered_tags.hash(&mut hasher);
 
@@ -395,7 +505,7 @@ impl MergedNode {
                     let json = serde_json::to_string(&run_result).unwrap();
                     let latency = format!("{:?}", self.link_latency_pretty(run_result.link_latency));
                     self.log(format!("Stopping, delivered msgs length: {:?}, hash: {:?}, buffer len: {}, run time: {} secs",self.delivered_tags.len(), hasher.finish(), self.buffer.len(), self.run_result.metadata.run_length.clone()));
-                    printlnu(format!("Stopping, delivered msgs length: {:?}, hash: {:?}, buffer len: {}, run time: {} secs counter {}",self.delivered_tags.len(), hasher.finish(), self.buffer.len(), run_result.metadata.run_length.clone(), self.counter));
+                    printlnu(format!("Stopping, delivered msgs length: {:?}, hash: {:?}, buffer len: {}, run time: {} secs",self.delivered_tags.len(), hasher.finish(), self.buffer.len(), run_result.metadata.run_length.clone()));
                     self.log(format!("link latency {}",latency));
                     printlnu(format!("link latency {}",latency));
                     if let Some(latencies) = self.run_result.msg_latencies.clone() {
@@ -423,14 +533,13 @@ impl MergedNode {
                         printlnu(format!("Recovery time {:?} micros", rt));
                         self.log(format!("Recovery time {:?} micros", rt));
                     }
-                    if SETTINGS.variant() == Variant::SNAPSHOT {
+                    if let Some(report) = self.application.as_ref().and_then(|application| application.report()) {
                         if let Some(r_lats) = self.run_result.read_latencies.clone() {
-                            let s = self.reg_pretty();
-                            printlnu(format!("Number of reads: {}, Snapshot final registry: {}", r_lats.len(), s));
-                            self.log(format!("Number of reads: {}, Snapshot final registry: {}", r_lats.len(), s));
+                            printlnu(format!("Number of reads: {}, {}", r_lats.len(), report));
+                            self.log(format!("Number of reads: {}, {}", r_lats.len(), report));
                         }
                     }
-                    if SETTINGS.variant() != Variant::URB {
+                    if self.config.variant != Variant::URB {
                         let msp = self.scd_msp();
                         printlnu(format!("(scd) sn: {}, txObsS: {:?}, rxObsS: {:?}, rxSpace: {:?}, txSpace: {:?}, ms_p(i):{:?}", self.sn, self.scd_txObsS, self.scd_rxObsS, self.scd_rxSpace, self.scd_txSpace, msp));
                         self.log(format!("(scd) sn: {}, txObsS: {:?}, rxObsS: {:?}, rxSpace: {:?}, txSpace: {:?}", self.sn, self.scd_txObsS, self.scd_rxObsS, self.scd_rxSpace, self.scd_txSpace));
@@ -438,8 +547,8 @@ impl MergedNode {
                     let trusted = self.trusted();
                     printlnu(format!("trusted: {:?}", trusted));
                     self.log(format!("trusted: {:?}", trusted));
-                    printlnu(format!("theta: {:?}", self.theta_vector));
-                    self.log(format!("theta: {:?}", self.theta_vector));
+                    printlnu(format!("theta: {:?}", self.failure_detector.heartbeats()));
+                    self.log(format!("theta: {:?}", self.failure_detector.heartbeats()));
                     printlnu(format!("(urb) seq: {}, txObsS: {:?}, rxObsS: {:?}", self.seq, self.urb_txObsS, self.urb_rxObsS));
                     self.log(format!("(urb) seq: {}, txObsS: {:?}, rxObsS: {:?}", self.seq, self.urb_txObsS, self.urb_rxObsS));
                     printlnu(format!("has seen bot {}", self.has_seen_bot));
@@ -448,42 +557,72 @@ impl MergedNode {
                     if self.buffer.len() <= 20 {
                     }
                     self.log(format!("\n\n Buffer: {:?} \n\n", self.buffer));
-                    let socket_addrs = SETTINGS.socket_addrs().clone();
+                    let socket_addrs = self.config.socket_addrs.clone();
                     printlnu(format!("socket addresses: {:?}", socket_addrs));
                     self.log(format!("socket addresses: {:?}", socket_addrs));
                     fs::write(
-                        arguments::run_result_file_name_from_node_id(SETTINGS.node_id()),
+                        arguments::run_result_file_name_from_node_id(self.config.node_id),
                         json,
                     )
                         .expect("Could not write the json result file");
-                    let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::Finished);
+                    let _ = self.finished.as_ref().unwrap().send(());
                     break;
                 }
             }
         }
     }
 
+    // Failing nodes corrupt their variables once, after a number of
+    // iterations that differs per node.
+    pub(crate) fn fail_if_due(&mut self, iterations: Int) {
+        if iterations > ITERATIONS_UNTIL_FAIL * self.node_id
+            && self.config.is_failing_node && !self.has_failed {
+            self.has_failed = true;
+            self.corrupt_variables();
+            self.fail_time = Some(self.clock.now());
+//            self.duplicate_records();
+//            self.modify_records();
+//            self.modify_clocks();
+        }
+    }
+
     pub(crate) fn fd_iter(&mut self) {
         self.theta_iter();
         self.hbfd_iter();
+        // URB and SCD only ask for the trusted nodes when they need them,
+        // subscribers want to hear about changes right away.
+        if !self.fd_subscribers.is_empty() {
+            self.trusted();
+            self.publish_hb_events();
+        }
+    }
+
+    // Suspicions and recoveries from both failure detectors are sent to
+    // the returned receiver. Must be called before the do forever loop is
+    // started.
+    pub fn subscribe_fd_events(&mut self) -> Receiver<FdEvent> {
+        self.fd_subscribers.subscribe()
     }
     pub(crate) fn bare_bone_loop_iter(&mut self, should_exec_ss: bool) {
         self.gossip_sent = vec![false; self.node_ids.len()];
         self.recv_messages();
 
         self.fd_iter();
+        self.reset_iter();
 
         self.handle_gossip_messages();
         self.handle_received_msgs();
 
         self.urb_loop_iter(should_exec_ss);
 
-        match SETTINGS.variant() {
+        match self.config.variant {
             Variant::URB => {},
             _ => {
                 self.scd_loop_iter(should_exec_ss);
             }
         }
+
+        self.flush_outbox();
     }
 
 
@@ -491,7 +630,10 @@ impl MergedNode {
         let mut to_recv = VecDeque::new();
         let mut num_of_msgs = 1;
         loop {
-            match SETTINGS.variant() {
+            if self.is_resetting() {
+                break;
+            }
+            match self.config.variant {
                 Variant::COUNTER | Variant::SCD | Variant::SNAPSHOT => {
                     if !self.scd_available_space_for(num_of_msgs) {
                         break;
@@ -508,32 +650,15 @@ impl MergedNode {
                 Err(TryRecvError::Empty) => {
                     break;
                 }
-                Ok(msg) => {
-                    if msg == "COUNTER_INCREASE" {
-                        self.increase();
-                    } else if msg == "COUNTER_DECREASE" {
-                        self.decrease();
-                    } else if msg == "COUNTER_READ" {
-                        //read operation
-                        if let Some(result) = self.read() {
-                            let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::ResultReady);
-                            printlnu(format!("counter: {}", result));
-                            self.is_reading = false;
-                        }
-                    } else if msg == "SCD_BROADCAST" || msg == "URB_BROADCAST" {
-                        to_recv.push_back(msg);
-                        num_of_msgs += 1;
-                    } else if msg == "SNAPSHOT" {
-                        self.snapshot();
-
-                    } else if msg == "SNAPSHOT_WRITE" {
-                        self.snapshot_write(self.node_id + self.last_r, self.last_v+1);
-                        self.last_r += 1;
-                        self.last_v += 1;
-                    }
+                Ok((id, ClientRequest::BROADCAST(msg))) => {
+                    to_recv.push_back((id, msg));
+                    num_of_msgs += 1;
+                }
+                Ok((id, request)) => {
+                    self.execute_request(id, request);
                 }
                 Err(e) => {
-                    if !SETTINGS.record_evaluation_info() {
+                    if !self.config.record_evaluation_info {
                         panic!(format!("Error receiving from app: {:?}", e));
                     } else {
                         printlnu(format!("Error receiving from app: {:?}", e));
@@ -541,12 +666,19 @@ impl MergedNode {
                 }
             }
         }
-        while let Some(msg) = to_recv.pop_front() {
-            if SETTINGS.variant() == Variant::URB {
-                self.urb_broadcast(msg);
-            } else if SETTINGS.variant() == Variant::SCD {
-                self.scd_broadcast(msg);
-            }
+        while let Some((id, msg)) = to_recv.pop_front() {
+            let response = match self.config.variant {
+                Variant::URB => self.urb_broadcast(msg).map(ClientResponse::Broadcast),
+                Variant::SCD => self.scd_broadcast(msg).map(ClientResponse::Broadcast),
+                _ => Some(ClientResponse::Error(ClientError::Unsupported)),
+            };
+            self.respond(id, response.unwrap_or(ClientResponse::Error(ClientError::NoSpace)));
+        }
+    }
+
+    pub(crate) fn respond(&mut self, id: RequestId, response: ClientResponse) {
+        if let Some(responses) = self.responses.as_ref() {
+            let _ = responses.send((id, response));
         }
     }
 }
@@ -0,0 +1,44 @@
+use std::fmt::Debug;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::scd::messages::FORWARD;
+use crate::scd::algorithm5::messages::{SYNC, WRITE};
+use crate::scd::algorithm6::messages::{PLUS, MINUS};
+
+// What the applications broadcast. A payload is encoded once, as part of
+// the message carrying it, when it goes on the wire. Any type that can be
+// serialized is one.
+pub trait Payload: Clone + Debug + PartialEq + Serialize + DeserializeOwned + Send + Sync + 'static {}
+
+impl<T: Clone + Debug + PartialEq + Serialize + DeserializeOwned + Send + Sync + 'static> Payload for T {}
+
+// The payload of the applications that come with the node, the URB and
+// SCD benchmarks, the counter and the snapshot object.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum AppMessage {
+    BROADCAST(String),
+    PLUS(PLUS),
+    MINUS(MINUS),
+    SYNC(SYNC),
+    WRITE(WRITE),
+}
+
+// What URB carries. With the URB variant that is the broadcast payload
+// itself, with the SCD based ones it is the FORWARD of an SCD message.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(bound = "V: Payload")]
+pub enum UrbPayload<V> {
+    MESSAGE(V),
+    FORWARD(FORWARD<V>),
+}
+
+impl<V: Payload> UrbPayload<V> {
+    pub fn forward(&self) -> Option<&FORWARD<V>> {
+        match self {
+            UrbPayload::FORWARD(forward) => Some(forward),
+            UrbPayload::MESSAGE(_) => None,
+        }
+    }
+}
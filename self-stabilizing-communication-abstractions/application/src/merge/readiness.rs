@@ -0,0 +1,119 @@
+use std::sync::mpsc::{SendError, Sender};
+use std::time::{Duration, Instant};
+
+use mio::{Events, Poll, PollOpt, Ready, Registration, SetReadiness, Token};
+
+use super::transport::Transport;
+
+const TRANSPORT: Token = Token(0);
+const COMMANDS: Token = Token(1);
+
+// Sends to the do-forever loop and wakes it up if it is waiting for events.
+pub struct WakingSender<M> {
+    sender: Sender<M>,
+    set_readiness: SetReadiness,
+}
+
+impl<M> WakingSender<M> {
+    pub fn send(&self, message: M) -> Result<(), SendError<M>> {
+        self.sender.send(message)?;
+        let _ = self.set_readiness.set_readiness(Ready::readable());
+        Ok(())
+    }
+}
+
+impl<M> Clone for WakingSender<M> {
+    fn clone(&self) -> WakingSender<M> {
+        WakingSender {
+            sender: self.sender.clone(),
+            set_readiness: self.set_readiness.clone(),
+        }
+    }
+}
+
+// Decides when the do-forever loop runs its next iteration: when the
+// transport has something to read, when the application or the stop
+// signal sends something, or at the latest when the next tick is due. The
+// ticks are what keep the self-stabilizing parts (gossip, retransmissions,
+// failure detectors) going on a node that receives nothing.
+pub struct Readiness {
+    poll: Poll,
+    events: Events,
+    // Never read, but dropping it would deregister the commands.
+    _registration: Registration,
+    set_readiness: SetReadiness,
+    tick_interval: Duration,
+    last_tick: Instant,
+}
+
+impl Readiness {
+    pub fn new(tick_interval: Duration) -> Readiness {
+        let poll = Poll::new().expect("Could not create poll.");
+        let (registration, set_readiness) = Registration::new2();
+        poll.register(&registration, COMMANDS, Ready::readable(), PollOpt::edge())
+            .expect("Could not register the command readiness.");
+
+        Readiness {
+            poll,
+            events: Events::with_capacity(16),
+            _registration: registration,
+            set_readiness,
+            tick_interval,
+            last_tick: Instant::now(),
+        }
+    }
+
+    // Transports that can't be registered are only looked at every tick.
+    pub fn register_transport<T: Transport>(&self, transport: &T) {
+        transport.register(&self.poll, TRANSPORT)
+            .expect("Could not register the transport.");
+    }
+
+    pub fn waking_sender<M>(&self, sender: Sender<M>) -> WakingSender<M> {
+        WakingSender {
+            sender,
+            set_readiness: self.set_readiness.clone(),
+        }
+    }
+
+    // Blocks until something is ready or the next tick is due.
+    pub fn wait(&mut self) {
+        let timeout = self.tick_interval
+            .checked_sub(self.last_tick.elapsed())
+            .unwrap_or(Duration::from_secs(0));
+        let _ = self.poll.poll(&mut self.events, Some(timeout));
+
+        // The commands are drained by the iteration that follows, so
+        // anything sent after this point wakes us up again.
+        if self.events.iter().any(|event| event.token() == COMMANDS) {
+            let _ = self.set_readiness.set_readiness(Ready::empty());
+        }
+        if self.last_tick.elapsed() >= self.tick_interval {
+            self.last_tick = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn test_commands_wake_the_loop_before_the_tick() {
+        let mut readiness = Readiness::new(Duration::from_secs(10));
+        let (tx, rx) = mpsc::channel();
+        let sender = readiness.waking_sender(tx);
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            sender.send("SCD_BROADCAST").unwrap();
+        });
+
+        let start = Instant::now();
+        readiness.wait();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(rx.try_recv(), Ok("SCD_BROADCAST"));
+    }
+}
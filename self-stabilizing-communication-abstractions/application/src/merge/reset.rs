@@ -0,0 +1,378 @@
+use std::cmp;
+
+use bit_vec::BitVec;
+use serde::{Deserialize, Serialize};
+
+use commons::types::{Int, NodeId, Tag};
+use commons::variant::Variant;
+use super::mergednode::{MergedNode, MessageType};
+use super::transport::Transport;
+use super::payload::Payload;
+use crate::terminal_output::printlnu;
+use crate::failure_detector::FailureDetector;
+
+// A paused node waits this many iterations for its buffer to drain before
+// it resets anyway, a crashed node might never acknowledge what is left.
+const MAX_PAUSE_ITERATIONS: Int = 1000;
+
+// The phases of the global reset. A node that has a counter close to
+// MAXINT, or sees a trusted node pausing, pauses too and stops accepting
+// operations. Once every trusted node pauses or has reset, it resets its
+// counters, and once every trusted node has reset it resumes. Nodes only
+// ever wait for nodes one phase behind them, so the phases converge from
+// any state, just like the counters are assumed to.
+//
+// Every reset starts a new epoch, which is gossiped with the phase. The
+// links may reorder and duplicate gossip, so a PAUSE from the last epoch
+// can arrive after everyone is back in IDLE. Phases one step behind the
+// last one heard from a node are dropped for that reason.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ResetPhase {
+    IDLE,
+    PAUSE,
+    RESET,
+}
+
+impl ResetPhase {
+    // The order of the phases within an epoch, which starts with the reset.
+    fn rank(self) -> Int {
+        match self {
+            ResetPhase::RESET => 0,
+            ResetPhase::IDLE => 1,
+            ResetPhase::PAUSE => 2,
+        }
+    }
+}
+
+pub struct GlobalReset {
+    max_int: Int,
+    // The phase and epoch of every node as last gossiped, the own ones
+    // included.
+    phases: Vec<ResetPhase>,
+    epochs: Vec<Int>,
+    paused_iterations: Int,
+}
+
+impl GlobalReset {
+    pub fn new(number_of_nodes: usize, max_int: Int) -> GlobalReset {
+        GlobalReset {
+            max_int,
+            phases: vec![ResetPhase::IDLE; number_of_nodes],
+            epochs: vec![0; number_of_nodes],
+            paused_iterations: 0,
+        }
+    }
+
+    pub fn phase_of(&self, node_id: NodeId) -> ResetPhase {
+        self.phases[node_id as usize - 1]
+    }
+
+    pub fn epoch_of(&self, node_id: NodeId) -> Int {
+        self.epochs[node_id as usize - 1]
+    }
+
+    pub fn on_gossip(&mut self, sender_id: NodeId, epoch: Int, phase: ResetPhase) {
+        if sender_id < 1 || sender_id as usize > self.phases.len() {
+            return;
+        }
+        let idx = sender_id as usize - 1;
+        if Self::is_stale(self.epochs[idx], self.phases[idx], epoch, phase) {
+            return;
+        }
+        self.phases[idx] = phase;
+        self.epochs[idx] = epoch;
+    }
+
+    // Whether the gossiped phase comes before the known one, in the same
+    // epoch or the one before. Anything further off is taken as is, so that
+    // corrupted epochs are overwritten.
+    fn is_stale(known_epoch: Int, known_phase: ResetPhase, epoch: Int, phase: ResetPhase) -> bool {
+        match known_epoch.wrapping_sub(epoch) {
+            0 => phase.rank() < known_phase.rank(),
+            1 => true,
+            _ => false,
+        }
+    }
+
+    // Counters from this value on trigger a reset. The headroom is how far
+    // a counter can still move before the reset takes effect.
+    pub fn limit(&self, headroom: Int) -> Int {
+        cmp::max(self.max_int - headroom, self.max_int / 2)
+    }
+
+    // Advances the own phase and returns it if it changed.
+    pub fn step(&mut self, node_id: NodeId, trusted: &BitVec, near_max: bool, drained: bool) -> Option<ResetPhase> {
+        let phases = &self.phases;
+        let all_trusted_in = |allowed: &[ResetPhase]| {
+            (0..phases.len()).all(|idx| !trusted[idx] || allowed.contains(&phases[idx]))
+        };
+        let any_trusted_in = |phase: ResetPhase| {
+            (0..phases.len()).any(|idx| trusted[idx] && phases[idx] == phase)
+        };
+
+        let next = match self.phase_of(node_id) {
+            ResetPhase::IDLE if near_max || any_trusted_in(ResetPhase::PAUSE) => Some(ResetPhase::PAUSE),
+            ResetPhase::PAUSE if all_trusted_in(&[ResetPhase::PAUSE, ResetPhase::RESET])
+                && (drained || self.paused_iterations >= MAX_PAUSE_ITERATIONS) => Some(ResetPhase::RESET),
+            ResetPhase::RESET if all_trusted_in(&[ResetPhase::RESET, ResetPhase::IDLE]) => Some(ResetPhase::IDLE),
+            _ => None,
+        };
+
+        match next {
+            Some(phase) => {
+                if phase == ResetPhase::RESET {
+                    self.epochs[node_id as usize - 1] = self.epoch_of(node_id).wrapping_add(1);
+                }
+                self.phases[node_id as usize - 1] = phase;
+                self.paused_iterations = 0;
+            }
+            None => {
+                if self.phase_of(node_id) == ResetPhase::PAUSE {
+                    self.paused_iterations += 1;
+                }
+            }
+        }
+        next
+    }
+}
+
+impl<T: Transport, V: Payload> MergedNode<T, V> {
+    // Reset
+    pub(crate) fn reset_iter(&mut self) {
+        let near_max = self.counters_near_max();
+        let drained = self.buffer.iter().all(|record| record.delivered);
+        let trusted = self.trusted();
+        if let Some(phase) = self.global_reset.step(self.node_id, &trusted, near_max, drained) {
+            printlnu(format!("Global reset phase: {:?}, seq: {}, sn: {}", phase, self.seq, self.sn));
+            self.log(format!("Global reset phase: {:?}, seq: {}, sn: {}", phase, self.seq, self.sn));
+            if phase == ResetPhase::RESET {
+                self.reset_counters();
+            }
+        }
+    }
+
+    pub fn reset_phase(&self) -> ResetPhase {
+        self.global_reset.phase_of(self.node_id)
+    }
+
+    pub fn reset_epoch(&self) -> Int {
+        self.global_reset.epoch_of(self.node_id)
+    }
+
+    // No operations are accepted from the application during a reset.
+    pub fn is_resetting(&self) -> bool {
+        self.reset_phase() != ResetPhase::IDLE
+    }
+
+    fn counters_near_max(&self) -> bool {
+        let headroom = cmp::max(self.urb_buffer_unit_size(), self.scd_buffer_unit_size());
+        let limit = self.global_reset.limit(headroom);
+        let own_hb = self.hbfd.heartbeats()[self.node_id as usize - 1];
+
+        self.seq >= limit
+            || self.sn >= limit
+            || own_hb >= limit
+            || self.urb_txObsS.iter().any(|obs| *obs >= limit)
+            || self.scd_txObsS.iter().any(|obs| *obs >= limit)
+    }
+
+    // Everything that depends on the counters is dropped with them. The
+    // tags start over too, so the delivered ones are forgotten. The own
+    // broadcasts that were not delivered during the pause are lost, they
+    // are recorded in the run result. Messages received before the reset
+    // would bring back the old records, they are dropped unhandled.
+    fn reset_counters(&mut self) {
+        let number_of_nodes = self.node_ids.len();
+        let dropped = self.undelivered_own_tags();
+        if !dropped.is_empty() {
+            printlnu(format!("Global reset dropped {} undelivered broadcasts: {:?}", dropped.len(), dropped));
+            self.log(format!("Global reset dropped {} undelivered broadcasts: {:?}", dropped.len(), dropped));
+            self.run_result.dropped_by_reset.extend(dropped);
+        }
+        self.buffer.clear();
+        self.delivered_tags.clear();
+        self.seq = 0;
+        self.urb_rxObsS = vec![0; number_of_nodes];
+        self.urb_txObsS = vec![0; number_of_nodes];
+        self.next_to_deliver = vec![1; number_of_nodes];
+        self.sn = 1;
+        self.scd_rxObsS = vec![0; number_of_nodes];
+        self.scd_txObsS = vec![0; number_of_nodes];
+        self.scd_rxSpace = vec![None; number_of_nodes];
+        self.scd_txSpace = vec![None; number_of_nodes];
+        self.hbfd.reset();
+        self.drain_message_buffers();
+    }
+
+    fn drain_message_buffers(&mut self) {
+        if let Some(msgs_buffer_rxs) = self.msgs_buffer_rxs.as_mut() {
+            for (msg_type, rxs) in msgs_buffer_rxs.iter_mut() {
+                if let MessageType::MSG | MessageType::MSGAck | MessageType::FORWARD = msg_type {
+                    for rx in rxs.values_mut() {
+                        while rx.try_recv().is_ok() {}
+                    }
+                }
+            }
+        }
+    }
+
+    // The tags broadcasts of this node were accepted with.
+    fn undelivered_own_tags(&self) -> Vec<Tag> {
+        self.buffer.iter().filter_map(|record| {
+            match (self.config.variant, &record.meta) {
+                (Variant::URB, _) => record.urb_tag.as_ref()
+                    .filter(|tag| tag.id == self.node_id && !record.delivered),
+                (_, Some(meta)) => Some(&meta.tag)
+                    .filter(|tag| tag.id == self.node_id && !meta.delivered),
+                (_, None) => None,
+            }
+        }).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use crate::config::{NodeConfig, NodeBuilder};
+    use crate::merge::payload::AppMessage;
+    use crate::merge::transport::ChannelTransport;
+    use crate::merge::simulator::Simulation;
+    use crate::merge::wire::WireMessage;
+    use crate::urb::messages::MSGAck;
+
+    // Steps every node and gossips its phase to all others, node 2 is near
+    // MAXINT in the first round. Returns how often each node reset.
+    fn run_rounds(resets: &mut Vec<GlobalReset>, rounds: usize) -> Vec<Int> {
+        let trusted = BitVec::from_elem(resets.len(), true);
+        let mut reset_count = vec![0; resets.len()];
+        for round in 0..rounds {
+            for idx in 0..resets.len() {
+                let node_id = idx as NodeId + 1;
+                let near_max = round == 0 && node_id == 2;
+                if resets[idx].step(node_id, &trusted, near_max, true) == Some(ResetPhase::RESET) {
+                    reset_count[idx] += 1;
+                }
+                let phase = resets[idx].phase_of(node_id);
+                let epoch = resets[idx].epoch_of(node_id);
+                for other in resets.iter_mut() {
+                    other.on_gossip(node_id, epoch, phase);
+                }
+            }
+        }
+        reset_count
+    }
+
+    fn node() -> MergedNode<ChannelTransport, AppMessage> {
+        let socket_addrs: HashMap<NodeId, SocketAddr> = (1..=3)
+            .map(|node_id| (node_id, format!("127.0.0.1:{}", 63000 + node_id).parse().unwrap()))
+            .collect();
+        let node_ids: HashSet<NodeId> = socket_addrs.keys().cloned().collect();
+        let transport = ChannelTransport::network(&node_ids).remove(&1).unwrap();
+        let mut node = NodeBuilder::new(NodeConfig::new(1, socket_addrs)).build_with_transport(transport);
+        node.open_message_buffers();
+        node
+    }
+
+    #[test]
+    fn test_every_node_resets_once_and_resumes() {
+        let mut resets: Vec<GlobalReset> = (0..3).map(|_| GlobalReset::new(3, 1000)).collect();
+        assert_eq!(run_rounds(&mut resets, 10), vec![1, 1, 1]);
+        assert!((1..=3).all(|node_id| resets[0].phase_of(node_id) == ResetPhase::IDLE));
+        assert!((1..=3).all(|node_id| resets[0].epoch_of(node_id) == 1));
+        assert_eq!(resets[0].limit(100), 900);
+        assert_eq!(resets[0].limit(800), 500);
+    }
+
+    #[test]
+    fn test_stale_pause_does_not_restart_the_reset() {
+        let mut resets: Vec<GlobalReset> = (0..3).map(|_| GlobalReset::new(3, 1000)).collect();
+        run_rounds(&mut resets, 10);
+
+        // A PAUSE from the epoch that is over arrives late, or twice.
+        resets[0].on_gossip(2, 0, ResetPhase::PAUSE);
+        resets[0].on_gossip(2, 1, ResetPhase::RESET);
+        assert_eq!(resets[0].phase_of(2), ResetPhase::IDLE);
+        assert_eq!(resets[0].step(1, &BitVec::from_elem(3, true), false, true), None);
+
+        // A PAUSE of the current epoch starts the next reset.
+        resets[0].on_gossip(2, 1, ResetPhase::PAUSE);
+        assert_eq!(resets[0].step(1, &BitVec::from_elem(3, true), false, true), Some(ResetPhase::PAUSE));
+    }
+
+    #[test]
+    fn test_gossip_from_unknown_senders_is_ignored() {
+        let mut reset = GlobalReset::new(3, 1000);
+        for sender_id in vec![0, 4, -1, NodeId::max_value()] {
+            reset.on_gossip(sender_id, 0, ResetPhase::PAUSE);
+        }
+        assert!((1..=3).all(|node_id| reset.phase_of(node_id) == ResetPhase::IDLE));
+    }
+
+    #[test]
+    fn test_reset_forgets_the_delivered_tags() {
+        let mut node = node();
+        node.delivered_tags.insert(Tag { id: 2, seq: 1 });
+        node.reset_counters();
+        assert!(node.delivered_tags.is_empty());
+    }
+
+    #[test]
+    fn test_reset_records_the_broadcasts_it_drops() {
+        let mut node = node();
+        let tag = node.scd_broadcast(AppMessage::BROADCAST(String::from("lost"))).unwrap();
+        node.reset_counters();
+        assert_eq!(node.buffer.len(), 0);
+        assert_eq!(node.run_result.dropped_by_reset, vec![tag]);
+    }
+
+    #[test]
+    fn test_reset_drops_the_messages_received_before_it() {
+        let mut node = node();
+        let ack = MSGAck { sender: 2, tag: Tag { id: 1, seq: 7 }, recv_by: vec![] };
+        let txs = node.msgs_buffer_txs.as_mut().unwrap();
+        txs.get_mut(&MessageType::MSGAck).unwrap().get_mut(&2).unwrap().send(WireMessage::MSGAck(ack)).unwrap();
+        node.reset_counters();
+        let rxs = node.msgs_buffer_rxs.as_mut().unwrap();
+        assert!(rxs.get_mut(&MessageType::MSGAck).unwrap().get_mut(&2).unwrap().try_recv().is_err());
+    }
+
+    #[test]
+    fn test_cluster_resets_and_delivers_afterwards() {
+        // A small MAXINT, so that the heartbeat counters alone reach it
+        // within a second.
+        let configs = Simulation::<AppMessage>::configs(3).into_iter().map(|mut config| {
+            config.max_int = 1000;
+            config.window_size = 10;
+            config
+        }).collect();
+        let mut simulation: Simulation = Simulation::new(configs, 0);
+        for i in 0..5 {
+            simulation.node_mut(1).scd_broadcast(AppMessage::BROADCAST(i.to_string())).unwrap();
+        }
+
+        let reset = simulation.run_until(Duration::from_secs(10), |simulation| {
+            simulation.node_ids().iter().all(|node_id| {
+                let node = simulation.node(*node_id);
+                node.reset_epoch() >= 1 && node.reset_phase() == ResetPhase::IDLE
+            })
+        });
+        assert!(reset, "The cluster did not reset");
+
+        let delivered_before: Vec<usize> = simulation.node_ids().iter()
+            .map(|node_id| simulation.delivered(*node_id).len())
+            .collect();
+        let tags: Vec<Tag> = (0..5)
+            .map(|i| simulation.node_mut(1).scd_broadcast(AppMessage::BROADCAST(format!("after {}", i))).unwrap())
+            .collect();
+        let delivered = simulation.run_until(Duration::from_secs(10), |simulation| {
+            simulation.node_ids().iter().zip(&delivered_before).all(|(node_id, before)| {
+                let after = &simulation.delivered(*node_id)[*before..];
+                tags.iter().all(|tag| after.contains(tag))
+            })
+        });
+        assert!(delivered, "Not every node delivered {:?} after the reset", tags);
+    }
+}
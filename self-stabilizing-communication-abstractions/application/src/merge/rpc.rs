@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use commons::types::Tag;
+use crate::terminal_output::{self, printlnu};
+use super::client::{RequestId, ClientRequest, ClientResponse};
+use super::payload::Payload;
+use super::readiness::WakingSender;
+
+// Every frame is [length: u32, big endian][json], both ways.
+const LENGTH_PREFIX_LEN: usize = 4;
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+// A subscriber that can't take a delivery within this is disconnected,
+// so it doesn't hold up the others.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// What a local process sends to the node. The id of a CALL is chosen by
+// the caller and is only used to match the RESPONSE to it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(bound = "V: Payload")]
+pub enum RpcRequest<V> {
+    CALL { id: RequestId, request: ClientRequest<V> },
+    // Every delivery from now on is sent on the connection.
+    SUBSCRIBE,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(bound = "V: Payload")]
+pub enum RpcMessage<V> {
+    RESPONSE { id: RequestId, response: ClientResponse },
+    URBDELIVERY { tag: Tag, payload: V },
+    SCDDELIVERY(Vec<(Tag, V)>),
+}
+
+// Lets processes on the same host use the node the way main does, over
+// loopback TCP. Every connection gets its own reader thread, responses
+// and deliveries are written by the threads draining the node's channels.
+pub struct RpcServer {
+    local_addr: SocketAddr,
+}
+
+struct Connection {
+    stream: TcpStream,
+    subscribed: bool,
+}
+
+struct Connections {
+    next_connection: usize,
+    streams: HashMap<usize, Connection>,
+    // The node only sees ids handed out here, they map to the connection
+    // and the id the caller chose.
+    next_id: RequestId,
+    calls: HashMap<RequestId, (usize, RequestId)>,
+}
+
+impl RpcServer {
+    pub fn start<V: Payload>(
+        addr: SocketAddr,
+        requests: WakingSender<(RequestId, ClientRequest<V>)>,
+        responses: Receiver<(RequestId, ClientResponse)>,
+        urb_deliveries: Receiver<(Tag, V)>,
+        scd_deliveries: Receiver<Vec<(Tag, V)>>,
+    ) -> io::Result<RpcServer> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let connections = Arc::new(Mutex::new(Connections {
+            next_connection: 0,
+            streams: HashMap::new(),
+            next_id: 1,
+            calls: HashMap::new(),
+        }));
+
+        // The connection threads print as the node that started the server.
+        let node = terminal_output::node();
+        let accepting = Arc::clone(&connections);
+        thread::spawn(move || {
+            if let Some((node_id, color)) = node {
+                terminal_output::set_node(node_id, color);
+            }
+            accept_loop(listener, accepting, requests)
+        });
+
+        let responding = Arc::clone(&connections);
+        thread::spawn(move || {
+            for (id, response) in responses {
+                let mut connections = responding.lock().unwrap();
+                if let Some((connection, caller_id)) = connections.calls.remove(&id) {
+                    connections.send_to(connection, &RpcMessage::<V>::RESPONSE { id: caller_id, response });
+                }
+            }
+        });
+
+        let urb_subscribers = Arc::clone(&connections);
+        thread::spawn(move || {
+            for (tag, payload) in urb_deliveries {
+                urb_subscribers.lock().unwrap().send_to_subscribers(&RpcMessage::URBDELIVERY { tag, payload });
+            }
+        });
+
+        let scd_subscribers = connections;
+        thread::spawn(move || {
+            for batch in scd_deliveries {
+                scd_subscribers.lock().unwrap().send_to_subscribers(&RpcMessage::SCDDELIVERY(batch));
+            }
+        });
+
+        Ok(RpcServer { local_addr })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Connections {
+    fn send_to<M: Serialize>(&mut self, connection: usize, message: &M) {
+        let failed = match self.streams.get_mut(&connection) {
+            Some(Connection { stream, .. }) => write_frame(stream, message).is_err(),
+            None => false,
+        };
+        if failed {
+            self.streams.remove(&connection);
+        }
+    }
+
+    fn send_to_subscribers<M: Serialize>(&mut self, message: &M) {
+        let bytes = match serde_json::to_vec(message) {
+            Ok(bytes) => bytes,
+            Err(_) => return,
+        };
+        self.streams.retain(|_, connection| {
+            !connection.subscribed || write_frame_bytes(&mut connection.stream, &bytes).is_ok()
+        });
+    }
+}
+
+fn accept_loop<V: Payload>(listener: TcpListener, connections: Arc<Mutex<Connections>>, requests: WakingSender<(RequestId, ClientRequest<V>)>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                printlnu(format!("Could not accept an rpc connection: {:?}", e));
+                continue;
+            }
+        };
+        let writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(_) => continue,
+        };
+        let _ = writer.set_write_timeout(Some(WRITE_TIMEOUT));
+        let _ = writer.set_nodelay(true);
+
+        let connection = {
+            let mut connections = connections.lock().unwrap();
+            let connection = connections.next_connection;
+            connections.next_connection += 1;
+            connections.streams.insert(connection, Connection { stream: writer, subscribed: false });
+            connection
+        };
+
+        let connections = Arc::clone(&connections);
+        let requests = requests.clone();
+        let node = terminal_output::node();
+        thread::spawn(move || {
+            if let Some((node_id, color)) = node {
+                terminal_output::set_node(node_id, color);
+            }
+            read_loop(stream, connection, connections, requests)
+        });
+    }
+}
+
+fn read_loop<V: Payload>(mut stream: TcpStream, connection: usize, connections: Arc<Mutex<Connections>>, requests: WakingSender<(RequestId, ClientRequest<V>)>) {
+    loop {
+        match read_frame(&mut stream) {
+            Ok(RpcRequest::CALL { id: caller_id, request }) => {
+                let id = {
+                    let mut connections = connections.lock().unwrap();
+                    let id = connections.next_id;
+                    connections.next_id += 1;
+                    connections.calls.insert(id, (connection, caller_id));
+                    id
+                };
+                if requests.send((id, request)).is_err() {
+                    break;
+                }
+            }
+            Ok(RpcRequest::SUBSCRIBE) => {
+                if let Some(connection) = connections.lock().unwrap().streams.get_mut(&connection) {
+                    connection.subscribed = true;
+                }
+            }
+            Err(e) => {
+                if e.kind() != ErrorKind::UnexpectedEof {
+                    printlnu(format!("Closing rpc connection: {:?}", e));
+                }
+                break;
+            }
+        }
+    }
+    let mut connections = connections.lock().unwrap();
+    connections.streams.remove(&connection);
+    connections.calls.retain(|_, (call_connection, _)| *call_connection != connection);
+}
+
+pub fn write_frame<M: Serialize>(stream: &mut impl Write, message: &M) -> io::Result<()> {
+    let bytes = serde_json::to_vec(message).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+    write_frame_bytes(stream, &bytes)
+}
+
+fn write_frame_bytes(stream: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    let len: u32 = bytes.len().try_into().map_err(|_| io::Error::new(ErrorKind::InvalidInput, "frame too long"))?;
+    stream.write_all(&len.to_be_bytes())?;
+    stream.write_all(bytes)
+}
+
+pub fn read_frame<M: DeserializeOwned>(stream: &mut impl Read) -> io::Result<M> {
+    let mut len = [0; LENGTH_PREFIX_LEN];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(ErrorKind::InvalidData, "frame too long"));
+    }
+    let mut bytes = vec![0; len];
+    stream.read_exact(&mut bytes)?;
+    serde_json::from_slice(&bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::mpsc;
+    use crate::merge::payload::AppMessage;
+    use crate::merge::readiness::Readiness;
+
+    #[test]
+    fn test_calls_are_answered_with_the_callers_id_and_subscribers_get_deliveries() {
+        let readiness = Readiness::new(Duration::from_millis(1));
+        let (request_send, request_recv) = mpsc::channel();
+        let (response_send, response_recv) = mpsc::channel();
+        let (urb_send, urb_recv) = mpsc::channel();
+        let (_scd_send, scd_recv) = mpsc::channel::<Vec<(Tag, AppMessage)>>();
+        let server = RpcServer::start(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+            readiness.waking_sender(request_send),
+            response_recv,
+            urb_recv,
+            scd_recv,
+        ).unwrap();
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        let payload = AppMessage::BROADCAST("x".to_string());
+        write_frame(&mut stream, &RpcRequest::<AppMessage>::SUBSCRIBE).unwrap();
+        write_frame(&mut stream, &RpcRequest::CALL { id: 42, request: ClientRequest::BROADCAST(payload.clone()) }).unwrap();
+
+        let (id, request) = request_recv.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(request, ClientRequest::BROADCAST(payload.clone()));
+        let tag = Tag { id: 1, seq: 1 };
+        response_send.send((id, ClientResponse::Broadcast(tag.clone()))).unwrap();
+        let response: RpcMessage<AppMessage> = read_frame(&mut stream).unwrap();
+        assert_eq!(response, RpcMessage::RESPONSE { id: 42, response: ClientResponse::Broadcast(tag.clone()) });
+
+        urb_send.send((tag.clone(), payload.clone())).unwrap();
+        let delivery: RpcMessage<AppMessage> = read_frame(&mut stream).unwrap();
+        assert_eq!(delivery, RpcMessage::URBDELIVERY { tag, payload });
+    }
+}
@@ -1,22 +1,23 @@
 use super::mergednode::MergedNode;
+use super::transport::Transport;
+use super::payload::{Payload, UrbPayload};
+use super::wire::WireMessage;
 use commons::types::{Tag, Int, NodeId};
 use crate::terminal_output::printlnu;
 use std::cmp;
-use crate::merge::mergednode::StatusCode;
 use crate::scd::types::{VectorClock, Entry};
-use crate::scd::messages::{SCDMETA, FORWARD, SCDGOSSIP, json_is_FORWARD_message};
-use std::borrow::Cow;
+use crate::scd::messages::{SCDMETA, FORWARD, SCDGOSSIP};
 use std::collections::{HashSet, BTreeSet, HashMap, VecDeque};
 use crate::urb::types::BufferRecord;
 use std::rc::Rc;
 use std::cmp::max;
-use crate::settings::SETTINGS;
 use commons::variant::Variant;
 use bit_vec::BitVec;
 use std::time::Instant;
 use commons::constants::WINDOW_SIZE;
+use crate::merge::reset::ResetPhase;
 
-impl MergedNode {
+impl<T: Transport, V: Payload> MergedNode<T, V> {
     // SCD
     pub fn scd_loop_iter(&mut self, should_execute_self_stab_statement: bool) {
         if should_execute_self_stab_statement {
@@ -39,7 +40,7 @@ impl MergedNode {
     }
 
     pub fn scd_buffer_unit_size(&self) -> Int {
-        SETTINGS.window_size()
+        self.config.window_size
     }
 
 
@@ -58,59 +59,50 @@ impl MergedNode {
         r
     }
 
-    pub fn scd_broadcast(&mut self, msg: String) -> Option<Tag> {
-        if SETTINGS.print_client_operations() {
+    pub fn scd_broadcast(&mut self, msg: V) -> Option<Tag> {
+        if self.config.print_client_operations {
             printlnu(format!("Trying to scdBroadcast, sn: {:?}, rxObsS: {:?}, txObsS: {:?} txSpace {:?}", self.sn, self.scd_rxObsS, self.scd_txObsS, self.scd_txSpace));
         }
         self.log(format!("Trying to scdBroadcast, sn: {:?}, rxObsS: {:?}, txObsS: {:?}", self.sn, self.scd_rxObsS, self.scd_txObsS));
 
         if !self.scd_available_space() {
-            if SETTINGS.variant() == Variant::SCD {
-                let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::ErrNoSpace);
+            if self.config.variant == Variant::SCD {
                 return None;
             }
         }
         let sn = &self.sn;
         let msg_tag = Tag {id: self.node_id, seq: *sn};
 
-        if SETTINGS.print_client_operations() {
+        if self.config.print_client_operations {
         printlnu(format!("scdBroadcasting: {:?}, sn: {:?}, rxObsS: {:?}, txObsS: {:?} txSpace {:?}", msg_tag, self.sn, self.scd_rxObsS, self.scd_txObsS, self.scd_txSpace));
         }
         self.log(format!("scdBroadcasting: {:?}, sn: {:?}, rxObsS: {:?}, txObsS: {:?}", msg_tag, self.sn, self.scd_rxObsS, self.scd_txObsS));
 
-        let r = self.forward(msg, msg_tag.clone(), msg_tag, None);
-        if SETTINGS.variant() == Variant::SCD || SETTINGS.variant() == Variant::COUNTER {
-            let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::Ok);
-        }
-        r
+        self.forward(msg, msg_tag.clone(), msg_tag, None)
     }
 
-    pub fn scd_broadcast_eventually(&mut self, msg: String) -> Option<Tag> {
-        if SETTINGS.print_client_operations() {
+    pub fn scd_broadcast_eventually(&mut self, msg: V) -> Option<Tag> {
+        if self.config.print_client_operations {
             printlnu(format!("Trying to scdBroadcast, sn: {:?}, rxObsS: {:?}, txObsS: {:?}", self.sn, self.scd_rxObsS, self.scd_txObsS));
         }
         self.log(format!("Trying to scdBroadcast eventually, sn: {:?}, rxObsS: {:?}, txObsS: {:?}", self.sn, self.scd_rxObsS, self.scd_txObsS));
 
         if !self.scd_available_space() {
-            if SETTINGS.variant() == Variant::SCD {
-                let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::ErrNoSpace);
+            if self.config.variant == Variant::SCD {
                 return None;
             } else {
                 let mut iter = 0;
                 while !self.scd_available_space() {
-                    self.bare_bone_loop_iter(iter % SETTINGS.delta() == 0);
+                    self.readiness.wait();
+                    self.bare_bone_loop_iter(iter % self.config.delta == 0);
                     iter += 1;
                 }
             }
-        } else {
-            if SETTINGS.variant() == Variant::SCD {
-                let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::Ok);
-            }
         }
         let sn = &self.sn;
         let msg_tag = Tag {id: self.node_id, seq: *sn};
 
-        if SETTINGS.print_client_operations() {
+        if self.config.print_client_operations {
             printlnu(format!("scdBroadcasting: {:?}, sn: {:?}, rxObsS: {:?}, txObsS: {:?}", msg_tag, self.sn, self.scd_rxObsS, self.scd_txObsS));
         }
         self.log(format!("scdBroadcasting: {:?}, sn: {:?}, rxObsS: {:?}, txObsS: {:?}", msg_tag, self.sn, self.scd_rxObsS, self.scd_txObsS));
@@ -118,38 +110,26 @@ impl MergedNode {
         self.forward(msg, msg_tag.clone(), msg_tag, None)
     }
 
-    pub fn scd_deliver(&mut self, msgs: Vec<String>) {
+    pub fn scd_deliver(&mut self, msgs: Vec<V>) {
 
-        if SETTINGS.print_client_operations() {
+        if self.config.print_client_operations {
             printlnu(format!("scdDelivered: {:?}, sn: {}, rxObsS: {:?}, txObsS: {:?}", msgs, self.sn, self.scd_rxObsS, self.scd_txObsS));
         }
         self.log(format!("scdDelivered: {:?}, sn: {}, rxObsS: {:?}, txObsS: {:?}", msgs, self.sn, self.scd_rxObsS, self.scd_txObsS));
 
-        match SETTINGS.variant() {
-            Variant::URB => {
-                panic!("this should not happen")
-            },
-            Variant::SCD => {},
-            Variant::COUNTER => {
-                self.counter_received(msgs);
-            },
-            Variant::SNAPSHOT => {
-                self.snapshot_msg_received(msgs);
-            }
-        }
-
+        self.deliver_to_scd_application(msgs);
     }
 
-    pub fn forward(&mut self, msg: String, msg_tag: Tag, forward_tag: Tag, cl: Option<VectorClock>) -> Option<Tag> {
+    pub fn forward(&mut self, msg: V, msg_tag: Tag, forward_tag: Tag, cl: Option<VectorClock>) -> Option<Tag> {
         match self.get_urb_index(&msg_tag) {
             Some(index) => {
-                if SETTINGS.print_client_operations() {
+                if self.config.print_client_operations {
                         printlnu(format!("debug: Some clause msg_tag: {:?} cl {:?}", msg_tag, cl));
                 }
 //                printlnu(format!("index: {} urb_tag {:?} buffer {:?}", index, urb_tag, self.buffer));
                 let mut entry = self.buffer.get_mut(index as usize).unwrap();
-                if let Some(mut scdMeta) = MergedNode::parse_meta(&entry.meta) {
-                    let mut parsed_msg = MergedNode::parse_forward_msg(&entry.msg).unwrap();
+                let entry = &mut *entry;
+                if let Some(scdMeta) = entry.meta.as_mut() {
                     scdMeta.cl.set(forward_tag.id, forward_tag.seq);
                     if cl.is_some() {
                         let vc = cl.unwrap();
@@ -161,15 +141,13 @@ impl MergedNode {
                         }
                     }
 //                    self.set_scd_meta(&urb_tag, scdMeta);
-                    parsed_msg.cl = scdMeta.cl.clone();
-                    let new_parsed_msg = serde_json::to_string(&parsed_msg).unwrap();
-                    let meta_s = serde_json::to_string(&scdMeta).unwrap();
-                    entry.meta = Some(meta_s);
-                    entry.msg = Some(new_parsed_msg);
+                    if let Some(UrbPayload::FORWARD(forward_msg)) = entry.msg.as_mut() {
+                        forward_msg.cl = scdMeta.cl.clone();
+                    }
                 }
             }
             None => {
-                if SETTINGS.print_client_operations() {
+                if self.config.print_client_operations {
                 }
                 if msg_tag.seq > self.scd_rxObsS[msg_tag.id as usize - 1] {
                     let mut threshold = VectorClock::new(self.node_ids.len(), Int::max_value());
@@ -185,7 +163,7 @@ impl MergedNode {
                     };
 
                     let mut forward_msg = FORWARD {
-                        msg: Cow::Borrowed(&msg),
+                        msg,
                         msg_tag: msg_tag.clone(),
                         forward_tag: Tag { id: self.node_id, seq: self.sn },
                         cl: threshold
@@ -199,17 +177,17 @@ impl MergedNode {
                     let number_of_nodes = self.node_ids.len();
                     let buffer_record = BufferRecord {
                         urb_tag: None,
-                        msg: Some(self.jsonify_message(&forward_msg)),
+                        msg: Some(UrbPayload::FORWARD(forward_msg)),
                         delivered: false,
                         recBy: recBy,
                         recBy_trusted: recBy_trusted,
                         prevHB: vec![-1; number_of_nodes],
                         urb_needed: true,
                         scd_needed: true,
-                        meta: Some(serde_json::to_string(&meta).unwrap()),
+                        meta: Some(meta),
                         creation_instant:
                             if msg_tag.id == self.node_id {
-                                Some(Instant::now())
+                                Some(self.clock.now())
                             } else {
                                 None
                             }
@@ -220,14 +198,14 @@ impl MergedNode {
 //                    printlnu(format!("inserted msg to buffer entry: {:?}", self.buffer.get(&urb_tag).unwrap()));
                     self.log(format!("Creating scd record: {:?}", buffer_record));
 
-                    if SETTINGS.print_client_operations() {
+                    if self.config.print_client_operations {
                     printlnu(format!("Creating scd record: {:?}, rx: {:?} tx: {:?}", msg_tag.clone(), self.scd_rxObsS, self.scd_txObsS));
                     }
                     self.sn_seen.insert(self.sn.clone());
                     self.sn += 1;
                     return Some(Tag { id: self.node_id, seq: self.sn - 1 });
                 } else {
-                    if SETTINGS.print_client_operations() {
+                    if self.config.print_client_operations {
                         printlnu(format!("Ignoring msg: {:?}, rx: {:?}, tx: {:?}, rxObsS[f]={}, sF={}, sn={}", msg_tag, self.scd_rxObsS, self.scd_txObsS, self.scd_rxObsS[forward_tag.id as usize - 1], forward_tag.seq, self.sn));
                     }
                     self.log(format!("Ignoring msg: {:?}, rx: {:?}, tx: {:?}", msg_tag, self.scd_rxObsS, self.scd_txObsS));
@@ -246,12 +224,12 @@ impl MergedNode {
 
         for record in self.buffer.iter() {
             if record.delivered || record.urb_tag.is_none() {
-                if let Some(scd_meta) = MergedNode::parse_meta(&record.meta) {
+                if let Some(scd_meta) = &record.meta {
                     //printlnu(format!("Testing entry: {:?}", scd_entry));
                     if !scd_meta.delivered {
                         let msg = record.msg.as_ref().unwrap().clone();
 
-                        let (scd_tag, cl, delivered) = (scd_meta.tag, scd_meta.cl, scd_meta.delivered);
+                        let (scd_tag, cl, delivered) = (scd_meta.tag.clone(), scd_meta.cl.clone(), scd_meta.delivered);
                         let (cl_clone, delivered_clone) = (cl.clone(), delivered.clone());
                         let urb_clone;
                         if record.urb_tag.is_some() {
@@ -306,26 +284,28 @@ impl MergedNode {
             }
         }
         let mut msgs_to_deliver = Vec::new();
+        let mut tagged_msgs_to_deliver = Vec::new();
         let mut msgs_to_deliver_info = Vec::new();
         let buf_size = self.buffer.len() as i32;
         let low_size = cmp::max((self.scd_buffer_unit_size() as f32 * 0.3) as i32, 0);
         let high_size = cmp::max((self.scd_buffer_unit_size() as f32 * 0.5) as i32, 1);
 
         if buf_size >= high_size && self.throughput_instant.is_none() {
-            self.throughput_instant = Some(Instant::now());
+            self.throughput_instant = Some(self.clock.now());
             self.throughput_msgs = Some(Vec::new());
         }
 
         for index in &index_to_deliver {
 //            let index = self.get_index_by_urb_tag(urb_tag).unwrap();
             let mut entry = self.buffer.get_mut(*index as usize).unwrap();
-            let mut meta = MergedNode::parse_meta(&entry.meta).unwrap();
+            let entry = &mut *entry;
 
-            let msg = MergedNode::parse_forward_msg(&entry.msg).unwrap();
-            msgs_to_deliver.push(msg.msg.to_string());
-            msgs_to_deliver_info.push(format!("{}|scd_tag {:?}", msg.msg, msg.msg_tag));
+            let msg = Self::parse_forward_msg(&entry.msg).unwrap();
+            msgs_to_deliver_info.push(format!("{:?}|scd_tag {:?}", msg.msg, msg.msg_tag));
+            msgs_to_deliver.push(msg.msg.clone());
+            tagged_msgs_to_deliver.push((msg.msg_tag.clone(), msg.msg.clone()));
 
-            match SETTINGS.variant() {
+            match self.config.variant {
                 Variant::URB => { panic!("this should not happen") },
                 Variant::SCD | Variant::COUNTER | Variant::SNAPSHOT => {
                     if let Some(bset) = self.run_result.scd_delivered_msgs.get_mut(&msg.msg_tag.id) {
@@ -337,8 +317,8 @@ impl MergedNode {
                     }
                     self.delivered_tags.insert(msg.msg_tag.clone());
                     if let Some(instant) = entry.creation_instant {
-                        if SETTINGS.variant() != Variant::SNAPSHOT {
-                            self.run_result.msg_latencies.as_mut().unwrap().push(instant.elapsed().as_micros());
+                        if self.config.variant != Variant::SNAPSHOT {
+                            self.run_result.msg_latencies.as_mut().unwrap().push(self.clock.elapsed(instant).as_micros());
                         }
                     }
                 },
@@ -348,14 +328,15 @@ impl MergedNode {
             }
 
 
+            let meta = entry.meta.as_mut().unwrap();
             meta.delivered = true;
             if meta.txDes.is_none() && meta.tag.id == self.node_id {
                 printlnu(format!("WTF delivered but none txDEs: {:?}", meta.clone()));
             }
-            self.set_scd_meta(*index, meta);
         }
 
         if !msgs_to_deliver.is_empty() {
+            self.deliver_scd_to_application(tagged_msgs_to_deliver);
             self.scd_deliver(msgs_to_deliver);
         }
 
@@ -364,7 +345,7 @@ impl MergedNode {
                 self.run_result.throughputs = Some(Vec::new());
             }
             let msgs = self.throughput_msgs.as_ref().unwrap().len() as f64;
-            let time = self.throughput_instant.as_ref().unwrap().elapsed().as_micros() as f64;
+            let time = self.clock.elapsed(self.throughput_instant.unwrap()).as_micros() as f64;
             self.run_result.throughputs.as_mut().unwrap().push((msgs / time) * 1000000.0);
             self.throughput_instant = None;
             self.throughput_msgs = None;
@@ -401,12 +382,13 @@ impl MergedNode {
 
     fn handle_scd_records(&mut self, should_retransmit: bool) {
         let mut broadcast_vector = Vec::new();
+        let mut send_vec = VecDeque::new();
         let mut index = 0;
         let trusted = self.trusted();
         for record in self.buffer.iter() {
-            if let Some(mut meta) = MergedNode::parse_meta(&record.meta) {
+            if let Some(meta) = &record.meta {
                 if meta.txDes.is_none() || self.urb_has_terminated(meta.txDes.clone().unwrap()) {
-                    let parsed_msg = MergedNode::parse_forward_msg(&record.msg).unwrap();
+                    let parsed_msg = Self::parse_forward_msg(&record.msg).unwrap();
                     if meta.txDes.is_some()
                         && self.urb_has_terminated(meta.txDes.clone().unwrap())
                         && meta.transmission_counter >= 2 {
@@ -416,22 +398,25 @@ impl MergedNode {
                         }
                     }
                     if meta.tag.id == self.node_id || !trusted.get(meta.tag.id as usize - 1).unwrap() {
+                        let mut meta = meta.clone();
                         meta.transmission_counter = cmp::min(2, meta.transmission_counter + 1);
-                        broadcast_vector.push((meta.tag.seq.clone(), record.msg.clone().unwrap(), meta.clone(), index));
+                        broadcast_vector.push((meta.tag.seq.clone(), record.msg.clone().unwrap(), meta, index));
                     } else {
                         let forward_ack_msg = FORWARD {
-                            msg: parsed_msg.msg,
-                            msg_tag: parsed_msg.msg_tag,
-                            forward_tag: parsed_msg.forward_tag,
-                            cl: meta.clone().cl,
+                            msg: parsed_msg.msg.clone(),
+                            msg_tag: parsed_msg.msg_tag.clone(),
+                            forward_tag: parsed_msg.forward_tag.clone(),
+                            cl: meta.cl.clone(),
                         };
-                        let json = self.jsonify_message(&forward_ack_msg);
-                        self.send_json_message_to(&json, meta.tag.id);
+                        send_vec.push_back((WireMessage::FORWARD(forward_ack_msg).encode(self.config.encoding), meta.tag.id));
                     }
                 }
             }
             index += 1;
         }
+        while let Some((bytes, node_id)) = send_vec.pop_front() {
+            self.send_bytes_to(bytes, node_id);
+        }
 //        broadcast_vector.sort_by(|(_, tag1, _), (_, tag2, _)| {
 //            tag2.seq.cmp(&tag1.seq)
 //        });
@@ -441,7 +426,7 @@ impl MergedNode {
         while let Some((_, msg, mut meta, index)) = broadcast_vector.pop() {
 //            let index = self.get_index_by_urb_tag(&urb_tag).unwrap();
 //            let rec = self.buffer.get(index).unwrap();
-            if SETTINGS.print_client_operations() {
+            if self.config.print_client_operations {
                 printlnu(format!("(in handle_scd_records) Trying to urbBroadcast: {:?}", msg));
             }
 
@@ -452,7 +437,7 @@ impl MergedNode {
             } else {
                 meta.txDes = d.clone();
             }
-            self.set_scd_meta(index, meta.clone());
+            self.set_scd_meta(index, meta);
 
         }
 
@@ -489,8 +474,7 @@ impl MergedNode {
                     scd_rxSpace: scd_rxSpace_for_id,
                     scd_txSpace: scd_txSpace_for_id,
                 };
-                let json_msg = self.jsonify_message(&gossip);
-                self.send_json_to(&json_msg, node_id);
+                self.send_wire_message_to(&WireMessage::SCDGOSSIP(gossip), node_id);
             }
         }
 
@@ -525,7 +509,7 @@ impl MergedNode {
         let mut buffer_corrupted_bound = false;
         let mut faulty_records = Vec::new();
         for record in self.buffer.iter() {
-            if let Some(meta) = MergedNode::parse_meta(&record.meta) {
+            if let Some(meta) = &record.meta {
                 let sd = meta.tag.id;
                 let cl_sd = meta.cl.get(sd);
                 if clock_seen.contains(&(sd, cl_sd)) {
@@ -560,13 +544,13 @@ impl MergedNode {
                 format!("")
             };
 
-            self.buffer = Vec::new();
-            if !SETTINGS.record_evaluation_info() {
+            self.buffer.clear();
+            if !self.config.record_evaluation_info {
                 panic!("Node {} SCD Buffer was corrupted and then emptied, {}", self.node_id, s);
             } else {
                 printlnu(format!("SCD Buffer was corrupted and then emptied, {}", s));
             }
-            self.run_result.illegally_triggered_ss = !SETTINGS.is_failing_node();
+            self.run_result.illegally_triggered_ss = !self.config.is_failing_node;
             self.log(format!("SCD Buffer was corrupted and then emptied, {}", s))
         }
     }
@@ -598,7 +582,7 @@ impl MergedNode {
         let mut sn_found = Vec::new();
         let mut extra_found = Vec::new();
         for r in self.buffer.iter() {
-            if let Some(meta) = MergedNode::parse_meta(&r.meta) {
+            if let Some(meta) = &r.meta {
                 if meta.cl.get(self.node_id) <= ms_i {
                     if !extra_sns.remove(&meta.cl.get(self.node_id)) {
                         extra_is_subset = false;
@@ -615,11 +599,11 @@ impl MergedNode {
             all_sn_in_process_are_present = true;
         }
         if !(sn_reasonable && all_sn_in_process_are_present) {
-            if SETTINGS.print_client_operations() {
+            if self.config.print_client_operations {
                 let self_id = self.node_id;
                 let mut str = "sn: ".to_string();
                 for record in self.buffer.iter() {
-                    if let Some(meta) = MergedNode::parse_meta(&record.meta) {
+                    if let Some(meta) = &record.meta {
                         str.push_str(format!(", {}", meta.cl.get(self.node_id)).as_ref());
                     }
                 }
@@ -636,14 +620,14 @@ impl MergedNode {
                 printlnu(format!("Extra sns were not a subset, looking for: {:?} also found: {:?}, mSp: {:?}, ms: {}, rxObsS: {:?}, txObsS: {:?}, txSpace: {:?}", extra_sns, extra_found, self.scd_msp(), self.scd_ms(self.node_id), self.scd_rxObsS, self.scd_txObsS, self.scd_txSpace));
             }
 //            printlnu(format!("bound(i,1): {}", self.bound(self.node_id, 1)));
-            if !SETTINGS.record_evaluation_info() {
+            if !self.config.record_evaluation_info {
                 panic!("Node {} panic scd uniform {:?}",self.node_id, self.buffer);
             } else {
                 printlnu(format!("Node {} panic scd uniform\n buffer: {:?}",self.node_id, self.buffer));
             }
             self.log(format!("Seq not resonable! sn: {}, ms: {}, scdRx: {:?}, scdTx: {:?}, maxBufferSize: {}", self.sn, ms_i, self.scd_rxObsS, self.scd_txObsS, self.scd_buffer_unit_size()*self.node_ids.len() as i32));
             self.log(format!("Node {} panic scd uniform, buffer: {:?}",self.node_id, self.buffer));
-            self.run_result.illegally_triggered_ss = !SETTINGS.is_failing_node();
+            self.run_result.illegally_triggered_ss = !self.config.is_failing_node;
             self.uniform_scd_obsS(self.sn);
             self.update_seen();
         }
@@ -662,13 +646,13 @@ impl MergedNode {
             if self.scd_rxObsS[(node_id - 1) as usize] < at_least  {
                 printlnu(format!("scd_rxObsS corrupted. current rxObsS for node {} = {} maxseq = {}, n*bUS = {}, at_least: {} buffer {:?}", node_id, self.scd_rxObsS[(node_id - 1) as usize], self.scd_maxSeq(node_id ), self.scd_buffer_unit_size() * self.node_ids.len() as i32, at_least, self.buffer));
 
-                if !SETTINGS.record_evaluation_info() {
+                if !self.config.record_evaluation_info {
                     panic!("Node {} panic in adjust_scd_rxObsS_if_corrupted", self.node_id);
                 } else {
                     printlnu(format!("scd_rxObsS corrupted. current value: {}, at_least: {}", self.scd_rxObsS[(node_id - 1) as usize], at_least));
                 }
                 self.log(format!("scd_rxObsS corrupted. current value: {}, at_least: {}", self.scd_rxObsS[(node_id - 1) as usize], at_least));
-                self.run_result.illegally_triggered_ss = !SETTINGS.is_failing_node();
+                self.run_result.illegally_triggered_ss = !self.config.is_failing_node;
 
                 self.scd_rxObsS[(node_id - 1) as usize] = cmp::max(at_least, self.scd_rxObsS[(node_id - 1) as usize]);
             }
@@ -747,8 +731,9 @@ impl MergedNode {
 
         let mut logvec = VecDeque::new();
 
-        for record in self.buffer.iter_mut() {
-            if let Some(meta) = MergedNode::parse_meta(&record.meta) {
+        for mut record in self.buffer.iter_mut() {
+            let record = &mut *record;
+            if let Some(meta) = &record.meta {
                 let record_ok;
 
                 record_ok = {
@@ -782,8 +767,9 @@ impl MergedNode {
             }
         }
 
-        for record in self.buffer.iter_mut() {
-            if let Some(meta) = MergedNode::parse_meta(&record.meta) {
+        for mut record in self.buffer.iter_mut() {
+            let record = &mut *record;
+            if let Some(meta) = &record.meta {
                 let record_ok;
                 let mut min_ci =  if clock_i.is_empty() { break; } else { Int::max_value() };
                 for c_i in clock_i.clone() {
@@ -821,10 +807,11 @@ impl MergedNode {
         }
 
 
+        let print_client_operations = self.config.print_client_operations;
         self.buffer.retain(|r| {
-            if let Some(meta) = MergedNode::parse_meta(&r.meta.clone()) {
+            if let Some(meta) = &r.meta {
                 if !r.scd_needed && !r.urb_needed {
-                        if SETTINGS.print_client_operations() {
+                        if print_client_operations {
                             printlnu(format!(" (scd) Removing cl[i] = {}: {:?} rx {:?} tx {:?} sn: {}, {} < {} tx_space {:?}, msp: {:?} ", meta.cl.get(node_id), r, scd_rxObsS, scd_txObsS, sn, scd_ms[node_id.clone() as usize - 1], meta.cl.get(node_id), scd_txSpace, scd_msp));
                         }
                     logvec.push_back(format!(" (scd) Removing: {:?} rx {:?} tx {:?} sn {}, scd_ms: {:?}", r, scd_rxObsS, scd_txObsS, sn, scd_ms));
@@ -848,8 +835,8 @@ impl MergedNode {
 
     // SCD macro
 
-    fn scd_obsolete(&mut self, record: &BufferRecord<String>, forwarder: NodeId, scd_rxObsS: &Vec<Int>) -> bool {
-        let scd_meta = MergedNode::parse_meta(&record.meta).unwrap();
+    fn scd_obsolete(&mut self, record: &BufferRecord<UrbPayload<V>>, forwarder: NodeId, scd_rxObsS: &Vec<Int>) -> bool {
+        let scd_meta = record.meta.as_ref().unwrap();
         let trusted = self.trusted();
 
         let obs = ((scd_meta.txDes.is_some() && (scd_meta.tag.id == self.node_id || !trusted.get(scd_meta.tag.id as usize - 1).unwrap())) || (scd_meta.tag.id != self.node_id))
@@ -864,18 +851,7 @@ impl MergedNode {
     }
 
     pub(crate) fn scd_maxSeq(&self, node_id: NodeId) -> Int {
-        let mut max_seq = 0;
-        for record in self.buffer.iter() {
-            if let Some(meta) = MergedNode::parse_meta(&record.meta) {
-                let mut forwarder_max_seq = 0;
-
-                if meta.cl.get(node_id) < Int::max_value() {
-                    forwarder_max_seq = meta.cl.get(node_id);
-                }
-                max_seq = cmp::max(max_seq, forwarder_max_seq);
-            }
-        }
-        cmp::max(max_seq, self.scd_rxObsS[node_id as usize - 1])
+        cmp::max(self.buffer.scd_max_clock(node_id), self.scd_rxObsS[node_id as usize - 1])
     }
 
     pub fn scd_msp(&mut self) -> Option<Int> {
@@ -920,7 +896,7 @@ impl MergedNode {
         let mut min_s = Int::max_value();
         let scd_rxObsS = &self.scd_rxObsS;
 
-        if SETTINGS.print_client_operations() {
+        if self.config.print_client_operations {
             printlnu(format!("scd_rxObsS {:?}", scd_rxObsS));
         }
 
@@ -947,33 +923,34 @@ impl MergedNode {
 
     // SCD msg reception
 
-    pub fn scd_msg_received(&mut self, msg: String) {
-        if json_is_FORWARD_message(&msg) {
-            if let Ok(forward_msg) = serde_json::from_str(&msg) {
-                if SETTINGS.print_client_operations(){
-                    printlnu(format!("FORWARD recv: {:?}", forward_msg));
-                }
-                self.SCD_forward_recieved(forward_msg);
-            }
+    pub fn scd_msg_received(&mut self, forward_msg: FORWARD<V>) {
+        if self.config.print_client_operations{
+            printlnu(format!("FORWARD recv: {:?}", forward_msg));
         }
+        self.SCD_forward_recieved(forward_msg);
     }
 
-    pub fn SCD_forward_recieved(&mut self, msg: FORWARD) {
+    pub fn SCD_forward_recieved(&mut self, msg: FORWARD<V>) {
         let m = msg.msg;
         let msg_tag = msg.msg_tag;
         let forward_tag = msg.forward_tag;
 
-        let _ = self.forward(m.into_owned(), msg_tag.clone(), forward_tag.clone(), Some(msg.cl));
+        let _ = self.forward(m, msg_tag.clone(), forward_tag.clone(), Some(msg.cl));
     }
 
     pub fn SCDGOSSIP_received(&mut self, gossip: SCDGOSSIP) {
+        // Like the URB gossip, the counters of nodes that haven't reset yet
+        // would undo the reset.
+        if self.reset_phase() == ResetPhase::RESET {
+            return;
+        }
         let scd_maxSeq = gossip.scd_maxSeq;
         let txS_clone = self.scd_txSpace.clone();
         let mut scd_rxObsS = &mut self.scd_rxObsS;
         let mut scd_txObsS = &mut self.scd_txObsS;
         let mut scd_rxSpace = &mut self.scd_rxSpace;
         let mut scd_txSpace = &mut self.scd_txSpace;
-        if SETTINGS.print_client_operations() {
+        if self.config.print_client_operations {
             if self.sn < gossip.scd_maxSeq {
                 printlnu(format!("updating sn from {} to {}", self.sn, gossip.scd_maxSeq));
             }
@@ -1019,31 +996,14 @@ impl MergedNode {
     }
 
     pub fn scd_has_terminated(&self, txDes: &Tag) -> bool {
-        for record in self.buffer.iter() {
-            if record.meta.is_some() {
-                if let Some(scd_meta) = MergedNode::parse_meta(&record.meta) {
-                    if scd_meta.tag.id == txDes.id && scd_meta.cl.get(txDes.id) == txDes.seq {
-//                        printlnu(format!("txDes {:?} record {:?} return {}",txDes, record, scd_meta.delivered));
-                        return scd_meta.delivered;
-                    }
-                }
-            }
+        let index = self.buffer.position_of_scd_tag(txDes);
+        match index.and_then(|index| self.buffer.get(index)).and_then(|record| record.meta.as_ref()) {
+            Some(scd_meta) => scd_meta.delivered,
+            None => true,
         }
-        true
     }
 
     pub(crate) fn saved(&mut self, node_id: NodeId) -> HashSet<Int> {
-        let mut saved_clock = HashSet::new();
-
-        for record in self.buffer.iter() {
-            if record.meta.is_some() {
-                if let Some(scd_meta) = MergedNode::parse_meta(&record.meta) {
-                    if scd_meta.tag.id == node_id {
-                        saved_clock.insert(scd_meta.cl.get(node_id));
-                    }
-                }
-            }
-        }
-        saved_clock
+        self.buffer.scd_seqs_of(node_id)
     }
 }
\ No newline at end of file
@@ -0,0 +1,365 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use commons::types::{Int, NodeId, Tag};
+use crate::config::{NodeConfig, NodeBuilder};
+use crate::terminal_output;
+use super::clock::VirtualClock;
+use super::faulty_transport::FaultyTransport;
+use super::mergednode::MergedNode;
+use super::payload::{Payload, AppMessage};
+use super::transport::Transport;
+
+// Every datagram takes between this and twice this long, on top of the
+// delay and jitter the faults of its link hold it back for.
+const DEFAULT_LATENCY: Duration = Duration::from_micros(200);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    // The node runs one iteration of its loop.
+    STEP(NodeId),
+    // The datagram arrives at the receiver.
+    DELIVER { sender: NodeId, receiver: NodeId, datagram: Vec<u8> },
+    // The node stops for good, datagrams to it are lost.
+    CRASH(NodeId),
+}
+
+#[derive(Debug)]
+struct Event {
+    at: Duration,
+    // Events at the same time happen in the order they were scheduled.
+    seq: u64,
+    kind: EventKind,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Event) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Event) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, so that the BinaryHeap pops the earliest event first.
+impl Ord for Event {
+    fn cmp(&self, other: &Event) -> Ordering {
+        (other.at, other.seq).cmp(&(self.at, self.seq))
+    }
+}
+
+#[derive(Default)]
+struct SimulatedNetwork {
+    // Sent during the current event, in the order they were sent.
+    sent: Vec<(NodeId, NodeId, Vec<u8>)>,
+    inboxes: BTreeMap<NodeId, VecDeque<Vec<u8>>>,
+}
+
+// The transport of a simulated node. Sending hands the datagram to the
+// simulator, which decides when it arrives, receiving takes what has
+// arrived so far.
+pub struct SimulatedTransport {
+    node_id: NodeId,
+    network: Arc<Mutex<SimulatedNetwork>>,
+}
+
+impl Transport for SimulatedTransport {
+    fn send_to(&mut self, bytes: &[u8], receiver_id: NodeId) {
+        self.network.lock().unwrap().sent.push((self.node_id, receiver_id, bytes.to_vec()));
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let mut network = self.network.lock().unwrap();
+        let datagram = network.inboxes.get_mut(&self.node_id)?.pop_front()?;
+        let amt = datagram.len().min(buf.len());
+        buf[..amt].copy_from_slice(&datagram[..amt]);
+        Some(amt)
+    }
+}
+
+struct SimulatedNode<V: Payload> {
+    node: MergedNode<FaultyTransport<SimulatedTransport>, V>,
+    iterations: Int,
+    crashed: bool,
+    urb_deliveries: Receiver<(Tag, V)>,
+    scd_deliveries: Receiver<Vec<(Tag, V)>>,
+    // Every tag the node delivered, in the order it delivered them.
+    delivered: Vec<Tag>,
+}
+
+// Runs a cluster of MergedNodes in one thread, on a simulated network and
+// a virtual clock. The nodes are stepped one loop iteration at a time and
+// datagrams are delivered one at a time, in an order drawn from a
+// generator seeded with the seed of the simulation. Each node sends
+// through a FaultyTransport on the virtual clock, so the faults in its
+// config drop, duplicate, delay and reorder datagrams as in a real run.
+//
+// The nodes read the time from the virtual clock and draw their random
+// numbers from generators seeded with the fault_seed of their config,
+// which the seed of the simulation replaces. Nothing else depends on the
+// real time or on the order of hash maps, so creating the simulation
+// again from the same configs and seed replays a run exactly, trace hash
+// and order of deliveries included.
+//
+// The nodes run no application. Client operations that wait, like reading
+// the counter, would run the loop of their node without the others and
+// never finish here, so broadcasts are made on the nodes directly.
+pub struct Simulation<V: Payload = AppMessage> {
+    seed: u64,
+    rng: StdRng,
+    clock: VirtualClock,
+    network: Arc<Mutex<SimulatedNetwork>>,
+    nodes: BTreeMap<NodeId, SimulatedNode<V>>,
+    events: BinaryHeap<Event>,
+    next_seq: u64,
+    latency: Duration,
+    trace: DefaultHasher,
+    pub events_processed: u64,
+}
+
+impl<V: Payload> Simulation<V> {
+    // The configs of a cluster of number_of_nodes nodes with the default
+    // settings. The socket addresses are never bound.
+    pub fn configs(number_of_nodes: Int) -> Vec<NodeConfig> {
+        let socket_addrs: std::collections::HashMap<NodeId, SocketAddr> = (1..=number_of_nodes)
+            .map(|node_id| (node_id, SocketAddr::from(([127, 0, 0, 1], 20000 + node_id as u16))))
+            .collect();
+        (1..=number_of_nodes)
+            .map(|node_id| NodeConfig::new(node_id, socket_addrs.clone()))
+            .collect()
+    }
+
+    pub fn new(configs: Vec<NodeConfig>, seed: u64) -> Simulation<V> {
+        let clock = VirtualClock::new();
+        let network = Arc::new(Mutex::new(SimulatedNetwork::default()));
+        let mut nodes = BTreeMap::new();
+        for mut config in configs {
+            config.fault_seed = seed;
+            let node_id = config.node_id;
+            let transport = SimulatedTransport {
+                node_id,
+                network: network.clone(),
+            };
+            let transport = FaultyTransport::new(transport, config.faults, config.link_faults.clone(), seed.wrapping_add(node_id as u64))
+                .with_clock(Arc::new(clock.clone()));
+            network.lock().unwrap().inboxes.insert(node_id, VecDeque::new());
+            let mut node = NodeBuilder::for_payload(config).build_with_clock(transport, Arc::new(clock.clone()));
+            node.open_message_buffers();
+            let (urb_deliveries, scd_deliveries) = node.subscribe_deliveries();
+            nodes.insert(node_id, SimulatedNode {
+                node,
+                iterations: 0,
+                crashed: false,
+                urb_deliveries,
+                scd_deliveries,
+                delivered: Vec::new(),
+            });
+        }
+
+        let mut simulation = Simulation {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            clock,
+            network,
+            nodes,
+            events: BinaryHeap::new(),
+            next_seq: 0,
+            latency: DEFAULT_LATENCY,
+            trace: DefaultHasher::new(),
+            events_processed: 0,
+        };
+        for node_id in simulation.node_ids() {
+            let tick_interval = simulation.nodes[&node_id].node.config.tick_interval;
+            let first_step = tick_interval.mul_f64(simulation.rng.gen::<f64>());
+            simulation.schedule(first_step, EventKind::STEP(node_id));
+        }
+        simulation
+    }
+
+    pub fn with_latency(mut self, latency: Duration) -> Simulation<V> {
+        self.latency = latency;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // The virtual time since the simulation started.
+    pub fn time(&self) -> Duration {
+        self.clock.time()
+    }
+
+    // A hash of every event processed so far, two runs with the same hash
+    // went exactly the same way.
+    pub fn trace_hash(&self) -> u64 {
+        self.trace.finish()
+    }
+
+    pub fn node_ids(&self) -> Vec<NodeId> {
+        self.nodes.keys().cloned().collect()
+    }
+
+    pub fn node(&self, node_id: NodeId) -> &MergedNode<FaultyTransport<SimulatedTransport>, V> {
+        &self.nodes[&node_id].node
+    }
+
+    pub fn node_mut(&mut self, node_id: NodeId) -> &mut MergedNode<FaultyTransport<SimulatedTransport>, V> {
+        &mut self.nodes.get_mut(&node_id).expect("No such node in the simulation").node
+    }
+
+    // The tags of the URB and SCD deliveries of the node, in order.
+    pub fn delivered(&self, node_id: NodeId) -> &[Tag] {
+        &self.nodes[&node_id].delivered
+    }
+
+    pub fn is_crashed(&self, node_id: NodeId) -> bool {
+        self.nodes[&node_id].crashed
+    }
+
+    pub fn crash_at(&mut self, node_id: NodeId, time: Duration) {
+        self.schedule(time, EventKind::CRASH(node_id));
+    }
+
+    // Processes the next event, false if there are none left.
+    pub fn step(&mut self) -> bool {
+        let event = match self.events.pop() {
+            Some(event) => event,
+            None => return false,
+        };
+        self.clock.advance_to(event.at);
+        event.at.hash(&mut self.trace);
+        event.kind.hash(&mut self.trace);
+        self.events_processed += 1;
+
+        match event.kind {
+            EventKind::STEP(node_id) => {
+                let simulated = self.nodes.get_mut(&node_id).unwrap();
+                if simulated.crashed {
+                    return true;
+                }
+                let node = &mut simulated.node;
+                terminal_output::set_node(node_id, node.config.terminal_color);
+                node.fail_if_due(simulated.iterations);
+                node.bare_bone_loop_iter(simulated.iterations % node.config.delta == 0);
+                simulated.iterations += 1;
+
+                let urb_tags = simulated.urb_deliveries.try_iter().map(|(tag, _)| tag);
+                let scd_tags = simulated.scd_deliveries.try_iter().flatten().map(|(tag, _)| tag);
+                for tag in urb_tags.chain(scd_tags) {
+                    tag.hash(&mut self.trace);
+                    simulated.delivered.push(tag);
+                }
+
+                let tick_interval = node.config.tick_interval;
+                let next_step = self.time() + tick_interval.mul_f64(0.5 + self.rng.gen::<f64>());
+                self.schedule(next_step, EventKind::STEP(node_id));
+            }
+            EventKind::DELIVER { receiver, datagram, .. } => {
+                if !self.nodes[&receiver].crashed {
+                    self.network.lock().unwrap().inboxes.get_mut(&receiver).unwrap().push_back(datagram);
+                }
+            }
+            EventKind::CRASH(node_id) => {
+                self.nodes.get_mut(&node_id).unwrap().crashed = true;
+                self.network.lock().unwrap().inboxes.get_mut(&node_id).unwrap().clear();
+            }
+        }
+        self.send_datagrams();
+        true
+    }
+
+    // Runs until the virtual time has moved on by duration.
+    pub fn run_for(&mut self, duration: Duration) {
+        let end = self.time() + duration;
+        while self.events.peek().map_or(false, |event| event.at <= end) {
+            self.step();
+        }
+        self.clock.advance_to(end);
+    }
+
+    // Runs until done holds, checking after every event. False if it
+    // doesn't hold within limit of virtual time.
+    pub fn run_until<F: FnMut(&Simulation<V>) -> bool>(&mut self, limit: Duration, mut done: F) -> bool {
+        let end = self.time() + limit;
+        while !done(self) {
+            if !self.events.peek().map_or(false, |event| event.at <= end) || !self.step() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn schedule(&mut self, at: Duration, kind: EventKind) {
+        self.events.push(Event { at, seq: self.next_seq, kind });
+        self.next_seq += 1;
+    }
+
+    // Schedules the arrival of what the nodes sent during the last event.
+    // The faulty transports already dropped and held back datagrams.
+    fn send_datagrams(&mut self) {
+        let sent = std::mem::replace(&mut self.network.lock().unwrap().sent, Vec::new());
+        for (sender, receiver, datagram) in sent {
+            let at = self.time() + self.latency.mul_f64(1.0 + self.rng.gen::<f64>());
+            self.schedule(at, EventKind::DELIVER { sender, receiver, datagram });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Node 1 broadcasts over lossy links that delay and reorder datagrams
+    // and node 3 corrupts its variables a while after. Returns the trace
+    // hash and the order in which each node delivered.
+    fn lossy_run(seed: u64) -> (u64, Vec<Vec<Tag>>) {
+        let configs = Simulation::<AppMessage>::configs(3).into_iter().map(|mut config| {
+            config.faults = "drop=0.1,duplicate=0.05,reorder=4,delay=1,jitter=2".parse().unwrap();
+            if config.node_id == 1 {
+                config.link_faults.insert(2, config.faults.with_spec("delay=5,jitter=10").unwrap());
+            }
+            config.is_failing_node = config.node_id == 3;
+            // Otherwise finding the corruption panics instead of recovering.
+            config.record_evaluation_info = true;
+            config
+        }).collect();
+        let mut simulation: Simulation = Simulation::new(configs, seed);
+        let tags: Vec<Tag> = (0..10)
+            .map(|i| simulation.node_mut(1).scd_broadcast(AppMessage::BROADCAST(i.to_string())).unwrap())
+            .collect();
+
+        let delivered = simulation.run_until(Duration::from_secs(60), |simulation| {
+            simulation.node_ids().iter().all(|node_id| tags.iter().all(|tag| simulation.node(*node_id).delivered_tags.contains(tag)))
+        });
+        assert!(delivered, "Not every node delivered {:?} with seed {}", tags, seed);
+        simulation.run_for(Duration::from_secs(1));
+        assert!(simulation.node(3).has_failed, "Node 3 did not fail with seed {}", seed);
+        let deliveries = simulation.node_ids().iter().map(|node_id| simulation.delivered(*node_id).to_vec()).collect();
+        (simulation.trace_hash(), deliveries)
+    }
+
+    #[test]
+    fn test_same_seed_replays_the_same_run() {
+        for seed in 0..3 {
+            let (trace_hash, deliveries) = lossy_run(seed);
+            assert!(deliveries.iter().all(|delivered| delivered.len() >= 10), "Seed {} delivered {:?}", seed, deliveries);
+            assert_eq!((trace_hash, deliveries), lossy_run(seed), "Seed {} did not replay", seed);
+        }
+        assert_ne!(lossy_run(1), lossy_run(2));
+    }
+}
@@ -1,4 +1,6 @@
-use super::mergednode::MergedNode;
+use super::application::{ScdApplication, ScdContext};
+use super::client::{ClientRequest, ClientResponse, ClientError};
+use super::payload::AppMessage;
 use crate::scd::algorithm5;
 use crate::scd::algorithm5::messages::{SYNC, WRITE};
 use commons::types::{Int, NodeId, Tag};
@@ -6,9 +8,6 @@ use serde::{Deserialize, Serialize};
 use crate::terminal_output::printlnu;
 use std::collections::HashMap;
 use std::cmp::max;
-use crate::settings::SETTINGS;
-use crate::merge::mergednode::StatusCode;
-use std::time::Instant;
 
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
@@ -26,47 +25,58 @@ impl Timestamp {
     }
 }
 
-impl MergedNode {
-    pub fn snapshot(&mut self) -> HashMap<Int,Int> {
-        let now = Instant::now();
-        printlnu(format!("-------------    Initiating snapshot read"));
-        let message = SYNC { sender_id: self.node_id };
+// The snapshot object, reg holds the last value written to each register.
+#[derive(Debug)]
+pub struct Snapshot {
+    pub node_id: NodeId,
+    pub reg: HashMap<Int, Int>,
+    pub tsa: HashMap<Int, Timestamp>,
+}
+
+impl Snapshot {
+    pub fn new(node_id: NodeId) -> Self {
+        Snapshot {
+            node_id,
+            reg: HashMap::new(),
+            tsa: HashMap::new(),
+        }
+    }
 
-        let json_message = self.jsonify_message(&message);
+    pub fn snapshot(&mut self, scd: &mut dyn ScdContext<AppMessage>) -> HashMap<Int,Int> {
+        let now = scd.clock().now();
+        printlnu(format!("-------------    Initiating snapshot read"));
+        let message = AppMessage::SYNC(SYNC { sender_id: self.node_id });
 
-        let mut txDes = self.scd_broadcast_eventually(json_message.clone());
+        let mut txDes = scd.broadcast_eventually(message);
 
         if txDes.is_some() {
-            self.wait_until_txDex_terminate(txDes.as_ref().unwrap());
+            self.wait_for(scd, txDes.as_ref().unwrap());
         }
         let s = format!("-------------    snapshot returns {}", self.reg_pretty());
         printlnu(s.clone());
-        if SETTINGS.print_client_operations() {
-        }
-        self.log(s);
-        let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::ResultReady);
-        self.run_result.read_latencies.as_mut().unwrap().push(now.elapsed().as_micros());
+        scd.log(s);
+        let latency = scd.clock().elapsed(now).as_micros();
+        scd.run_result().read_latencies.as_mut().unwrap().push(latency);
         return self.reg.clone()
 
     }
 
-    pub fn snapshot_write(&mut self, r: Int, v: Int) {
-        let now = Instant::now();
-        if SETTINGS.print_client_operations() {
+    pub fn write(&mut self, scd: &mut dyn ScdContext<AppMessage>, r: Int, v: Int) {
+        let now = scd.clock().now();
+        if scd.config().print_client_operations {
             printlnu(format!{"r {} v {}", r, v});
         }
-        self.log(format!{"r {} v {}", r, v});
-        let message = SYNC { sender_id: self.node_id };
+        scd.log(format!{"r {} v {}", r, v});
+        let message = AppMessage::SYNC(SYNC { sender_id: self.node_id });
 
-        let json_message = self.jsonify_message(&message);
-        let mut txDes = self.scd_broadcast_eventually(json_message.clone());
+        let mut txDes = scd.broadcast_eventually(message.clone());
         while txDes.is_none() {
-            txDes = self.scd_broadcast_eventually(json_message.clone());
+            txDes = scd.broadcast_eventually(message.clone());
         }
 
 //        wait(txDes = ⊥ ∨ hasTerminated(txDes));
         if txDes.is_some() {
-            self.wait_until_all_terminate();
+            self.wait_for_all(scd);
         } else {
             panic!("txDes is non in snapshot write");
         }
@@ -80,28 +90,43 @@ impl MergedNode {
         tsa_date.date += 1;
         tsa_date.proc = Some(self.node_id);
 
-        let message = WRITE {
+        let message = AppMessage::WRITE(WRITE {
             r,
             v,
             timestamp: tsa_date
-        };
+        });
 
-        let json_message = self.jsonify_message(&message);
-
-        let mut txDes = self.scd_broadcast_eventually(json_message.clone());
+        let mut txDes = scd.broadcast_eventually(message.clone());
 
         while txDes.is_none() {
-            txDes = self.scd_broadcast_eventually(json_message.clone());
+            txDes = scd.broadcast_eventually(message.clone());
         }
 
 //        wait(txDes = ⊥ ∨ hasTerminated(txDes));
         if txDes.is_some() {
-            self.wait_until_all_terminate();
+            self.wait_for_all(scd);
         } else {
             panic!("txDes is non in snapshot write 2");
         }
-        let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::Ok);
-        self.run_result.msg_latencies.as_mut().unwrap().push(now.elapsed().as_micros());
+        let latency = scd.clock().elapsed(now).as_micros();
+        scd.run_result().msg_latencies.as_mut().unwrap().push(latency);
+    }
+
+    pub fn reg_pretty(&self) -> String {
+        let mut reg_vec = Vec::new();
+        for (i, val) in self.reg.iter() {
+            reg_vec.push((*i , *val));
+        }
+        reg_vec.sort_by(|(i,v),(i2,v2)| {
+            i.cmp(i2)
+        });
+        let mut s = "[".to_string();
+        for (i, v) in reg_vec {
+            s = format!("{} {},", s,v);
+        }
+        let (sstr,_) = s.split_at(s.len()-1);
+        s = format!("{}]", sstr);
+        format!("{}",s)
     }
 
     fn compare_timestamp(&self, ts1: &Timestamp, ts2: &Timestamp) -> bool {
@@ -111,13 +136,12 @@ impl MergedNode {
 
     }
 
-    pub fn snapshot_msg_received(&mut self, msgs: Vec<String>) {
+    pub fn snapshot_msg_received(&mut self, msgs: &[AppMessage]) {
         let mut write_vec: Vec<WRITE> = Vec::new();
 
         for msg in msgs {
-            if let Ok(write_msg) = serde_json::from_str::<WRITE>(msg.as_ref()) {
-                write_vec.push(write_msg);
-            } else {
+            if let AppMessage::WRITE(write_msg) = msg {
+                write_vec.push(write_msg.clone());
             }
         }
 
@@ -157,14 +181,25 @@ impl MergedNode {
         }
 
     }
+}
 
-    fn wait_until_txDex_terminate(&mut self, txDes: &Tag) {
-        let mut has_terminated = false;
-        let mut iter = 0;
-        while !has_terminated {
-            self.bare_bone_loop_iter(iter % SETTINGS.delta() == 0);
-            has_terminated = self.scd_has_terminated(txDes);
-            iter += 1;
+impl ScdApplication<AppMessage> for Snapshot {
+    fn execute(&mut self, request: ClientRequest<AppMessage>, scd: &mut dyn ScdContext<AppMessage>) -> ClientResponse {
+        match request {
+            ClientRequest::SNAPSHOT => ClientResponse::Snapshot(self.snapshot(scd)),
+            ClientRequest::WRITE { r, v } => {
+                self.write(scd, r, v);
+                ClientResponse::Written
+            },
+            _ => ClientResponse::Error(ClientError::Unsupported)
         }
     }
-}
\ No newline at end of file
+
+    fn on_scd_deliver(&mut self, batch: &[AppMessage]) {
+        self.snapshot_msg_received(batch);
+    }
+
+    fn report(&self) -> Option<String> {
+        Some(format!("Snapshot final registry: {}", self.reg_pretty()))
+    }
+}
@@ -0,0 +1,430 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mio::net::{TcpListener, TcpStream};
+use mio::{Events, Poll, PollOpt, Ready, Registration, SetReadiness, Token};
+
+use commons::types::NodeId;
+use super::fragmentation::MAX_DATAGRAM_SIZE;
+use super::transport::Transport;
+
+// Datagrams sent to a peer that can't keep up are dropped beyond this.
+const MAX_QUEUED_PER_PEER: usize = 1024;
+// Frames are only taken from the queue of a peer while fewer bytes than
+// this wait to be written, the rest wait in the queue until it's full.
+const MAX_PENDING_BYTES: usize = 64 * 1024;
+
+const FIRST_RECONNECT_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(2);
+const IDLE_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+// Connecting blocks the IO thread, for at most this long.
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(100);
+
+// Every frame is [length: u32, big endian][datagram].
+const LENGTH_PREFIX_LEN: usize = 4;
+
+const LISTENER: Token = Token(0);
+const WAKEUP: Token = Token(1);
+const FIRST_STREAM: usize = 2;
+
+// Carries the same datagrams as UdpTransport over TCP connections. Each
+// node connects to a peer the first time it sends to it and only writes
+// on that connection, the connections accepted from others are only read
+// from. The sockets are handled by a thread of their own, so sending
+// never blocks the do-forever loop.
+pub struct TcpTransport {
+    queues: HashMap<NodeId, SyncSender<Vec<u8>>>,
+    received: Receiver<Vec<u8>>,
+    received_registration: Registration,
+    wake_io_thread: SetReadiness,
+    stopped: Arc<AtomicBool>,
+}
+
+impl TcpTransport {
+    pub fn bind(node_id: NodeId, socket_addrs: HashMap<NodeId, SocketAddr>) -> TcpTransport {
+        let port = socket_addrs.get(&node_id).expect("Could not found own socket address.").port();
+        let own_socket_addr = SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(0,0,0,0)),
+            port,
+        );
+        // Bound with std and handed to mio, binding through mio fails with
+        // EINVAL on current platforms.
+        let listener = std::net::TcpListener::bind(own_socket_addr)
+            .and_then(TcpListener::from_std)
+            .expect("Could not create listener.");
+
+        let (received_tx, received) = mpsc::channel();
+        let (received_registration, received_readiness) = Registration::new2();
+        let (wakeup_registration, wake_io_thread) = Registration::new2();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let mut queues = HashMap::new();
+        let mut peers = Vec::new();
+        for (peer_id, addr) in socket_addrs {
+            let (tx, rx) = mpsc::sync_channel(MAX_QUEUED_PER_PEER);
+            queues.insert(peer_id, tx);
+            peers.push(Peer::new(addr, rx));
+        }
+
+        let poll = Poll::new().expect("Could not create poll.");
+        poll.register(&listener, LISTENER, Ready::readable(), PollOpt::edge())
+            .expect("Could not register the listener.");
+        poll.register(&wakeup_registration, WAKEUP, Ready::readable(), PollOpt::edge())
+            .expect("Could not register the wakeup.");
+
+        let mut io_thread = IoThread {
+            next_token: FIRST_STREAM + peers.len(),
+            poll,
+            listener,
+            peers,
+            incoming: HashMap::new(),
+            received: received_tx,
+            received_readiness,
+            _wakeup_registration: wakeup_registration,
+            stopped: Arc::clone(&stopped),
+        };
+        thread::spawn(move || io_thread.run());
+
+        TcpTransport {
+            queues,
+            received,
+            received_registration,
+            wake_io_thread,
+            stopped,
+        }
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send_to(&mut self, bytes: &[u8], receiver_id: NodeId) {
+        let queue = self
+            .queues
+            .get(&receiver_id)
+            .expect("Could not find receiver among the socket addresses");
+        // A full queue means the link is down or too slow, which the
+        // protocols handle like a lost datagram.
+        let _ = queue.try_send(bytes.to_vec());
+        let _ = self.wake_io_thread.set_readiness(Ready::readable());
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize> {
+        match self.received.try_recv() {
+            Ok(datagram) => {
+                let amt = datagram.len().min(buf.len());
+                buf[..amt].copy_from_slice(&datagram[..amt]);
+                Some(amt)
+            }
+            Err(_) => None,
+        }
+    }
+
+    fn register(&self, poll: &Poll, token: Token) -> io::Result<()> {
+        poll.register(&self.received_registration, token, Ready::readable(), PollOpt::edge())
+    }
+}
+
+impl Drop for TcpTransport {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        let _ = self.wake_io_thread.set_readiness(Ready::readable());
+    }
+}
+
+// The outgoing connection to one peer.
+struct Peer {
+    addr: SocketAddr,
+    queue: Receiver<Vec<u8>>,
+    stream: Option<TcpStream>,
+    // Frames taken from the queue but not yet written.
+    pending: Vec<u8>,
+    has_written_on_stream: bool,
+    backoff: Duration,
+    reconnect_at: Instant,
+}
+
+impl Peer {
+    fn new(addr: SocketAddr, queue: Receiver<Vec<u8>>) -> Peer {
+        Peer {
+            addr,
+            queue,
+            stream: None,
+            pending: Vec::new(),
+            has_written_on_stream: false,
+            backoff: FIRST_RECONNECT_BACKOFF,
+            reconnect_at: Instant::now(),
+        }
+    }
+
+    fn take_queued(&mut self) {
+        while self.pending.len() < MAX_PENDING_BYTES {
+            match self.queue.try_recv() {
+                Ok(datagram) => {
+                    self.pending.extend_from_slice(&(datagram.len() as u32).to_be_bytes());
+                    self.pending.extend_from_slice(&datagram);
+                }
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn disconnect(&mut self, now: Instant) {
+        self.stream = None;
+        // Part of a frame may have been written, so what is left can't be
+        // continued on a new connection. If nothing was written it's kept.
+        if self.has_written_on_stream {
+            self.pending.clear();
+        }
+        self.has_written_on_stream = false;
+        self.reconnect_at = now + self.backoff;
+        self.backoff = (self.backoff * 2).min(MAX_RECONNECT_BACKOFF);
+    }
+}
+
+// A connection accepted from another node.
+struct Incoming {
+    stream: TcpStream,
+    buf: Vec<u8>,
+}
+
+struct IoThread {
+    poll: Poll,
+    listener: TcpListener,
+    peers: Vec<Peer>,
+    incoming: HashMap<Token, Incoming>,
+    next_token: usize,
+    received: Sender<Vec<u8>>,
+    received_readiness: SetReadiness,
+    _wakeup_registration: Registration,
+    stopped: Arc<AtomicBool>,
+}
+
+impl IoThread {
+    fn run(&mut self) {
+        let mut events = Events::with_capacity(64);
+        while !self.stopped.load(Ordering::SeqCst) {
+            let _ = self.poll.poll(&mut events, Some(self.poll_timeout()));
+            for event in events.iter() {
+                match event.token() {
+                    LISTENER => self.accept(),
+                    WAKEUP => {}
+                    token if token.0 >= FIRST_STREAM + self.peers.len() => {
+                        if !self.read(token) {
+                            return;
+                        }
+                    }
+                    // Writability of an outgoing stream, handled below.
+                    _ => {}
+                }
+            }
+            self.write_to_peers();
+        }
+    }
+
+    fn poll_timeout(&self) -> Duration {
+        let now = Instant::now();
+        self.peers.iter()
+            .filter(|peer| peer.stream.is_none() && !peer.pending.is_empty())
+            .map(|peer| peer.reconnect_at.saturating_duration_since(now))
+            .min()
+            .unwrap_or(IDLE_POLL_TIMEOUT)
+            .min(IDLE_POLL_TIMEOUT)
+    }
+
+    fn accept(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            let token = Token(self.next_token);
+            self.next_token += 1;
+            if self.poll.register(&stream, token, Ready::readable(), PollOpt::edge()).is_ok() {
+                self.incoming.insert(token, Incoming { stream, buf: Vec::new() });
+                // Data may have arrived before the registration.
+                if !self.read(token) {
+                    return;
+                }
+            }
+        }
+    }
+
+    // Reads until the socket is drained and hands over every complete
+    // frame. Returns false if the node has stopped listening.
+    fn read(&mut self, token: Token) -> bool {
+        let incoming = match self.incoming.get_mut(&token) {
+            Some(incoming) => incoming,
+            None => return true,
+        };
+
+        let mut chunk = [0; 4096];
+        let mut is_closed = false;
+        loop {
+            match incoming.stream.read(&mut chunk) {
+                Ok(0) => {
+                    is_closed = true;
+                    break;
+                }
+                Ok(amt) => incoming.buf.extend_from_slice(&chunk[..amt]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => {
+                    is_closed = true;
+                    break;
+                }
+            }
+        }
+
+        let (frames, is_corrupt) = take_frames(&mut incoming.buf);
+        for frame in frames {
+            if self.received.send(frame).is_err() {
+                return false;
+            }
+            let _ = self.received_readiness.set_readiness(Ready::readable());
+        }
+        if is_closed || is_corrupt {
+            self.incoming.remove(&token);
+        }
+        true
+    }
+
+    fn write_to_peers(&mut self) {
+        let now = Instant::now();
+        for (index, peer) in self.peers.iter_mut().enumerate() {
+            // Connections are only opened once there is something to send.
+            peer.take_queued();
+            if peer.pending.is_empty() {
+                continue;
+            }
+
+            if peer.stream.is_none() {
+                if now < peer.reconnect_at {
+                    continue;
+                }
+                match connect(peer.addr) {
+                    Ok(stream) => {
+                        let token = Token(FIRST_STREAM + index);
+                        if self.poll.register(&stream, token, Ready::writable(), PollOpt::edge()).is_err() {
+                            peer.disconnect(now);
+                            continue;
+                        }
+                        peer.stream = Some(stream);
+                    }
+                    Err(_) => {
+                        peer.disconnect(now);
+                        continue;
+                    }
+                }
+            }
+
+            let stream = peer.stream.as_mut().unwrap();
+            while !peer.pending.is_empty() {
+                match stream.write(&peer.pending) {
+                    Ok(0) => {
+                        peer.disconnect(now);
+                        break;
+                    }
+                    Ok(amt) => {
+                        peer.pending.drain(..amt);
+                        peer.has_written_on_stream = true;
+                        peer.backoff = FIRST_RECONNECT_BACKOFF;
+                    }
+                    // Also what a connection that is still being set up
+                    // returns, the writable event tells when to go on.
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(_) => {
+                        peer.disconnect(now);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Like the listener, connected with std and handed to mio.
+fn connect(addr: SocketAddr) -> io::Result<TcpStream> {
+    let stream = std::net::TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    TcpStream::from_stream(stream)
+}
+
+// Removes the complete frames from the start of buf. A length no datagram
+// can have means the stream is corrupt, after which nothing more is taken.
+fn take_frames(buf: &mut Vec<u8>) -> (Vec<Vec<u8>>, bool) {
+    let mut frames = Vec::new();
+    let mut start = 0;
+    let mut is_corrupt = false;
+    while buf.len() - start >= LENGTH_PREFIX_LEN {
+        let len = u32::from_be_bytes(buf[start..start + LENGTH_PREFIX_LEN].try_into().unwrap()) as usize;
+        if len > MAX_DATAGRAM_SIZE {
+            is_corrupt = true;
+            break;
+        }
+        let end = start + LENGTH_PREFIX_LEN + len;
+        if buf.len() < end {
+            break;
+        }
+        frames.push(buf[start + LENGTH_PREFIX_LEN..end].to_vec());
+        start = end;
+    }
+    buf.drain(..start);
+    (frames, is_corrupt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn test_delivers_once_the_peer_comes_up() {
+        let mut socket_addrs = HashMap::new();
+        socket_addrs.insert(1, SocketAddr::from(([127, 0, 0, 1], free_port())));
+        socket_addrs.insert(2, SocketAddr::from(([127, 0, 0, 1], free_port())));
+
+        let mut node1 = TcpTransport::bind(1, socket_addrs.clone());
+        node1.send_to(b"hello", 2);
+        node1.send_to(&vec![7; 1000], 2);
+        // Node 2 isn't listening yet, so node 1 has to reconnect.
+        thread::sleep(Duration::from_millis(50));
+        let mut node2 = TcpTransport::bind(2, socket_addrs);
+
+        let mut buf = [0; MAX_DATAGRAM_SIZE];
+        let mut received = Vec::new();
+        let start = Instant::now();
+        while received.len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            match node2.recv(&mut buf) {
+                Some(amt) => received.push(buf[..amt].to_vec()),
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+        assert_eq!(received, vec![b"hello".to_vec(), vec![7; 1000]]);
+    }
+
+    #[test]
+    fn test_pending_frames_of_a_dead_peer_are_bounded() {
+        let (queue_tx, queue_rx) = mpsc::sync_channel(MAX_QUEUED_PER_PEER);
+        let mut peer = Peer::new(SocketAddr::from(([127, 0, 0, 1], free_port())), queue_rx);
+        for _ in 0..10 {
+            while queue_tx.try_send(vec![0; MAX_DATAGRAM_SIZE]).is_ok() {}
+            peer.take_queued();
+        }
+        assert!(peer.pending.len() < MAX_PENDING_BYTES + LENGTH_PREFIX_LEN + MAX_DATAGRAM_SIZE);
+    }
+
+    #[test]
+    fn test_take_frames_keeps_incomplete_frames() {
+        let mut buf = vec![0, 0, 0, 2, b'h', b'i', 0, 0, 0, 3, b'x'];
+        assert_eq!(take_frames(&mut buf), (vec![b"hi".to_vec()], false));
+        assert_eq!(buf, vec![0, 0, 0, 3, b'x']);
+
+        let mut corrupt = vec![0xff, 0xff, 0xff, 0xff, 0];
+        assert!(take_frames(&mut corrupt).1);
+    }
+}
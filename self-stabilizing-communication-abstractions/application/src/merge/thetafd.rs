@@ -1,48 +1,57 @@
 use super::mergednode::MergedNode;
-use std::collections::HashSet;
-use commons::types::{NodeId, Int};
-use commons::constants;
+use super::transport::Transport;
+use super::payload::Payload;
+use super::wire::WireMessage;
+use commons::types::Int;
+use commons::run_result::TrustChange;
+use crate::failure_detector::{FailureDetector, Detector, FdEvent};
 use crate::urb::thetafd::ThetafdMessage;
 use crate::terminal_output::printlnu;
 use bit_vec::BitVec;
 
-impl MergedNode {
+impl<T: Transport, V: Payload> MergedNode<T, V> {
     //Thetafd
-    pub(crate) fn theta_iter(&self) {
+    pub(crate) fn theta_iter(&mut self) {
         for id in self.node_ids.clone() {
             self.send_theta_hb(id);
         }
     }
     pub fn trusted(&mut self) -> BitVec {
-        let vector = &self.theta_vector;
-        for (idx, x) in vector.iter() {
-            if *x >= constants::THETAFD_W  {
-                if self.current_trusted.get(*idx as usize - 1).unwrap() {
-                    self.current_trusted.set(*idx as usize - 1, false);
-                    printlnu(format!("Node {} is not trusted {:?}", idx, vector));
-                }
+        let trusted = self.failure_detector.trusted();
+        if trusted != self.current_trusted {
+            let heartbeats = self.failure_detector.heartbeats();
+            let events = FdEvent::changes(&self.current_trusted, &trusted, Detector::TRUST, &heartbeats);
+            for event in events.iter() {
+                let (node_id, is_trusted) = match event {
+                    FdEvent::Suspected { node_id, .. } => {
+                        printlnu(format!("Node {} is not trusted {:?}", node_id, heartbeats));
+                        (*node_id, false)
+                    }
+                    FdEvent::Restored { node_id, .. } => {
+                        printlnu(format!("Node {} is trusted again {:?}", node_id, heartbeats));
+                        (*node_id, true)
+                    }
+                };
+                self.run_result.trust_changes.push(TrustChange {
+                    node_id,
+                    trusted: is_trusted,
+                    micros: self.clock.system_now().duration_since(self.start_time).unwrap_or_default().as_micros(),
+                });
             }
+            self.fd_subscribers.publish(events);
+            self.current_trusted = trusted.clone();
         }
-        self.current_trusted.clone()
+        trusted
     }
 
-    pub fn on_theta_hb(&mut self, msg: ThetafdMessage){
-        let sender_id = msg.sender;
-        for(idx, val) in self.theta_vector.iter_mut() {
-            *val = if *idx == sender_id || *idx == self.node_id {
-                0
-            } else {
-//                printlnu(format!("idx {} sender_id {} *val = {} *val + 1 = {} msg {:?}",idx, sender_id, *val, *val+1, msg));
-                *val + 1
-            }
-        }
+    pub fn on_theta_hb(&mut self, msg: &ThetafdMessage){
+        self.failure_detector.on_heartbeat(msg.sender);
     }
 
-    fn send_theta_hb(&self, reciever_id: Int) {
-        let message = ThetafdMessage {sender:self.node_id};
-        let json_msg = serde_json::to_string(&message).expect("Could not serialize theta msg");
-        self.send_json_to(&json_msg, reciever_id);
+    fn send_theta_hb(&mut self, reciever_id: Int) {
+        let message = WireMessage::ThetafdMessage(ThetafdMessage {sender:self.node_id});
+        self.send_wire_message_to(&message, reciever_id);
     }
 
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,181 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, IpAddr, Ipv4Addr};
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use mio::net::UdpSocket;
+use mio::{Poll, PollOpt, Ready, Token};
+
+use commons::types::NodeId;
+use crate::terminal_output::printlnu;
+
+// The link used by a MergedNode to reach the other nodes. A transport
+// only moves opaque datagrams, everything above it (serialization,
+// dispatching to URB/SCD) is handled by the node.
+pub trait Transport: Send {
+    fn send_to(&mut self, bytes: &[u8], receiver_id: NodeId);
+
+    // Copies the next received datagram into buf and returns its length,
+    // or None if nothing is available right now. Must not block.
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize>;
+
+    // Registers for readability, so that the node only wakes up when
+    // there is something to receive. Transports that can't be registered
+    // are polled every tick instead.
+    fn register(&self, _poll: &Poll, _token: Token) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Box<dyn Transport> {
+    fn send_to(&mut self, bytes: &[u8], receiver_id: NodeId) {
+        (**self).send_to(bytes, receiver_id)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize> {
+        (**self).recv(buf)
+    }
+
+    fn register(&self, poll: &Poll, token: Token) -> io::Result<()> {
+        (**self).register(poll, token)
+    }
+}
+
+// When the socket buffer is full, sending is retried after waiting
+// FIRST_SEND_BACKOFF, doubling the wait each time. The datagram is given up
+// after MAX_SEND_ATTEMPTS, which the protocols handle like any other loss.
+const FIRST_SEND_BACKOFF: Duration = Duration::from_micros(10);
+const MAX_SEND_ATTEMPTS: u32 = 10;
+
+pub struct UdpTransport {
+    socket: UdpSocket,
+    socket_addrs: HashMap<NodeId, SocketAddr>,
+    // Whether dropped datagrams are printed.
+    print_drops: bool,
+}
+
+impl UdpTransport {
+    pub fn bind(node_id: NodeId, socket_addrs: HashMap<NodeId, SocketAddr>, print_drops: bool) -> UdpTransport {
+        let port = socket_addrs.get(&node_id).expect("Could not found own socket address.").port();
+        let own_socket_addr = SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(0,0,0,0)),
+            port,
+        );
+        let socket = UdpSocket::bind(&own_socket_addr).expect("Could not create socket.");
+
+        UdpTransport {
+            socket,
+            socket_addrs,
+            print_drops,
+        }
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send_to(&mut self, bytes: &[u8], receiver_id: NodeId) {
+        let dst_socket_addr = self
+            .socket_addrs
+            .get(&receiver_id)
+            .expect("Could not find receiver among the socket addresses");
+        let mut backoff = FIRST_SEND_BACKOFF;
+        for _ in 0..MAX_SEND_ATTEMPTS {
+            match self.socket.send_to(bytes, dst_socket_addr) {
+                Ok(_) => return,
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    if self.print_drops {
+                        printlnu(format!("Unable to send on socket, dropping datagram. Err: {:?}", e));
+                    }
+                    return;
+                }
+            }
+        }
+        if self.print_drops {
+            printlnu(format!("Socket still full after {} attempts, dropping datagram.", MAX_SEND_ATTEMPTS));
+        }
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize> {
+        self.socket.recv(buf).ok()
+    }
+
+    fn register(&self, poll: &Poll, token: Token) -> io::Result<()> {
+        // Level triggered, so that a datagram left in the socket still
+        // wakes up the next wait.
+        poll.register(&self.socket, token, Ready::readable(), PollOpt::level())
+    }
+}
+
+// In-process transport where every node owns the receiving end of an
+// mpsc channel and a sending end to every other node. Used to run several
+// nodes inside one process, for example in tests.
+pub struct ChannelTransport {
+    senders: HashMap<NodeId, Sender<Vec<u8>>>,
+    receiver: Receiver<Vec<u8>>,
+}
+
+impl ChannelTransport {
+    pub fn network(node_ids: &HashSet<NodeId>) -> HashMap<NodeId, ChannelTransport> {
+        let mut senders = HashMap::new();
+        let mut receivers = HashMap::new();
+        for node_id in node_ids {
+            let (tx, rx) = mpsc::channel();
+            senders.insert(*node_id, tx);
+            receivers.insert(*node_id, rx);
+        }
+
+        receivers.into_iter().map(|(node_id, receiver)| {
+            (node_id, ChannelTransport { senders: senders.clone(), receiver })
+        }).collect()
+    }
+}
+
+impl Transport for ChannelTransport {
+    fn send_to(&mut self, bytes: &[u8], receiver_id: NodeId) {
+        let sender = self
+            .senders
+            .get(&receiver_id)
+            .expect("Could not find receiver among the channel senders");
+        // The receiving node may already have stopped, which is the same
+        // as the datagram being lost.
+        let _ = sender.send(bytes.to_vec());
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize> {
+        match self.receiver.try_recv() {
+            Ok(datagram) => {
+                let amt = datagram.len().min(buf.len());
+                buf[..amt].copy_from_slice(&datagram[..amt]);
+                Some(amt)
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_transport_delivers_to_receiver_only() {
+        let node_ids: HashSet<NodeId> = vec![1, 2, 3].into_iter().collect();
+        let mut network = ChannelTransport::network(&node_ids);
+        let mut node1 = network.remove(&1).unwrap();
+        let mut node2 = network.remove(&2).unwrap();
+        let mut node3 = network.remove(&3).unwrap();
+
+        node1.send_to(b"hello", 2);
+
+        let mut buf = [0; 16];
+        assert_eq!(node3.recv(&mut buf), None);
+        assert_eq!(node2.recv(&mut buf), Some(5));
+        assert_eq!(&buf[..5], b"hello");
+        assert_eq!(node2.recv(&mut buf), None);
+    }
+}
@@ -1,21 +1,23 @@
 use super::mergednode::MergedNode;
+use super::transport::Transport;
+use super::payload::{Payload, UrbPayload};
+use super::wire::WireMessage;
 use crate::urb::types::BufferRecord;
 use commons::types::{Int, NodeId, Tag};
 use std::cmp;
 use crate::terminal_output::printlnu;
 use std::collections::{HashSet, HashMap, VecDeque, BTreeSet};
-use crate::urb::messages::{MSG, GOSSIP, MSGAck, json_is_MSG_message, json_is_MSGAck_message, json_is_GOSSIP_message, CombinedGossip};
+use crate::urb::messages::{MSG, GOSSIP, MSGAck, CombinedGossip};
 use std::borrow::Cow;
-use crate::scd::messages::{json_is_SCDGOSSIP_message, SCDGOSSIP, json_is_FORWARD_message};
+use crate::scd::messages::SCDGOSSIP;
 use crate::merge::mergednode::MessageType;
-use crate::settings::SETTINGS;
 use commons::variant::Variant;
-use crate::merge::mergednode::StatusCode;
 use bit_vec::BitVec;
 use std::cmp::{min, Ordering};
 use std::time::Instant;
+use crate::merge::reset::ResetPhase;
 
-impl MergedNode {
+impl<T: Transport, V: Payload> MergedNode<T, V> {
     //Urb
     pub fn urb_loop_iter(&mut self, should_execute_self_stab_statement: bool) {
         if should_execute_self_stab_statement {
@@ -43,10 +45,10 @@ impl MergedNode {
         self.adjust_urb_rxObsS_and_next_to_deliver_if_corrupted();
     }
 
-    fn urb_obsolete(&mut self, record: &BufferRecord<String>, urb_rxObsS: &Vec<Int>) -> bool {
+    fn urb_obsolete(&mut self, record: &BufferRecord<UrbPayload<V>>, urb_rxObsS: &Vec<Int>) -> bool {
         let tag = &record.urb_tag.as_ref().unwrap();
         let mut trusted = self.trusted();
-        let recBy_is_subset = MergedNode::is_subset(&trusted, &record.recBy);
+        let recBy_is_subset = Self::is_subset(&trusted, &record.recBy);
 
 
         let obs = urb_rxObsS[tag.id as usize - 1] + 1 == tag.seq &&
@@ -57,16 +59,7 @@ impl MergedNode {
     }
 
     pub(crate) fn urb_maxSeq(&self, node_id: NodeId) -> Int {
-        let mut max_seq = 0;
-        for record in self.buffer.iter() {
-            if let Some(urb_tag) = &record.urb_tag {
-                if urb_tag.id == node_id {
-                    max_seq = cmp::max(max_seq, urb_tag.seq);
-                }
-            }
-        }
-        max_seq = cmp::max(max_seq, self.next_to_deliver[node_id as usize - 1] - 1);
-        max_seq
+        cmp::max(self.buffer.urb_max_seq(node_id), self.next_to_deliver[node_id as usize - 1] - 1)
     }
 
     pub(crate) fn min_urb_TxObsS(&mut self) -> Int {
@@ -87,31 +80,25 @@ impl MergedNode {
         min_s
     }
 
-    pub fn urb_broadcast(&mut self, msg: String) {
+    pub fn urb_broadcast(&mut self, msg: V) -> Option<Tag> {
         //self.wait_until_receivers_have_enough_space();
         if !self.urb_available_space() {
-            let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::ErrNoSpace);
-            return;
-        }
-        match SETTINGS.variant() {
-            Variant::URB => {
-                let _ = self.bcast_status.as_ref().unwrap().send(StatusCode::Ok);
-            },
-            _ => {}
+            return None;
         }
 
         self.seq += 1;
         let tag = Tag { id: self.node_id, seq: self.seq };
-        if SETTINGS.print_client_operations() {
+        if self.config.print_client_operations {
             printlnu(format!("urbBroadcasting: {:?} | {:?}", msg.clone(), tag.clone()));
         }
         self.log(format!("urbBroadcasting: {:?} | {:?}", msg.clone(), tag.clone()));
-        self.update(Some(msg), tag, self.node_id, None, None);
+        self.update(Some(UrbPayload::MESSAGE(msg)), tag.clone(), self.node_id, None, None);
+        Some(tag)
     }
 
-    pub fn non_blocking_urb_broadcast(&mut self, msg: String, index: Int) -> Option<Tag> {
+    pub fn non_blocking_urb_broadcast(&mut self, msg: UrbPayload<V>, index: Int) -> Option<Tag> {
         if self.urb_available_space() {
-            let record = self.buffer.get_mut(index as usize).unwrap();
+            let mut record = self.buffer.get_mut(index as usize).unwrap();
             let urb_tag = if record.urb_tag.is_none() {
                 self.seq += 1;
                 record.urb_tag = Some( Tag { id: self.node_id, seq: self.seq });
@@ -120,7 +107,8 @@ impl MergedNode {
                 self.seq += 1;
                 Tag { id: self.node_id, seq: self.seq }
             };
-            if SETTINGS.print_client_operations() {
+            drop(record);
+            if self.config.print_client_operations {
                 printlnu(format!("urbBroadcasting: {:?}, sn: {}, scdRxObsS: {:?}, scdTxObsS: {:?}, urb_rxObsS: {:?}", urb_tag, self.sn, self.scd_rxObsS, self.scd_txObsS, self.urb_rxObsS));
             }
             self.log(format!("urbBroadcasting: {:?}, sn: {}, scdRxObsS: {:?}, scdTxObsS: {:?}, urb_rxObsS: {:?}", urb_tag, self.sn, self.scd_rxObsS, self.scd_txObsS, self.urb_rxObsS));
@@ -131,33 +119,37 @@ impl MergedNode {
 
     }
 
-    pub fn urb_deliver(&mut self, tag: &Tag, msg: String) {
-        if SETTINGS.print_client_operations(){
+    pub fn urb_deliver(&mut self, tag: &Tag, msg: UrbPayload<V>) {
+        if self.config.print_client_operations{
 //            printlnu(format!("New message delivered: {:?} {:?}", msg, self.buffer.get(&tag).unwrap()));
             printlnu(format!("New message delivered: {:?} {:?}", msg, tag));
         }
-        match SETTINGS.variant() {
-            Variant::URB => {
+        match (self.config.variant, msg) {
+            (Variant::URB, UrbPayload::MESSAGE(msg)) => {
                 self.run_result.urb_delivered_msgs.insert(tag.clone());
                 self.delivered_tags.insert(tag.clone());
+                self.deliver_urb_to_application(tag.clone(), msg);
                 if self.has_failed && tag.id == self.node_id {
                     if let Some(fail_t) = self.fail_time {
                         if self.run_result.recovery_time.is_none() {
-                            printlnu(format!("Recovered in {} ms", fail_t.elapsed().as_millis()));
-                            self.run_result.recovery_time = Some(fail_t.elapsed().as_micros());
+                            let recovery_time = self.clock.elapsed(fail_t);
+                            printlnu(format!("Recovered in {} ms", recovery_time.as_millis()));
+                            self.run_result.recovery_time = Some(recovery_time.as_micros());
                         }
                     }
                 }
             },
-            _ => {
-                self.scd_msg_received(msg);
+            (Variant::URB, UrbPayload::FORWARD(_)) => {}
+            (_, UrbPayload::FORWARD(forward_msg)) => {
+                self.scd_msg_received(forward_msg);
             }
+            (_, UrbPayload::MESSAGE(_)) => {}
         }
     }
 
     pub fn urb_buffer_unit_size(&self) -> Int {
-        match SETTINGS.variant() {
-            Variant::URB => {SETTINGS.window_size()}
+        match self.config.variant {
+            Variant::URB => {self.config.window_size}
             _ => {self.scd_buffer_unit_size() * self.node_ids.len() as i32}
         }
     }
@@ -178,7 +170,7 @@ impl MergedNode {
     fn wait_until_receivers_have_enough_space(&mut self) {
         let mut seq = self.seq;
         while seq >= self.min_urb_TxObsS() + self.urb_buffer_unit_size() {
-            if !SETTINGS.record_evaluation_info() {
+            if !self.config.record_evaluation_info {
                 panic!("Receiver does not have enough space, going to block. min_urb_TxObsS = {}, seq = {}", self.min_urb_TxObsS(), seq);
             } else {
                 printlnu(format!("Receiver does not have enough space, going to block. min_urb_TxObsS = {}, seq = {}", self.min_urb_TxObsS(), seq));
@@ -186,7 +178,7 @@ impl MergedNode {
         }
     }
 
-    fn update(&mut self, msg: Option<String>, tag: Tag, forwarder: NodeId, recv_by_bitmap: Option<BitVec>, recv_by_trusted_bitmap: Option<BitVec>) -> bool {
+    fn update(&mut self, msg: Option<UrbPayload<V>>, tag: Tag, forwarder: NodeId, recv_by_bitmap: Option<BitVec>, recv_by_trusted_bitmap: Option<BitVec>) -> bool {
         let sn = self.sn;
         let scd_unit = self.scd_buffer_unit_size();
         let urb_unit = self.urb_buffer_unit_size();
@@ -221,8 +213,8 @@ impl MergedNode {
                 scd_needed: true,
                 meta: None,
                 creation_instant:
-                    if SETTINGS.variant() == Variant::URB && tag.id == self.node_id {
-                        Some(Instant::now())
+                    if self.config.variant == Variant::URB && tag.id == self.node_id {
+                        Some(self.clock.now())
                     } else {
                         None
                     }
@@ -232,13 +224,13 @@ impl MergedNode {
         } else {
 //            printlnu(format!("index: {:?} msg {:?} buffer {:?}", index, msg, buffer));
             if index.is_some() {
-                if let Some(record) = buffer.get_mut(index.unwrap()) {
+                if let Some(mut record) = buffer.get_mut(index.unwrap()) {
                     record.recBy.set(tag.id as usize - 1, true);
                     record.recBy.set(forwarder as usize - 1, true);
                     // Optimization
                     if let Some(mut recv_by) = recv_by_bitmap {
                         record.recBy.or(&recv_by);
-                        let recBy_is_subset = MergedNode::is_subset(&trusted, &recv_by);
+                        let recBy_is_subset = Self::is_subset(&trusted, &recv_by);
                         if recBy_is_subset {
                             record.recBy_trusted.set(tag.id as usize - 1, true);
                             record.recBy_trusted.set(forwarder as usize - 1, true);
@@ -248,7 +240,7 @@ impl MergedNode {
                             record.recBy_trusted.or(&recv_by_trusted);
                         }
                     }
-                    if MergedNode::is_subset(&trusted, &record.recBy) {
+                    if Self::is_subset(&trusted, &record.recBy) {
                         record.recBy_trusted.set(self.node_id as usize - 1, true);
                     }
                     return true;
@@ -272,16 +264,16 @@ impl MergedNode {
             }
         }
         if buffer_corrupted {
-            if SETTINGS.print_client_operations() {
+            if self.config.print_client_operations {
                 printlnu(format!("Buffer corrupted! Emptying the buffer."));
             }
-            self.buffer = Vec::new();
-            if !SETTINGS.record_evaluation_info() {
+            self.buffer.clear();
+            if !self.config.record_evaluation_info {
                 panic!("Buffer was corrupted and then emptied");
             } else {
                 printlnu(format!("Buffer was corrupted and then emptied"));
             }
-            self.run_result.illegally_triggered_ss = !SETTINGS.is_failing_node();
+            self.run_result.illegally_triggered_ss = !self.config.is_failing_node;
             self.log(format!("Buffer was corrupted and then emptied"));
         }
     }
@@ -290,16 +282,16 @@ impl MergedNode {
         let ms = self.min_urb_TxObsS().clone();
         let seq_reasonable = self.seq >= ms && self.seq <= ms + self.urb_buffer_unit_size() * self.node_ids.len() as i32;
         if !seq_reasonable {
-            if SETTINGS.print_client_operations() {
+            if self.config.print_client_operations {
                 printlnu(format!("Seq not resonable! seq: {}, ms: {}", self.seq, ms));
             }
             self.uniform_urb_txObsS(self.seq);
-            if !SETTINGS.record_evaluation_info() {
+            if !self.config.record_evaluation_info {
                 panic!("panic uniform");
             } else {
                 printlnu(format!("panic: Seq not resonable! seq: {}, ms: {}", self.seq, ms));
             }
-            self.run_result.illegally_triggered_ss = !SETTINGS.is_failing_node();
+            self.run_result.illegally_triggered_ss = !self.config.is_failing_node;
             self.log(format!("Seq not resonable! seq: {}, ms: {}", self.seq, ms));
             return;
         }
@@ -320,17 +312,17 @@ impl MergedNode {
         let all_seqs_in_process_are_present = seqs_should_be_in_buffer.is_empty();
 
         if !all_seqs_in_process_are_present {
-            if SETTINGS.print_client_operations() {
+            if self.config.print_client_operations {
                 printlnu(format!("Not all urb seqs are present. missing: {:?} ms: {}", seqs_should_be_in_buffer, ms + 1));
             }
-            if !SETTINGS.record_evaluation_info() {
+            if !self.config.record_evaluation_info {
                 printlnu(format!("Not all urb seqs are present. missing: {:?} ms: {} tags seen {:?}", seqs_should_be_in_buffer, ms + 1, tags_seen));
                 panic!("Node {} urb panic seq missing Not all urb seqs are present. missing: {:?} ms: {} , current seq {} {:?}", self.node_id, seqs_should_be_in_buffer, ms + 1, self.seq + 1, self.urb_txObsS);
             } else {
                 printlnu(format!("Node {} urb panic seq missing Not all urb seqs are present. missing: {:?} ms: {} , current seq {} tx {:?} tags seen {:?}", self.node_id, seqs_should_be_in_buffer, ms + 1, self.seq + 1, self.urb_txObsS, tags_seen));
             }
             self.log(format!("urb seq missing Not all urb seqs are present. missing: {:?} ms: {} , current seq {} {:?}", seqs_should_be_in_buffer, ms + 1, self.seq + 1, self.urb_txObsS));
-            self.run_result.illegally_triggered_ss = !SETTINGS.is_failing_node();
+            self.run_result.illegally_triggered_ss = !self.config.is_failing_node;
             self.uniform_urb_txObsS(self.seq);
         }
     }
@@ -344,10 +336,10 @@ impl MergedNode {
         for node_id in self.node_ids.clone() {
             let at_least = self.urb_maxSeq(node_id) - self.urb_buffer_unit_size();
             if self.urb_rxObsS[(node_id - 1) as usize] < at_least {
-                if SETTINGS.print_client_operations() {
+                if self.config.print_client_operations {
                     printlnu(format!("urb_rxObsS corrupted. current value: {}, at_least: {}", self.urb_rxObsS[(node_id - 1) as usize], at_least));
                 }
-                self.run_result.illegally_triggered_ss = !SETTINGS.is_failing_node();
+                self.run_result.illegally_triggered_ss = !self.config.is_failing_node;
                 self.log(format!("urb_rxObsS corrupted. current value: {}, at_least: {}", self.urb_rxObsS[(node_id - 1) as usize], at_least));
             }
             self.urb_rxObsS[(node_id - 1) as usize] = cmp::max(at_least, self.urb_rxObsS[(node_id - 1) as usize]);
@@ -379,7 +371,7 @@ impl MergedNode {
             }
         }
         if rx_clone != self.urb_rxObsS {
-            if SETTINGS.print_client_operations() {
+            if self.config.print_client_operations {
                 printlnu(format!("(urb) rxObsS updated from: {:?} to {:?}", rx_clone, self.urb_rxObsS));
             }
             self.log(format!("(urb) rxObsS updated from: {:?} to {:?}", rx_clone, self.urb_rxObsS));
@@ -405,7 +397,8 @@ impl MergedNode {
 
         //self.buffer.retain(|record| {
 //            return true;
-        for record in self.buffer.iter_mut() {
+        for mut record in self.buffer.iter_mut() {
+            let record = &mut *record;
             if let Some(urb_tag) = &record.urb_tag {
                 let id = urb_tag.id;
                 let seq = urb_tag.seq;
@@ -423,9 +416,10 @@ impl MergedNode {
             }
         }
         let mut logvec = VecDeque::new();
+        let print_client_operations = self.config.print_client_operations;
         self.buffer.retain(| r|{
             if !r.urb_needed && !r.meta.is_some() {
-                if SETTINGS.print_client_operations() {
+                if print_client_operations {
                     printlnu(format!("(urb) Removing record, min_tx: {} record: {:?}", min_urb_TxObsS, r));
                 }
                 logvec.push_back(format!("(urb) Removing min_tx: {} record: {:?}", min_urb_TxObsS, r));
@@ -475,23 +469,26 @@ impl MergedNode {
         let low_size = cmp::max((self.urb_buffer_unit_size() as f32 * 0.3) as i32, 0);
         let high_size = cmp::max((self.urb_buffer_unit_size() as f32 * 0.7) as i32, 1);
 
-        if buf_size >= high_size && self.throughput_instant.is_none() && SETTINGS.variant() == Variant::URB {
-            self.throughput_instant = Some(Instant::now());
+        if buf_size >= high_size && self.throughput_instant.is_none() && self.config.variant == Variant::URB {
+            self.throughput_instant = Some(self.clock.now());
             self.throughput_msgs = Some(Vec::new());
         }
+        let reset = self.reset_phase();
+        let reset_epoch = self.reset_epoch();
         for mut record in self.buffer.iter_mut() {
+            let record = &mut *record;
             if record.urb_tag.is_some() {
                 let urb_tag = record.urb_tag.as_ref().unwrap();
 
-//                let ack_by_trusted = MergedNode::is_subset(&trusted, &record.recBy);
-                let ack_by_majority_trusted = MergedNode::urb_is_ack_by_majority(&trusted, &record.recBy);
+//                let ack_by_trusted = Self::is_subset(&trusted, &record.recBy);
+                let ack_by_majority_trusted = Self::urb_is_ack_by_majority(&trusted, &record.recBy);
                 if ack_by_majority_trusted && !record.delivered && urb_tag.seq == self.next_to_deliver[urb_tag.id as usize - 1] {
                     let msg = record.msg.clone();
                     //self.urbDeliver(&tag, msg.unwrap());
-                    if SETTINGS.print_client_operations(){
+                    if self.config.print_client_operations{
                         printlnu(format!("urbDelivering: {:?}, sn: {}, scdRxObsS: {:?}, scdTxObsS: {:?}, buffelen: {}", record.urb_tag, self.sn, self.scd_rxObsS, self.scd_txObsS, len));
                     }
-                    if self.throughput_msgs.is_some() && SETTINGS.variant() == Variant::URB {
+                    if self.throughput_msgs.is_some() && self.config.variant == Variant::URB {
                         self.throughput_msgs.as_mut().unwrap().push(urb_tag.clone());
                     }
                     record.delivered = true;
@@ -546,6 +543,8 @@ impl MergedNode {
                                 urb_maxSeq: *urb_maxSeq,
                                 urb_rxObsS: urb_rxObsS_for_id,
                                 urb_txObsS: urb_txObsS_for_id,
+                                reset,
+                                reset_epoch,
                             },
                             scd_gossip: SCDGOSSIP {
                                 sender: self.node_id,
@@ -558,7 +557,7 @@ impl MergedNode {
                             },
                         };
                         let msg = MSG { sender: self.node_id, msg: Cow::Borrowed(&record.msg), tag: urb_tag.clone(), recv_by: record.recBy.to_bytes(), recv_by_trusted: record.recBy_trusted.to_bytes(), gossip };
-                        send_vec.push_back((serde_json::to_string(&msg).expect(""), node_id.clone()));
+                        send_vec.push_back((WireMessage::MSG(msg).encode(self.config.encoding), node_id.clone()));
                         // printlnu(format!("Sending buffer record msg to {}: {:?}", *node_id, &tag));
                         prevHB[node_id as usize -1] = hb.clone()[node_id as usize - 1];
                     }
@@ -566,12 +565,12 @@ impl MergedNode {
             }
         }
 
-        if buf_size <= low_size && self.throughput_instant.is_some() && SETTINGS.variant() == Variant::URB {
+        if buf_size <= low_size && self.throughput_instant.is_some() && self.config.variant == Variant::URB {
             if self.run_result.throughputs.is_none() {
                 self.run_result.throughputs = Some(Vec::new());
             }
             let msgs = self.throughput_msgs.as_ref().unwrap().len() as f64;
-            let time = self.throughput_instant.as_ref().unwrap().elapsed().as_micros() as f64;
+            let time = self.clock.elapsed(self.throughput_instant.unwrap()).as_micros() as f64;
             self.run_result.throughputs.as_mut().unwrap().push((msgs / time) * 1000000.0);
             self.throughput_instant = None;
             self.throughput_msgs = None;
@@ -584,25 +583,25 @@ impl MergedNode {
             }
             self.urb_deliver(&tag, msg);
             if let Some(instant) = record.creation_instant {
-                self.run_result.msg_latencies.as_mut().unwrap().push(instant.elapsed().as_micros());
+                self.run_result.msg_latencies.as_mut().unwrap().push(self.clock.elapsed(instant).as_micros());
             }
         }
 
-        while let Some((json_msg, node_id)) = send_vec.pop_front() {
-            self.send_json_message_to(&json_msg, node_id);
+        while let Some((bytes, node_id)) = send_vec.pop_front() {
+            self.send_bytes_to(bytes, node_id);
             //self.gossip_sent[node_id as usize - 1] = true;
         }
     }
 
-    fn victory_round(&mut self, tag: &Tag, record: &BufferRecord<String>) {
+    fn victory_round(&mut self, tag: &Tag, record: &BufferRecord<UrbPayload<V>>) {
         let trusted = self.trusted();
-        if !MergedNode::is_subset(&trusted, &record.recBy) {
+        if !Self::is_subset(&trusted, &record.recBy) {
             for node_id in self.node_ids.clone() {
                 let urb_maxSeq = self.urb_maxSeq(node_id);
                 let urb_rxObsS_for_id = self.urb_rxObsS[node_id as usize - 1];
                 let urb_txObsS_for_id = self.urb_txObsS[node_id as usize - 1];
 
-                let gossip = match SETTINGS.variant() {
+                let gossip = match self.config.variant {
                     Variant::URB => {
                         CombinedGossip {
                             urb_gossip: GOSSIP {
@@ -610,6 +609,8 @@ impl MergedNode {
                                 urb_maxSeq: urb_maxSeq,
                                 urb_rxObsS: urb_rxObsS_for_id,
                                 urb_txObsS: urb_txObsS_for_id,
+                                reset: self.reset_phase(),
+                                reset_epoch: self.reset_epoch(),
                             },
                             scd_gossip: SCDGOSSIP {
                                 sender: self.node_id,
@@ -650,6 +651,8 @@ impl MergedNode {
                                 urb_maxSeq: urb_maxSeq,
                                 urb_rxObsS: urb_rxObsS_for_id,
                                 urb_txObsS: urb_txObsS_for_id,
+                                reset: self.reset_phase(),
+                                reset_epoch: self.reset_epoch(),
                             },
                             scd_gossip: SCDGOSSIP {
                                 sender: self.node_id,
@@ -672,15 +675,14 @@ impl MergedNode {
                     recv_by_trusted: record.recBy_trusted.to_bytes(),
                     gossip: gossip
                 };
-                let json_msg = serde_json::to_string(&msg).unwrap();
-                self.send_json_message_to(&json_msg, node_id);
+                self.send_wire_message_to(&WireMessage::MSG(msg), node_id);
             }
         }
     }
 
     fn gossip(&mut self) {
-        let urb_rxObsS = &self.urb_rxObsS;
-        let urb_txObsS = &self.urb_txObsS;
+        let urb_rxObsS = self.urb_rxObsS.clone();
+        let urb_txObsS = self.urb_txObsS.clone();
 
         for node_id in self.node_ids.clone() {
             if node_id != self.node_id
@@ -688,16 +690,15 @@ impl MergedNode {
                 let urb_maxSeq = self.urb_maxSeq(node_id);
                 let urb_rxObsS_for_id = urb_rxObsS[node_id as usize - 1];
                 let urb_txObsS_for_id = urb_txObsS[node_id as usize - 1];
-                let gossip_msg = GOSSIP { sender: self.node_id, urb_maxSeq: urb_maxSeq, urb_rxObsS: urb_rxObsS_for_id, urb_txObsS: urb_txObsS_for_id };
+                let gossip_msg = GOSSIP { sender: self.node_id, urb_maxSeq: urb_maxSeq, urb_rxObsS: urb_rxObsS_for_id, urb_txObsS: urb_txObsS_for_id, reset: self.reset_phase(), reset_epoch: self.reset_epoch() };
     //            printlnu(format!("urb_sending gossip {:?}", gossip_msg));
-                let json_msg = self.jsonify_message(&gossip_msg);
-                self.send_json_message_to(&json_msg, node_id);
+                self.send_wire_message_to(&WireMessage::GOSSIP(gossip_msg), node_id);
             }
         }
         let urb_maxSeq = self.urb_maxSeq(self.node_id);
         let urb_rxObsS_for_id = urb_rxObsS[self.node_id as usize - 1];
         let urb_txObsS_for_id = urb_txObsS[self.node_id as usize - 1];
-        let gossip_msg = GOSSIP { sender: self.node_id, urb_maxSeq: urb_maxSeq, urb_rxObsS: urb_rxObsS_for_id, urb_txObsS: urb_txObsS_for_id };
+        let gossip_msg = GOSSIP { sender: self.node_id, urb_maxSeq: urb_maxSeq, urb_rxObsS: urb_rxObsS_for_id, urb_txObsS: urb_txObsS_for_id, reset: self.reset_phase(), reset_epoch: self.reset_epoch() };
         self.GOSSIP_received(gossip_msg);
     }
 
@@ -705,7 +706,7 @@ impl MergedNode {
         // urb
         self.GOSSIP_received(gossip.urb_gossip);
         // scd
-        if SETTINGS.variant() != Variant::URB {
+        if self.config.variant != Variant::URB {
             self.SCDGOSSIP_received(gossip.scd_gossip);
         }
     }
@@ -714,7 +715,7 @@ impl MergedNode {
     // Message reception triggered events.
     //
 
-    fn MSG_received(&mut self, msg: MSG<String>) {
+    fn MSG_received(&mut self, msg: MSG<UrbPayload<V>>) {
         // printlnu(format!("Sending ack to {} about tag: {:?} ", msg.sender, msg.tag));
         let mut recv_by_bitvec = BitVec::from_bytes(&msg.recv_by);
         recv_by_bitvec.truncate(self.node_ids.len());
@@ -737,8 +738,7 @@ impl MergedNode {
         }
         let ack = MSGAck {sender: self.node_id, tag: tag_clone, recv_by: ack_recv.to_bytes() };
         // self.buffer_updated.notify_one();
-        let json_ack = self.jsonify_message(&ack);
-        self.send_json_message_to(&json_ack, msg.sender);
+        self.send_wire_message_to(&WireMessage::MSGAck(ack), msg.sender);
 
     }
 
@@ -751,6 +751,11 @@ impl MergedNode {
     }
 
     fn GOSSIP_received(&mut self, msg: GOSSIP) {
+        self.global_reset.on_gossip(msg.sender, msg.reset_epoch, msg.reset);
+        // The counters of nodes that haven't reset yet would undo the reset.
+        if self.reset_phase() == ResetPhase::RESET {
+            return;
+        }
         let copy_seq = &self.seq.clone();
         self.seq = cmp::max(self.seq, msg.urb_maxSeq);
 
@@ -758,7 +763,7 @@ impl MergedNode {
         let copy_rx = self.urb_rxObsS.clone();
 
         if self.seq != *copy_seq {
-            if SETTINGS.print_client_operations() {
+            if self.config.print_client_operations {
                 printlnu(format!("updated seq in GOSSIP before: {} after {}", copy_seq, self.seq));
             }
         }
@@ -779,12 +784,12 @@ impl MergedNode {
         let tx_clone = urb_txObsS.clone();
         let rx_changed = copy_rx.cmp(&rx_clone) != Ordering::Equal;
         if  rx_changed {
-            if SETTINGS.print_client_operations() {
+            if self.config.print_client_operations {
                 printlnu(format!("before: rxObsS for {} = {}", msg.sender - 1, urb_rxObsS[msg.sender as usize - 1]));
             }
         }
         if copy_tx.cmp(&tx_clone) != Ordering::Equal {
-            if SETTINGS.print_client_operations() {
+            if self.config.print_client_operations {
                 printlnu(format!("after: rxObsS for {} = {}", msg.sender - 1, urb_rxObsS[msg.sender as usize - 1]));
             }
             self.log(format!("(urb) txObsS updated from:{:?} to:{:?}", copy_tx, tx_clone));
@@ -806,26 +811,10 @@ impl MergedNode {
                         // Handle at most 100 message for each channel to avoid starving.
                         for counter in 0..10000 {
                             match rx.try_recv() {
-                                Ok(json) => {
-                                    if json_is_MSG_message(&json) {
-                                        if let Ok(MSG_message) = serde_json::from_str(&json) {
-                                            msg_vector.push_back(MSG_message);
-                                        }
-                                        continue;
-                                    }
-                                    if json_is_MSGAck_message(&json) {
-                                        if let Ok(MSGAck_message) = serde_json::from_str(&json) {
-                                            msg_ack_vector.push_back(MSGAck_message);
-                                        }
-                                        continue;
-                                    }
-                                    if json_is_FORWARD_message(&json) {
-                                        if let Ok(Forward_message) = serde_json::from_str(&json) {
-                                            forward_vector.push_back(Forward_message);
-                                        }
-                                        continue;
-                                    }
-                                }
+                                Ok(WireMessage::MSG(MSG_message)) => msg_vector.push_back(MSG_message),
+                                Ok(WireMessage::MSGAck(MSGAck_message)) => msg_ack_vector.push_back(MSGAck_message),
+                                Ok(WireMessage::FORWARD(Forward_message)) => forward_vector.push_back(Forward_message),
+                                Ok(_) => continue,
                                 Err(_) => break,
                             }
                         }
@@ -835,6 +824,11 @@ impl MergedNode {
                 _ => {}
             }
         }
+        // Until everyone has reset, messages can still come from nodes that
+        // have not, their records and gossip would undo the reset.
+        if self.reset_phase() == ResetPhase::RESET {
+            return;
+        }
         while let Some(msg) = msg_vector.pop_front() {
             self.MSG_received(msg);
         }
@@ -855,22 +849,9 @@ impl MergedNode {
                     for (node_id, rx) in rxs {
                         // There is only one slot for GOSSIP messages.
                         match rx.try_recv() {
-                            Ok(json) => {
-                                if json_is_GOSSIP_message(&json) {
-                                    if let Ok(GOSSIP_message) = serde_json::from_str(&json) {
-//                                        printlnu(format!("urb_gossip recv {:?}", GOSSIP_message));
-                                        gossip.push_back(GOSSIP_message);
-                                    }
-                                    continue;
-                                }
-                                if json_is_SCDGOSSIP_message(&json) {
-                                    if let Ok(GOSSIP_message) = serde_json::from_str(&json) {
-                                        scd_gossip.push_back(GOSSIP_message);
-                                    }
-                                }
-                                continue;
-                            },
-                            Err(_) => continue,
+                            Ok(WireMessage::GOSSIP(GOSSIP_message)) => gossip.push_back(GOSSIP_message),
+                            Ok(WireMessage::SCDGOSSIP(GOSSIP_message)) => scd_gossip.push_back(GOSSIP_message),
+                            Ok(_) | Err(_) => continue,
                         }
                     }
                 },
@@ -887,17 +868,14 @@ impl MergedNode {
     }
 
     pub fn urb_has_terminated(&self, urb_tag: Tag) -> bool {
-        for record in self.buffer.iter() {
-            if record.urb_tag.is_some() {
-                let tag = record.urb_tag.clone().unwrap();
-                if tag == urb_tag {
-                    if !record.delivered {
-                        return false;
-                    }
-                    if let Some(meta) = MergedNode::parse_meta(&record.meta) {
-                        if !meta.delivered {
-                            //return false;
-                        }
+        for index in self.buffer.positions_of_urb_tag(&urb_tag) {
+            if let Some(record) = self.buffer.get(index) {
+                if !record.delivered {
+                    return false;
+                }
+                if let Some(meta) = &record.meta {
+                    if !meta.delivered {
+                        //return false;
                     }
                 }
             }
@@ -1,10 +1,11 @@
 use super::mergednode::MergedNode;
+use super::transport::Transport;
+use super::payload::{Payload, UrbPayload};
 use commons::types::{Tag, Int};
 use crate::scd::messages::{SCDMETA, FORWARD};
-use crate::settings::SETTINGS;
 use std::borrow::Borrow;
 use std::iter::FromIterator;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use crate::terminal_output::printlnu;
 use std::time::SystemTime;
 use crate::scd::types::VectorClock;
@@ -13,75 +14,37 @@ use core::cmp;
 use std::collections::HashMap;
 use commons::variant::Variant;
 
-impl MergedNode {
+impl<T: Transport, V: Payload> MergedNode<T, V> {
     pub fn get_index_by_urb_tag(&mut self, urb_tag: &Tag) -> Option<usize> {
-        self.buffer.iter().position(|record|
-            record.urb_tag == Some(urb_tag.clone())
-        )
+        self.buffer.position_of_urb_tag(urb_tag)
     }
 
     pub fn set_scd_meta(&mut self, index: i32, meta: SCDMETA) {
-        if let Some(record) = self.buffer.get_mut(index as usize) {
-            let meta_s = serde_json::to_string(&meta);
-            record.meta = Some(meta_s.unwrap());
+        if let Some(mut record) = self.buffer.get_mut(index as usize) {
+            record.meta = Some(meta);
         }
     }
 
     fn get_scd_tag(&self, tag: &Tag) -> Option<Tag> {
-        for record in self.buffer.iter() {
-            if let Some(urb_tag) = &record.urb_tag {
-                if urb_tag == tag {
-                    if let Some(meta) = MergedNode::parse_meta(&record.meta) {
-                        return Some(meta.tag);
-                    }
-                }
-            }
-        }
-        None
+        let index = self.buffer.position_of_urb_tag(tag)?;
+        self.buffer.get(index)?.meta.as_ref().map(|meta| meta.tag.clone())
     }
 
     pub fn get_urb_tag(&self, scd_tag: &Tag) -> Option<Tag> {
-        for record in self.buffer.iter() {
-            if let Some(meta) = MergedNode::parse_meta(&record.meta) {
-                if meta.tag.id == scd_tag.id && meta.cl.get(scd_tag.id) == scd_tag.seq && record.urb_tag.is_some() {
-                    return Some(record.urb_tag.as_ref().unwrap().clone());
-                }
-            }
-        }
-        None
+        let index = self.buffer.position_of_scd_tag(scd_tag)?;
+        self.buffer.get(index)?.urb_tag.clone()
     }
 
     pub fn get_urb_index(&self, scd_tag: &Tag) -> Option<Int> {
-        let mut index = 0;
-        for record in self.buffer.iter() {
-            if let Some(meta) = MergedNode::parse_meta(&record.meta) {
-                if meta.tag.id == scd_tag.id && meta.cl.get(scd_tag.id) == scd_tag.seq {
-                    return Some(index)
-                }
-            }
-            index += 1;
-        }
-        None
+        self.buffer.position_of_scd_tag(scd_tag).map(|index| index as Int)
     }
 
-    pub fn parse_meta(meta: &Option<String>) -> Option<SCDMETA> {
-        if let Some(meta_s) = meta {
-            if let Ok(scd_meta) = serde_json::from_str::<SCDMETA>(&meta_s) {
-                return Some(scd_meta);
-            }
-        }
-        None
-    }
-
-    pub fn parse_forward_msg(msg: &Option<String>) -> Option<FORWARD> {
-        if let Ok(parsed_msg) = serde_json::from_str::<FORWARD>(msg.as_ref().unwrap()) {
-            return Some(parsed_msg);
-        }
-        None
+    pub fn parse_forward_msg(msg: &Option<UrbPayload<V>>) -> Option<&FORWARD<V>> {
+        msg.as_ref().and_then(UrbPayload::forward)
     }
 
     pub fn log(&mut self, message: String) {
-        if SETTINGS.record_evaluation_info() {
+        if self.config.record_evaluation_info {
             return;
             self.run_result.log.push((self.start_time.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs(), format!("[Node {}]: {}", self.node_id, message)));
         }
@@ -157,13 +120,12 @@ impl MergedNode {
     }
 
     pub fn corrupt_variables(&mut self) {
-        let mut rng = thread_rng();
-        match SETTINGS.variant() {
+        match self.config.variant {
 
             Variant::URB => {
                 let seq_copy = self.seq.clone();
                 while self.seq == seq_copy {
-                    self.seq = rng.gen_range(0, 10000);
+                    self.seq = self.rng.gen_range(0, 10000);
                 }
                 let ms = self.min_urb_TxObsS().clone();
                 printlnu(format!("Corrupted seq was:{} into: {}, ms: {}", seq_copy, self.seq, ms));
@@ -172,7 +134,7 @@ impl MergedNode {
             _ => {
                 let sn_copy = self.sn.clone();
                 while self.sn == sn_copy {
-                    self.sn = rng.gen_range(0, 1000);
+                    self.sn = self.rng.gen_range(0, 1000);
                 }
                 printlnu(format!("Corrupted sn was:{} into: {}", sn_copy, self.sn));
                 self.log(format!("Corrupted sn was:{} into: {}", sn_copy, self.sn));
@@ -182,37 +144,23 @@ impl MergedNode {
 
     pub fn duplicate_records(&mut self) {
         let buffer_len = self.buffer.len();
-        let mut rng = thread_rng();
-        let random_index = rng.gen_range(0,  buffer_len);
-        let record = self.buffer[random_index].clone();
+        let random_index = self.rng.gen_range(0,  buffer_len);
+        let record = self.buffer.get(random_index).unwrap().clone();
         self.buffer.push(record);
     }
 
     pub fn modify_records(&mut self) {
         let buffer_len = self.buffer.len();
-        let mut rng = thread_rng();
-        let random_index = rng.gen_range(0,  buffer_len);
-        if let Some(record) = self.buffer.get_mut(random_index) {
+        let random_index = self.rng.gen_range(0,  buffer_len);
+        if let Some(mut record) = self.buffer.get_mut(random_index) {
             record.msg = None;
         }
     }
 
     pub fn modify_clocks(&mut self) {
-        let mut index = 0;
-        let mut meta_vec = Vec::new();
-        for record in self.buffer.iter() {
-            if record.meta.is_some() {
-                if let Some(mut scd_meta) = MergedNode::parse_meta(&record.meta) {
-                    scd_meta.cl = VectorClock::new(self.node_ids.len(), Int::max_value());
-                    meta_vec.push((index, scd_meta.clone()));
-                    break;
-                }
-            }
-            index += 1;
-        }
-
-        for (index, meta) in meta_vec  {
-            self.set_scd_meta(index, meta);
+        let number_of_nodes = self.node_ids.len();
+        if let Some(mut record) = self.buffer.iter_mut().find(|record| record.meta.is_some()) {
+            record.meta.as_mut().unwrap().cl = VectorClock::new(number_of_nodes, Int::max_value());
         }
     }
 
@@ -243,23 +191,6 @@ impl MergedNode {
         trusted_clone.iter().filter(|x| *x).count() < cmp::max(current_trusted_num / 2, 1)
     }
 
-    pub(crate) fn reg_pretty(&mut self) -> String {
-        let mut reg_vec = Vec::new();
-        for (i, val) in self.reg.iter() {
-            reg_vec.push((*i , *val));
-        }
-        reg_vec.sort_by(|(i,v),(i2,v2)| {
-            i.cmp(i2)
-        });
-        let mut s = "[".to_string();
-        for (i, v) in reg_vec {
-            s = format!("{} {},", s,v);
-        }
-        let (sstr,_) = s.split_at(s.len()-1);
-        s = format!("{}]", sstr);
-        format!("{}",s)
-    }
-
     pub fn link_latency_pretty(&mut self, link_latency: HashMap<Int, f64>) -> String {
         let mut link_vec = Vec::new();
         for (node_id, sec) in link_latency.iter() {
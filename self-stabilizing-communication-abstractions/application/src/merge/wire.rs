@@ -0,0 +1,193 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use commons::encoding::Encoding;
+use crate::urb::messages::{MSG, MSGAck, GOSSIP};
+use crate::scd::messages::{FORWARD, SCDGOSSIP};
+use crate::merge::payload::{Payload, UrbPayload};
+use crate::urb::hbfd::HbfdMessage;
+use crate::urb::thetafd::ThetafdMessage;
+
+// Bumped whenever the layout of a datagram changes. Datagrams with another
+// version are dropped instead of being misinterpreted.
+pub const WIRE_VERSION: u8 = 4;
+
+// Every datagram starts with [WIRE_VERSION, encoding, kind] followed by the
+// body of the message. The values are part of the protocol, never reuse one.
+const ENCODING_JSON: u8 = 1;
+const ENCODING_BINARY: u8 = 2;
+
+const KIND_MSG: u8 = 1;
+const KIND_MSG_ACK: u8 = 2;
+const KIND_GOSSIP: u8 = 3;
+const KIND_FORWARD: u8 = 4;
+const KIND_SCD_GOSSIP: u8 = 5;
+const KIND_HBFD: u8 = 6;
+const KIND_THETAFD: u8 = 7;
+
+const HEADER_LEN: usize = 3;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum WireMessage<'a, V: Payload> {
+    MSG(MSG<'a, UrbPayload<V>>),
+    MSGAck(MSGAck),
+    GOSSIP(GOSSIP),
+    FORWARD(FORWARD<V>),
+    SCDGOSSIP(SCDGOSSIP),
+    HbfdMessage(HbfdMessage),
+    ThetafdMessage(ThetafdMessage),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WireError {
+    Truncated,
+    UnsupportedVersion(u8),
+    UnknownEncoding(u8),
+    UnknownKind(u8),
+    MalformedBody(String),
+}
+
+impl<'a, V: Payload> WireMessage<'a, V> {
+    pub fn kind(&self) -> u8 {
+        match self {
+            WireMessage::MSG(_) => KIND_MSG,
+            WireMessage::MSGAck(_) => KIND_MSG_ACK,
+            WireMessage::GOSSIP(_) => KIND_GOSSIP,
+            WireMessage::FORWARD(_) => KIND_FORWARD,
+            WireMessage::SCDGOSSIP(_) => KIND_SCD_GOSSIP,
+            WireMessage::HbfdMessage(_) => KIND_HBFD,
+            WireMessage::ThetafdMessage(_) => KIND_THETAFD,
+        }
+    }
+
+    pub fn encode(&self, encoding: Encoding) -> Vec<u8> {
+        let encoding_byte = match encoding {
+            Encoding::JSON => ENCODING_JSON,
+            Encoding::BINARY => ENCODING_BINARY,
+        };
+        let mut bytes = vec![WIRE_VERSION, encoding_byte, self.kind()];
+        match self {
+            WireMessage::MSG(msg) => encode_body(&mut bytes, encoding, msg),
+            WireMessage::MSGAck(msg) => encode_body(&mut bytes, encoding, msg),
+            WireMessage::GOSSIP(msg) => encode_body(&mut bytes, encoding, msg),
+            WireMessage::FORWARD(msg) => encode_body(&mut bytes, encoding, msg),
+            WireMessage::SCDGOSSIP(msg) => encode_body(&mut bytes, encoding, msg),
+            WireMessage::HbfdMessage(msg) => encode_body(&mut bytes, encoding, msg),
+            WireMessage::ThetafdMessage(msg) => encode_body(&mut bytes, encoding, msg),
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<WireMessage<'static, V>, WireError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(WireError::Truncated);
+        }
+        if bytes[0] != WIRE_VERSION {
+            return Err(WireError::UnsupportedVersion(bytes[0]));
+        }
+
+        let encoding = match bytes[1] {
+            ENCODING_JSON => Encoding::JSON,
+            ENCODING_BINARY => Encoding::BINARY,
+            encoding => return Err(WireError::UnknownEncoding(encoding)),
+        };
+
+        let body = &bytes[HEADER_LEN..];
+        let message = match bytes[2] {
+            KIND_MSG => WireMessage::MSG(decode_body(body, encoding)?),
+            KIND_MSG_ACK => WireMessage::MSGAck(decode_body(body, encoding)?),
+            KIND_GOSSIP => WireMessage::GOSSIP(decode_body(body, encoding)?),
+            KIND_FORWARD => WireMessage::FORWARD(decode_body(body, encoding)?),
+            KIND_SCD_GOSSIP => WireMessage::SCDGOSSIP(decode_body(body, encoding)?),
+            KIND_HBFD => WireMessage::HbfdMessage(decode_body(body, encoding)?),
+            KIND_THETAFD => WireMessage::ThetafdMessage(decode_body(body, encoding)?),
+            kind => return Err(WireError::UnknownKind(kind)),
+        };
+        Ok(message)
+    }
+}
+
+fn encode_body<M: Serialize>(bytes: &mut Vec<u8>, encoding: Encoding, message: &M) {
+    match encoding {
+        Encoding::JSON => serde_json::to_writer(bytes, message).expect("Could not serialize a message"),
+        Encoding::BINARY => bincode::serialize_into(bytes, message).expect("Could not serialize a message"),
+    }
+}
+
+fn decode_body<M: DeserializeOwned>(body: &[u8], encoding: Encoding) -> Result<M, WireError> {
+    match encoding {
+        Encoding::JSON => serde_json::from_slice(body).map_err(|e| WireError::MalformedBody(e.to_string())),
+        Encoding::BINARY => bincode::deserialize(body).map_err(|e| WireError::MalformedBody(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use commons::types::Tag;
+    use crate::urb::messages::CombinedGossip;
+    use crate::scd::types::VectorClock;
+    use crate::merge::reset::ResetPhase;
+    use crate::merge::payload::{AppMessage, UrbPayload};
+
+    fn gossip() -> CombinedGossip {
+        CombinedGossip {
+            urb_gossip: GOSSIP { sender: 1, urb_maxSeq: 3, urb_rxObsS: 1, urb_txObsS: 2, reset: ResetPhase::IDLE, reset_epoch: 0 },
+            scd_gossip: SCDGOSSIP { sender: 1, scd_maxSeq: 0, scd_rxObsS: 0, scd_txObsS: 0, scd_rxSpace: None, scd_txSpace: Some(4) },
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let msg = AppMessage::BROADCAST(String::from("hello"));
+        let messages: Vec<WireMessage<AppMessage>> = vec![
+            WireMessage::MSG(MSG {
+                sender: 2,
+                msg: Cow::Owned(Some(UrbPayload::MESSAGE(msg.clone()))),
+                tag: Tag { id: 2, seq: 7 },
+                recv_by: vec![0b1100_0000],
+                recv_by_trusted: vec![0b1110_0000],
+                gossip: gossip(),
+            }),
+            WireMessage::MSGAck(MSGAck { sender: 3, tag: Tag { id: 2, seq: 7 }, recv_by: vec![0xff] }),
+            WireMessage::GOSSIP(gossip().urb_gossip),
+            WireMessage::FORWARD(FORWARD {
+                msg: msg.clone(),
+                msg_tag: Tag { id: 1, seq: 1 },
+                forward_tag: Tag { id: 2, seq: 1 },
+                cl: VectorClock::new(3, 0),
+            }),
+            WireMessage::SCDGOSSIP(gossip().scd_gossip),
+            WireMessage::HbfdMessage(HbfdMessage { sender: 4 }),
+            WireMessage::ThetafdMessage(ThetafdMessage { sender: 5 }),
+        ];
+
+        for encoding in vec![Encoding::JSON, Encoding::BINARY] {
+            for message in messages.iter() {
+                assert_eq!(WireMessage::decode(&message.encode(encoding)).as_ref(), Ok(message));
+            }
+        }
+    }
+
+    #[test]
+    fn test_binary_encoding_is_smaller() {
+        let message: WireMessage<AppMessage> = WireMessage::MSGAck(MSGAck { sender: 3, tag: Tag { id: 2, seq: 7 }, recv_by: vec![0xff, 0x80] });
+        assert!(message.encode(Encoding::BINARY).len() < message.encode(Encoding::JSON).len());
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_headers() {
+        let mut bytes = WireMessage::<AppMessage>::HbfdMessage(HbfdMessage { sender: 4 }).encode(Encoding::BINARY);
+        assert_eq!(WireMessage::<AppMessage>::decode(&bytes[..2]), Err(WireError::Truncated));
+
+        bytes[2] = 0xff;
+        assert_eq!(WireMessage::<AppMessage>::decode(&bytes), Err(WireError::UnknownKind(0xff)));
+
+        bytes[1] = 0xff;
+        assert_eq!(WireMessage::<AppMessage>::decode(&bytes), Err(WireError::UnknownEncoding(0xff)));
+
+        bytes[0] = WIRE_VERSION + 1;
+        assert_eq!(WireMessage::<AppMessage>::decode(&bytes), Err(WireError::UnsupportedVersion(WIRE_VERSION + 1)));
+    }
+}
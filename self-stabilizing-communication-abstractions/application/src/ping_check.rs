@@ -1 +1,96 @@
-pub mod main;
\ No newline at end of file
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::net::SocketAddr;
+
+use fastping_rs::Pinger;
+use fastping_rs::PingResult::{Idle, Receive};
+
+use commons::types::NodeId;
+
+// Where ping_check writes the link latencies and nodes read them from.
+pub const LATENCY_FILE: &str = "latency.txt";
+
+// The round trip time in seconds to every node, from ICMP pings. Needs
+// the privileges to open raw sockets.
+pub fn ping_all_nodes(s_addrs: HashMap<NodeId, SocketAddr>) -> HashMap<NodeId, f64> {
+    let mut ping_recv_set = HashSet::new();
+    let mut ping_attempt = 0;
+    let mut latency_vector = HashMap::new();
+    let (pinger, results) = match Pinger::new(None, None) {
+        Ok((pinger, results)) => (pinger, results),
+        Err(e) => panic!("Error creating pinger: {}", e)
+    };
+    for (_, addrs) in s_addrs.clone() {
+        pinger.add_ipaddr(format!("{}", addrs.ip()).as_ref());
+    }
+    pinger.run_pinger();
+    'outer: loop {
+        match results.recv() {
+            Ok(result) => {
+                match result {
+                    Idle { addr: _ } => {
+//                        println!("Idle Address {}.", addr);
+                        ping_attempt += 1;
+                        if ping_attempt == 3 {
+                            break 'outer;
+                        }
+                    },
+                    Receive { addr, rtt } => {
+//                        println!("Node {} Receive from Address {} in {:?}.", SETTINGS.node_id(), addr, rtt);
+                        for (node, addrs) in &s_addrs {
+                            if addrs.ip() == addr {
+                                ping_recv_set.insert(node);
+//                                println!("Node {} counter {} nodes {} addr.ip {:?} addr {:?}", SETTINGS.node_id(), ping_recv_counter, SETTINGS.socket_addrs().keys().len(),addrs.ip(), addr);
+                                latency_vector.insert(*node, rtt.as_secs_f64());
+                                if ping_recv_set.len() == s_addrs.keys().len() {
+                                    break 'outer;
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            Err(_) => panic!("Worker threads disconnected before the solution was found!"),
+        }
+    }
+    latency_vector
+}
+
+pub fn write_link_latencies(link_latencies: &HashMap<NodeId, f64>) {
+    let mut s = String::new();
+    for (node_id, latency) in link_latencies {
+        s.push_str(format!("{}:{},", node_id, latency).as_str());
+    }
+    let _ = fs::write(
+        LATENCY_FILE,
+        format!("{:?}",s)
+    );
+}
+
+pub fn read_link_latencies() -> HashMap<NodeId, f64> {
+    let mut latency_vector = HashMap::new();
+    let string = fs::read_to_string(LATENCY_FILE).expect("Unable to read the latency file.");
+    let (_, sstr1) = string.split_at(1);
+    let (sstr2, _) = sstr1.split_at(sstr1.len()-2);
+    let split: Vec<&str> = sstr2.split(",").collect();
+    for s in split.clone() {
+        let s1: Vec<&str> = s.split(":").collect();
+        let mut node_id = -1;
+        let mut latency= 0.0;
+        if s1.len() >= 2 {
+            if let Ok(id) = s1[0].parse::<i32>() {
+                node_id = id;
+            } else {
+                node_id = -1;
+            }
+            if let Ok(lat) = s1[1].parse::<f64>() {
+                latency = lat
+            } else {
+                latency = 0 as f64;
+            }
+        }
+        latency_vector.insert(node_id, latency);
+    }
+    latency_vector
+}
+
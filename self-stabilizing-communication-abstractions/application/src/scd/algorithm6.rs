@@ -7,7 +7,6 @@ use commons::types::{Int, NodeId};
 
 use crate::scd::scd::SCD;
 use crate::scd::algorithm6::messages::{PLUS, MINUS, json_is_PLUS_Message, json_is_MINUS_Message};
-use crate::settings::SETTINGS;
 use crate::terminal_output::printlnu;
 use std::thread;
 use crate::urb::messages::Message;
@@ -24,7 +23,7 @@ pub struct Algorithm6 {
 impl Algorithm6 {
     pub fn new(send_end: Sender<String>, recv_end: Receiver<HashSet<String>>, broadcaster: Arc<SCD>) -> Arc<Self> {
         let algorithm6 = Algorithm6 {
-            own_id: SETTINGS.node_id(),
+            own_id: broadcaster.node_id(),
             send_end: Mutex::new(send_end),
             recv_end: Mutex::new(recv_end),
             broadcaster,
@@ -5,15 +5,15 @@ use crate::scd::types::VectorClock;
 use crate::urb::messages::Message;
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
-pub struct FORWARD<'a> {
+pub struct FORWARD<V> {
     #[serde(rename = "FORWARD")]
-    pub msg: Cow<'a, String>,
+    pub msg: V,
     pub msg_tag: Tag,
     pub forward_tag: Tag,
     pub cl: VectorClock,
 }
 
-impl<'a> Message for FORWARD<'a> {}
+impl<V: Serialize> Message for FORWARD<V> {}
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct GOSSIP {
@@ -47,16 +47,3 @@ pub struct SCDMETA {
 }
 
 impl Message for SCDMETA {}
-
-
-pub fn json_is_FORWARD_message(json: &str) -> bool {
-    json.starts_with("{\"FORWARD\":")
-}
-
-pub fn json_is_GOSSIP_message(json: &str) -> bool {
-    json.starts_with("{\"GOSSIP\":")
-}
-
-pub fn json_is_SCDGOSSIP_message(json: &str) -> bool {
-    json.starts_with("{\"SCDGOSSIP\":")
-}
\ No newline at end of file
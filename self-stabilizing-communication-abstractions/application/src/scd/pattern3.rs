@@ -9,7 +9,6 @@ use std::collections::HashSet;
 use commons::types::{Int, NodeId};
 use std::thread;
 use std::time::Duration;
-use crate::settings::SETTINGS;
 use crate::terminal_output::printlnu;
 
 pub struct Pattern3 {
@@ -23,7 +22,7 @@ pub struct Pattern3 {
 impl Pattern3 {
     pub fn new(send_end: Sender<String>, recv_end: Receiver<HashSet<String>>, broadcaster: Arc<SCD>) -> Arc<Self> {
         let pattern3 = Pattern3 {
-            own_id: SETTINGS.node_id(),
+            own_id: broadcaster.node_id(),
             send_end: Mutex::new(send_end),
             recv_end: Mutex::new(recv_end),
             broadcaster: broadcaster,
@@ -7,7 +7,6 @@ use commons::types::{NodeId, Tag};
 
 use crate::scd::scd::SCD;
 use crate::scd::pattern4::messages::{SYNC, MSG, json_is_SYNC_Message, json_is_MSG_Message};
-use crate::settings::SETTINGS;
 use crate::scd::pattern4::messages::Message;
 use std::thread;
 use crate::terminal_output::printlnu;
@@ -24,7 +23,7 @@ pub struct Pattern4 {
 impl Pattern4 {
     pub fn new(send_end: Sender<String>, recv_end: Receiver<HashSet<String>>, broadcaster: Arc<SCD>) -> Arc<Self>{
         let pattern4 = Pattern4 {
-            own_id: SETTINGS.node_id(),
+            own_id: broadcaster.node_id(),
             send_end: Mutex::new(send_end),
             recv_end: Mutex::new(recv_end),
             broadcaster,
@@ -17,7 +17,6 @@ use crate::mediator::Mediator;
 use crate::urb::NodeDelegate;
 use super::messages::*;
 use super::types::*;
-use crate::settings::SETTINGS;
 use crate::terminal_output::printlnu;
 use crate::urb::messages::Message;
 
@@ -26,6 +25,7 @@ use crate::urb::messages::Message;
 pub struct SCD {
     id: NodeId,
     node_ids: HashSet<NodeId>,
+    print_client_operations: bool,
     buffer: Mutex<HashMap<Tag, Entry>>,
     sn: Mutex<Int>,
     clock: Mutex<VectorClock>,
@@ -42,11 +42,12 @@ impl SCD {
     // Initilization
     //
     
-    pub fn new(send_end: Sender<String>, recv_end: Receiver<String>, self_id: NodeId, node_ids: HashSet<NodeId>, send_pattern_end: Sender<HashSet<String>>, recv_pattern_end: Receiver<String>) -> Arc<Self> {
+    pub fn new(send_end: Sender<String>, recv_end: Receiver<String>, self_id: NodeId, node_ids: HashSet<NodeId>, print_client_operations: bool, send_pattern_end: Sender<HashSet<String>>, recv_pattern_end: Receiver<String>) -> Arc<Self> {
         let number_of_nodes = node_ids.len();
         let scd = SCD {
             id: self_id,
             node_ids: node_ids,
+            print_client_operations,
             buffer: Mutex::new(HashMap::new()),
             sn: Mutex::new(1),
             clock: Mutex::new(VectorClock::new(number_of_nodes, 0)),
@@ -68,6 +69,10 @@ impl SCD {
         scd
     }
 
+    pub fn node_id(&self) -> NodeId {
+        self.id
+    }
+
     fn listen_for_fifo_delivery_thread(&self, recv_end: Receiver<String>) {
         loop {
             match recv_end.recv() {
@@ -143,7 +148,7 @@ impl SCD {
     }
 
     pub fn scdDeliver(&self, msgs: HashSet<String>) {
-        if SETTINGS.print_client_operations() {
+        if self.print_client_operations {
             printlnu(format!("scdDelivered: {:?}", msgs));
         }
         let send_to_pattern_end = self.send_pattern_end.lock().unwrap();
@@ -200,7 +205,7 @@ impl SCD {
                     let mut sn = self.sn.lock().unwrap();
                     // printlnu(format!("Inside forward 6"));
                     let forward_msg = FORWARD {
-                        msg: Cow::Borrowed(&msg),
+                        msg: msg.clone(),
                         msg_tag: msg_tag.clone(),
                         forward_tag: Tag {id: self.id, seq: *sn},
                         cl: threshold.clone()
@@ -354,27 +359,21 @@ impl SCD {
 
     fn msg_received(&self, msg: String) {
         // printlnu(format!("msg_received: {:?}", msg));
-        if json_is_FORWARD_message(&msg) {
-            if let Ok(forward_message) = serde_json::from_str(&msg) {
-                return self.FORWARD_received(forward_message);
-            }
-
+        if let Ok(forward_message) = serde_json::from_str(&msg) {
+            return self.FORWARD_received(forward_message);
         }
-        if json_is_GOSSIP_message(&msg) {
-            if let Ok(gossip_message) = serde_json::from_str(&msg) {
-                return self.GOSSIP_received(gossip_message);
-            }
-
+        if let Ok(gossip_message) = serde_json::from_str(&msg) {
+            return self.GOSSIP_received(gossip_message);
         }
         printlnu(format!("Cannot handle received msg: {}", &msg));
 
     }
 
-    fn FORWARD_received(&self, forward_msg: FORWARD) {
+    fn FORWARD_received(&self, forward_msg: FORWARD<String>) {
         let m = forward_msg.msg;
         let msg_tag = forward_msg.msg_tag;
         let forward_tag = forward_msg.forward_tag;
-        let _ = self.forward(m.into_owned(), msg_tag, forward_tag);
+        let _ = self.forward(m, msg_tag, forward_tag);
 
     }
 
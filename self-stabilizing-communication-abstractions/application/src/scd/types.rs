@@ -2,8 +2,8 @@ use serde::{Deserialize, Serialize};
 use commons::types::{Tag, Int, NodeId};
 
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
-pub struct Entry {
-    pub msg: String,
+pub struct Entry<V = String> {
+    pub msg: V,
     pub tag: Tag,
     pub cl: VectorClock,
     pub delivered: bool,
@@ -6,93 +6,57 @@ use std::time::Duration;
 
 use clap::{App, AppSettings, Arg, ArgMatches};
 use colored::*;
-use lazy_static::lazy_static;
 
 use commons::arguments;
 use commons::types::{Int, NodeId};
 use commons::variant::Variant;
+use commons::encoding::Encoding;
+use commons::transport_kind::TransportKind;
+use commons::failure_detector_kind::FailureDetectorKind;
 use commons::constants::WINDOW_SIZE;
+use commons::workload::Workload;
 
-lazy_static! {
-    pub static ref SETTINGS: Settings = Settings::new();
-}
-
-#[derive(Debug)]
-pub struct Settings {
-    node_id: NodeId,
-    socket_addrs: HashMap<NodeId, SocketAddr>,
-    terminal_color: Color,
-    print_client_operations: bool,
-    run_length: Duration,
-    window_size: Option<Int>,
-    record_evaluation_info: bool,
-    is_writer: bool,
-    is_failing_node: bool,
-    is_crashing_node: bool,
-    delta: Int,
-    variant: Variant,
-}
+use crate::config::NodeConfig;
+use crate::merge::faulty_transport::LinkFaults;
 
-impl Settings {
-    fn new() -> Settings {
+impl NodeConfig {
+    // The config given on the command line of the application binary.
+    pub fn from_args() -> NodeConfig {
         let matches = get_matches();
 
-        Settings {
+        NodeConfig {
             node_id: node_id_from_matches(&matches),
             socket_addrs: socket_addrs_from_matches(&matches),
+            link_keys: link_keys_from_matches(&matches),
             terminal_color: color_from_matches(&matches),
             print_client_operations: print_client_operations_from_matches(&matches),
             run_length: run_length_from_matches(&matches),
-            window_size: arguments::window_size_from_matches(&matches),
+            window_size: arguments::window_size_from_matches(&matches).unwrap_or(WINDOW_SIZE),
             record_evaluation_info: record_evaluation_info_from_matches(&matches),
             is_writer: is_writer_from_matches(&matches),
             is_failing_node: is_failing_from_matches(&matches),
             is_crashing_node: is_crashing_from_matches(&matches),
             delta: arguments::delta_from_matches(&matches),
             variant: arguments::variant_from_matches(&matches),
+            encoding: arguments::encoding_from_matches(&matches),
+            transport: arguments::transport_from_matches(&matches),
+            faults: faults_from_matches(&matches),
+            link_faults: link_faults_from_matches(&matches),
+            fault_seed: arguments::fault_seed_from_matches(&matches),
+            tick_interval: tick_interval_from_matches(&matches),
+            failure_detector: arguments::failure_detector_from_matches(&matches),
+            thetafd_w: int_from_matches(&matches, "thetafd-w"),
+            thetafd_hysteresis: int_from_matches(&matches, "thetafd-hysteresis"),
+            hbfd_w: int_from_matches(&matches, "hbfd-w"),
+            phi_threshold: matches.value_of("phi-threshold").unwrap().parse().expect("Could not parse phi-threshold."),
+            phi_window: matches.value_of("phi-window").unwrap().parse().expect("Could not parse phi-window."),
+            theta_hb_timeout: millis_from_matches(&matches, "theta-hb-timeout"),
+            hbfd_hb_timeout: millis_from_matches(&matches, "hbfd-hb-timeout"),
+            max_int: arguments::max_int_from_matches(&matches),
+            rpc_port: rpc_port_from_matches(&matches),
+            workload: workload_from_matches(&matches),
         }
     }
-
-    pub fn node_id(&self) -> NodeId {
-        self.node_id
-    }
-
-    pub fn socket_addrs(&self) -> &HashMap<NodeId, SocketAddr> {
-        &self.socket_addrs
-    }
-
-    pub fn window_size(&self) -> Int {
-        if self.window_size.is_some() {
-            return self.window_size.unwrap();
-        }
-        WINDOW_SIZE
-    }
-
-    pub fn terminal_color(&self) -> Color {
-        self.terminal_color
-    }
-
-    #[allow(dead_code)]
-    pub fn record_evaluation_info(&self) -> bool {
-        self.record_evaluation_info
-    }
-
-    pub fn print_client_operations(&self) -> bool {
-        self.print_client_operations
-    }
-    pub fn is_writer(&self) -> bool { self.is_writer }
-    pub fn is_failing_node(&self) -> bool { self.is_failing_node }
-    pub fn number_of_nodes(&self) -> Int {
-        self.socket_addrs.len() as Int
-    }
-    pub fn is_crashing_node(&self) -> bool { self.is_crashing_node }
-    pub fn delta(&self) -> Int { self.delta }
-
-    pub fn run_length(&self) -> Duration {
-        self.run_length
-    }
-    pub fn variant(&self) -> Variant { self.variant }
-
 }
 
 fn get_matches() -> ArgMatches<'static> {
@@ -115,6 +79,24 @@ fn get_matches() -> ArgMatches<'static> {
         .arg(arguments::is_crashing_node())
         .arg(arguments::delta())
         .arg(arguments::variant())
+        .arg(arguments::encoding())
+        .arg(arguments::transport())
+        .arg(arguments::faults())
+        .arg(arguments::link_faults())
+        .arg(arguments::fault_seed())
+        .arg(tick_interval_argument())
+        .arg(arguments::failure_detector())
+        .arg(failure_detector_argument("thetafd-w", "5000", "Heartbeats from others after which the Θ failure detector suspects a silent node."))
+        .arg(failure_detector_argument("thetafd-hysteresis", "10", "Heartbeats a node suspected by the Θ failure detector has to send, without being suspected again, before it is trusted again."))
+        .arg(failure_detector_argument("phi-threshold", "8", "The phi above which the accrual failure detector suspects a node."))
+        .arg(failure_detector_argument("phi-window", "1000", "How many heartbeat inter-arrival times the accrual failure detector bases phi on."))
+        .arg(failure_detector_argument("hbfd-w", "5000", "Own heartbeat rounds after which the heartbeat failure detector suspects a silent node."))
+        .arg(failure_detector_argument("theta-hb-timeout", "1000", "Milliseconds between the heartbeats of the threaded Θ failure detector."))
+        .arg(failure_detector_argument("hbfd-hb-timeout", "1000", "Milliseconds between the heartbeats of the threaded heartbeat failure detector."))
+        .arg(arguments::max_int())
+        .arg(rpc_port_argument())
+        .arg(arguments::workload())
+        .arg(workload_file_argument())
         .get_matches()
 }
 
@@ -135,10 +117,10 @@ fn socket_addrs_from_matches(matches: &ArgMatches<'static>) -> HashMap<NodeId, S
     socket_addrs_from_string(string)
 }
 
-fn socket_addrs_from_string(string: String) -> HashMap<NodeId, SocketAddr> {
+pub fn socket_addrs_from_string(string: String) -> HashMap<NodeId, SocketAddr> {
     let mut socket_addrs = HashMap::new();
 
-    for line in string.lines() {
+    for line in string.lines().filter(|line| !arguments::is_link_key_line(line)) {
         let components: Vec<&str> = line.split(",").collect();
         let id = components[0].parse().unwrap();
         let socket_addr = components[1]
@@ -152,6 +134,96 @@ fn socket_addrs_from_string(string: String) -> HashMap<NodeId, SocketAddr> {
     socket_addrs
 }
 
+fn link_keys_from_matches(matches: &ArgMatches<'static>) -> HashMap<NodeId, Vec<u8>> {
+    let hosts_file_path = matches.value_of("hosts-file").unwrap();
+    let string = fs::read_to_string(hosts_file_path).expect("Unable to read file");
+    let node_id = node_id_from_matches(matches);
+    let link_keys = arguments::link_keys_from_string(&string, node_id);
+
+    if !link_keys.is_empty() {
+        for other_id in socket_addrs_from_string(string).keys() {
+            if *other_id != node_id && !link_keys.contains_key(other_id) {
+                panic!("The hosts file has link keys, but none for the link between {} and {}.", node_id, other_id);
+            }
+        }
+    }
+    link_keys
+}
+
+fn faults_from_matches(matches: &ArgMatches<'static>) -> LinkFaults {
+    match arguments::faults_string_from_matches(matches) {
+        Some(spec) => spec.parse().expect("Could not parse faults arg."),
+        None => LinkFaults::default(),
+    }
+}
+
+// Each value is "<node id>:<faults>", applied on top of --faults.
+fn link_faults_from_matches(matches: &ArgMatches<'static>) -> HashMap<NodeId, LinkFaults> {
+    let faults = faults_from_matches(matches);
+    arguments::link_faults_strings_from_matches(matches).iter().map(|string| {
+        let mut components = string.splitn(2, ':');
+        let node_id = components.next().unwrap().parse().expect("Could not parse node id of link faults arg.");
+        let spec = components.next().expect("Link faults arg must look like <node id>:<faults>.");
+        (node_id, faults.with_spec(spec).expect("Could not parse link faults arg."))
+    }).collect()
+}
+
+fn tick_interval_argument() -> Arg<'static, 'static> {
+    Arg::with_name("tick-interval")
+        .long("tick-interval")
+        .takes_value(true)
+        .default_value("1000")
+        .help("Microseconds between two iterations of the do forever loop when nothing is received.")
+}
+
+fn tick_interval_from_matches(matches: &ArgMatches<'static>) -> Duration {
+    let micros = matches.value_of("tick-interval").unwrap().parse().expect("Could not parse tick interval.");
+    Duration::from_micros(micros)
+}
+
+fn rpc_port_argument() -> Arg<'static, 'static> {
+    Arg::with_name("rpc-port")
+        .long("rpc-port")
+        .takes_value(true)
+        .help("Serve client requests from other processes on this loopback port, instead of running the built-in client.")
+}
+
+fn rpc_port_from_matches(matches: &ArgMatches<'static>) -> Option<u16> {
+    matches.value_of("rpc-port").map(|port| port.parse().expect("Could not parse rpc port."))
+}
+
+fn workload_file_argument() -> Arg<'static, 'static> {
+    Arg::with_name("workload-file")
+        .long("workload-file")
+        .takes_value(true)
+        .conflicts_with("workload")
+        .help("A file with the workload, one key of the --workload spec per line.")
+}
+
+fn workload_from_matches(matches: &ArgMatches<'static>) -> Workload {
+    let spec = match matches.value_of("workload-file") {
+        Some(path) => fs::read_to_string(path).expect("Unable to read the workload file."),
+        None => arguments::workload_string_from_matches(matches).unwrap_or_default(),
+    };
+    spec.parse().expect("Could not parse workload.")
+}
+
+fn failure_detector_argument(name: &'static str, default: &'static str, help: &'static str) -> Arg<'static, 'static> {
+    Arg::with_name(name)
+        .long(name)
+        .takes_value(true)
+        .default_value(default)
+        .help(help)
+}
+
+fn int_from_matches(matches: &ArgMatches<'static>, name: &str) -> Int {
+    matches.value_of(name).unwrap().parse().expect(&format!("Could not parse {}.", name))
+}
+
+fn millis_from_matches(matches: &ArgMatches<'static>, name: &str) -> Duration {
+    Duration::from_millis(matches.value_of(name).unwrap().parse().expect(&format!("Could not parse {}.", name)))
+}
+
 fn color_argument() -> Arg<'static, 'static> {
     let colors = &["Black", "Red", "Green", "Yellow", "Blue", "Magenta", "Cyan"];
     Arg::with_name("color")
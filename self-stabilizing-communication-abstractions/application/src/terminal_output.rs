@@ -1,9 +1,32 @@
-use colored::Colorize;
+use std::cell::Cell;
 
-use crate::settings::SETTINGS;
+use colored::{Color, Colorize};
+
+use commons::types::NodeId;
+
+thread_local! {
+    // The node whose output this thread prints, so that several nodes in
+    // one process can be told apart.
+    static NODE: Cell<Option<(NodeId, Color)>> = Cell::new(None);
+}
+
+// Prints the output of this thread as that of node_id, in its color.
+pub fn set_node(node_id: NodeId, color: Color) {
+    NODE.with(|node| node.set(Some((node_id, color))));
+}
+
+// The node set for this thread, to hand to the threads it spawns.
+pub fn node() -> Option<(NodeId, Color)> {
+    NODE.with(|node| node.get())
+}
 
 // Prints a string with the color assigned to this node.
 pub fn printlnu(string: String) {
-    let output_string = format!("[Node {}] {}", SETTINGS.node_id(), string);
-    println!("{}", output_string.color(SETTINGS.terminal_color()).bold());
+    match node() {
+        Some((node_id, color)) => {
+            let output_string = format!("[Node {}] {}", node_id, string);
+            println!("{}", output_string.color(color).bold());
+        }
+        None => println!("{}", string),
+    }
 }
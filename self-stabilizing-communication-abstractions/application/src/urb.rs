@@ -6,19 +6,18 @@ pub mod messages;
 
 use std::collections::HashSet;
 use std::sync::MutexGuard;
-use std::time::Duration;
 
 use commons::types::{Int, NodeId};
 use commons::run_result::RunResult;
 
-pub const THETA_HB_TIMEOUT: Duration = Duration::from_millis(1000);
-pub const HBFD_HB_TIMEOUT: Duration = Duration::from_millis(1000);
+use crate::config::NodeConfig;
 
 pub trait NodeDelegate {
     fn send_json_to(&self, json: &str, receiver: NodeId);
 
     fn node_id(&self) -> NodeId;
     fn node_ids(&self) -> &HashSet<NodeId>;
+    fn config(&self) -> &NodeConfig;
     fn number_of_nodes(&self) -> Int;
 
     fn record_evaluation_info(&self) -> bool;
@@ -1,18 +1,17 @@
 use std::sync::{mpsc::{self, Sender, Receiver, TryRecvError}, Arc, Weak, Mutex, MutexGuard};
-use std::collections::HashMap;
 use std::thread;
 
 use serde::{Deserialize, Serialize};
 
 use commons::types::{Int, NodeId};
 use crate::communicator::CommunicatorDelegate;
+use crate::failure_detector::{FailureDetector, HbDetector};
 use crate::urb::NodeDelegate;
 // use crate::terminal_output::printlnu;
-use super::HBFD_HB_TIMEOUT;
 
 pub struct Hbfd<D>{
     delegate: Weak<D>,
-    hb: Mutex<HashMap<NodeId, Int>>,
+    detector: Mutex<HbDetector>,
     stop_thread_handler: Mutex<Sender<()>>,
 }
 
@@ -21,20 +20,12 @@ impl <D> Hbfd<D>
 
     pub fn new(delegate: Weak<D>) -> Arc<Hbfd<D>> {
         let mediator = delegate.upgrade().unwrap();
-        let node_ids = mediator.node_ids();
-
-        let mut hashmap = HashMap::new();
-
-        for node_id in node_ids {
-            hashmap.insert(*node_id, 0);
-        }
-
-        let hb = Mutex::new(hashmap);
+        let detector = HbDetector::new(mediator.node_id(), mediator.node_ids(), mediator.config().hbfd_w);
         let (tx, rx) = mpsc::channel();
 
         let hbfd = Hbfd{
             delegate,
-            hb,
+            detector: Mutex::new(detector),
             stop_thread_handler: Mutex::new(tx), 
         };
 
@@ -45,12 +36,11 @@ impl <D> Hbfd<D>
     }
 
     pub fn get_hb(&self) -> Vec<Int> {
-        let hb = self.hb.lock().unwrap();
-        let mut hb_array = Vec::new();
-        for node_id in 1..self.delegate().node_ids().len() + 1 {
-            hb_array.push(*hb.get(&(node_id as Int)).unwrap());
-        }
-        hb_array
+        self.get_detector().heartbeats()
+    }
+
+    fn get_detector(&self) -> MutexGuard<HbDetector> {
+        self.detector.lock().unwrap()
     }
 
     fn id(&self) -> NodeId {
@@ -71,11 +61,12 @@ impl <D> Hbfd<D>
     }
 
     fn do_forever_loop(&self, rx: Receiver<()>) {
+        let timeout = self.delegate().config().hbfd_hb_timeout;
         loop {
             for id in self.delegate().node_ids() {
                 self.send_heartbeat(*id);
             }
-            thread::sleep(HBFD_HB_TIMEOUT);
+            thread::sleep(timeout);
 
             match rx.try_recv() {
                 Err(TryRecvError::Empty) => {}
@@ -88,16 +79,12 @@ impl <D> Hbfd<D>
 
     pub fn on_heartbeat(&self, msg: HbfdMessage) {
         // printlnu(format!("Received hbfd heartbeat from {}.", msg.sender));
-        let sender_id = msg.sender;
-        let mut hb = self.hb.lock().unwrap();
-
-        *hb.get_mut(&sender_id).unwrap() += 1; 
+        self.get_detector().on_heartbeat(msg.sender);
     }
 
     fn send_heartbeat(&self, receiver_id: Int) {
         if receiver_id == self.id() {
-            let mut hb = self.hb.lock().unwrap();
-            *hb.get_mut(&receiver_id).unwrap() += 1;
+            self.get_detector().on_heartbeat(receiver_id);
         } else {
             let msg = HbfdMessage { sender: self.id() };
             let json_msg = serde_json::to_string(&msg).expect("Could not serialize a message");
@@ -110,12 +97,8 @@ impl <D> Hbfd<D>
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct HbfdMessage {
     #[serde(rename = "HbfdMessage")]
     pub sender: NodeId,
 }
-
-pub fn json_is_HbfdMessage(json: &str) -> bool {
-    json.starts_with("{\"HbfdMessage\":")
-}
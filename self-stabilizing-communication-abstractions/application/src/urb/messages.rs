@@ -3,6 +3,7 @@ use std::borrow::Cow;
 
 use commons::types::{Tag, Int, NodeId};
 use crate::scd::messages::SCDGOSSIP;
+use crate::merge::reset::ResetPhase;
 
 
 pub trait Message: Serialize {}
@@ -47,18 +48,24 @@ pub struct GOSSIP {
     pub urb_maxSeq: Int,
     pub urb_rxObsS: Int,
     pub urb_txObsS: Int,
+    pub reset: ResetPhase,
+    pub reset_epoch: Int,
 }
 
 impl Message for GOSSIP {}
 
-pub fn json_is_MSG_message(json: &str) -> bool {
-    json.starts_with("{\"MSG\":")
-}
-
-pub fn json_is_MSGAck_message(json: &str) -> bool {
-    json.starts_with("{\"MSGAck\":")
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::urb::hbfd::HbfdMessage;
 
-pub fn json_is_GOSSIP_message(json: &str) -> bool {
-    json.starts_with("{\"GOSSIP\":")
+    #[test]
+    fn test_json_only_parses_as_its_own_message() {
+        let ack = MSGAck { sender: 1, tag: Tag { id: 1, seq: 2 }, recv_by: vec![] };
+        let json = serde_json::to_string(&ack).unwrap();
+        assert_eq!(serde_json::from_str::<MSGAck>(&json).unwrap(), ack);
+        assert!(serde_json::from_str::<MSG<String>>(&json).is_err());
+        assert!(serde_json::from_str::<GOSSIP>(&json).is_err());
+        assert!(serde_json::from_str::<HbfdMessage>(&json).is_err());
+    }
 }
@@ -1,20 +1,19 @@
 use std::sync::{mpsc::{self, Sender, Receiver, TryRecvError}, Arc, Weak, Mutex, MutexGuard};
 use std::thread;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use serde::{Deserialize, Serialize};
 
 use commons::types::{Int, NodeId};
-use commons::constants;
 use crate::communicator::CommunicatorDelegate;
+use crate::failure_detector::{self, FailureDetector};
+use crate::merge::clock;
 use crate::urb::NodeDelegate;
 // use crate::terminal_output::printlnu;
-use super::THETA_HB_TIMEOUT;
 
 pub struct Thetafd<D> {
     delegate: Weak<D>,
-    vector: Mutex<HashMap<NodeId, Int>>,
-    current_trusted: Mutex<HashSet<NodeId>>,
+    detector: Mutex<Box<dyn FailureDetector>>,
     stop_thread_handler: Mutex<Sender<()>>,
 }
 
@@ -22,21 +21,12 @@ impl<D> Thetafd<D>
     where D : CommunicatorDelegate + NodeDelegate + Send + Sync + 'static {
     pub fn new(delegate: Weak<D>) -> Arc<Self> {
         let mediator = delegate.upgrade().unwrap();
-        let node_ids = mediator.node_ids();
-        let mut hashmap = HashMap::new();
-
-        for node_id in node_ids {
-            hashmap.insert(*node_id, 0);
-        }
-
-        let vector = Mutex::new(hashmap);
-        let current_trusted = Mutex::new(node_ids.clone());
+        let detector = failure_detector::trust_detector(mediator.config(), clock::system_clock(), mediator.node_id(), mediator.node_ids());
 
         let (tx, rx) = mpsc::channel();
         let thetafd = Thetafd {
             delegate,
-            vector,
-            current_trusted,
+            detector: Mutex::new(detector),
             stop_thread_handler: Mutex::new(tx), 
         };
 
@@ -50,21 +40,14 @@ impl<D> Thetafd<D>
     }
 
     pub fn trusted(&self) -> HashSet<NodeId>{
-        let mut trusted = HashSet::new();
-        let vector = self.get_vector();
-
-        for (idx, x) in vector.iter() {
-            if *x < constants::THETAFD_W {
-                trusted.insert(*idx);
-            }
-        }
-        let mut current_trusted = self.current_trusted.lock().unwrap();
-        current_trusted.retain(|id| trusted.contains(id) );
-        current_trusted.clone()
+        let trusted = self.get_detector().trusted();
+        (1..=trusted.len() as NodeId)
+            .filter(|node_id| trusted.get(*node_id as usize - 1).unwrap())
+            .collect()
     }
 
-    fn get_vector(&self) -> MutexGuard<HashMap<NodeId, Int>> {
-        self.vector.lock().unwrap()
+    fn get_detector(&self) -> MutexGuard<Box<dyn FailureDetector>> {
+        self.detector.lock().unwrap()
     }
 
     fn start_thetafd_thread(thetafd: &Arc<Self>, rx: Receiver<()>) {
@@ -80,12 +63,13 @@ impl<D> Thetafd<D>
     }
 
     fn do_forever_loop(&self, rx:Receiver<()>) {
+        let timeout = self.delegate().config().theta_hb_timeout;
         loop {
             for id in self.delegate().node_ids() {
                 self.send_heartbeat(*id);
             }
 
-            thread::sleep(THETA_HB_TIMEOUT);
+            thread::sleep(timeout);
 
             match rx.try_recv() {
                 Err(TryRecvError::Empty) => {}
@@ -99,16 +83,7 @@ impl<D> Thetafd<D>
     pub fn on_heartbeat(&self, msg: ThetafdMessage) {
 
         // printlnu(format!("Received theta heartbeat from {}.", msg.sender));
-        let sender_id = msg.sender;
-        let mut vector = self.get_vector();
-        *vector.get_mut(&sender_id).unwrap() = 0;
-        for (&idx, val) in vector.iter_mut() {
-            *val = if idx == sender_id || idx == self.id() {
-                0
-            } else {
-                *val + 1
-            }
-        }
+        self.get_detector().on_heartbeat(msg.sender);
     }
 
     fn send_heartbeat(&self, receiver_id: Int) {
@@ -122,12 +97,8 @@ impl<D> Thetafd<D>
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct ThetafdMessage {
     #[serde(rename = "ThetafdMessage")]
     pub sender: NodeId,
 }
-
-pub fn json_is_ThetafdMessage(json: &str) -> bool {
-    json.starts_with("{\"ThetafdMessage\":")
-}
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use commons::types::{Int, NodeId, Tag};
 use bit_vec::BitVec;
 use std::time::Instant;
+use crate::scd::messages::SCDMETA;
 
 #[derive(Debug, Clone)]
 pub struct BufferRecord<V> {
@@ -16,7 +17,7 @@ pub struct BufferRecord<V> {
     pub prevHB: Vec<Int>,
     pub urb_needed: bool,
     pub scd_needed: bool,
-    pub meta: Option<String>,
+    pub meta: Option<SCDMETA>,
     pub creation_instant: Option<Instant>,
 }
 
@@ -22,6 +22,7 @@ use super::hbfd::*;
 use super::thetafd::*;
 use crate::communicator::CommunicatorDelegate;
 use crate::urb::{NodeDelegate, UrbBroadcaster};
+use crate::merge::reset::ResetPhase;
 use crate::terminal_output::printlnu;
 use bit_vec::BitVec;
 use crate::scd::messages::SCDGOSSIP;
@@ -475,7 +476,9 @@ impl<D,V> UrbNode<D,V>
                                 sender: 0,
                                 urb_maxSeq: 0,
                                 urb_rxObsS: 0,
-                                urb_txObsS: 0
+                                urb_txObsS: 0,
+                                reset: ResetPhase::IDLE,
+                                reset_epoch: 0,
                             },
                             scd_gossip: SCDGOSSIP {
                                 sender: 0,
@@ -509,7 +512,7 @@ impl<D,V> UrbNode<D,V>
             let maxSeq = self.maxSeq(node_id);
             let rxObsS_for_id = rxObsS[node_id as usize - 1];
             let txObsS_for_id = txObsS[node_id as usize - 1];
-            let gossip_msg = GOSSIP { sender: self.id(), urb_maxSeq: maxSeq, urb_rxObsS: rxObsS_for_id, urb_txObsS: txObsS_for_id };
+            let gossip_msg = GOSSIP { sender: self.id(), urb_maxSeq: maxSeq, urb_rxObsS: rxObsS_for_id, urb_txObsS: txObsS_for_id, reset: ResetPhase::IDLE, reset_epoch: 0 };
             let json_msg = self.jsonify_message(&gossip_msg);
             self.send_json_message_to(&json_msg, node_id);
         }
@@ -526,17 +529,10 @@ impl<D,V> UrbNode<D,V>
                         for counter in 0..10000 {
                             match rx.try_recv() {
                                 Ok(json) => {
-                                    if json_is_MSG_message(&json) {
-                                        if let Ok(MSG_message) = serde_json::from_str(&json) {
-                                            self.MSG_received(MSG_message);
-                                        }
-                                        continue;
-                                    }
-                                    if json_is_MSGAck_message(&json) {
-                                        if let Ok(MSGAck_message) = serde_json::from_str(&json) {
-                                            self.MSGAck_received(MSGAck_message);
-                                        }
-                                        continue;
+                                    if let Ok(MSG_message) = serde_json::from_str(&json) {
+                                        self.MSG_received(MSG_message);
+                                    } else if let Ok(MSGAck_message) = serde_json::from_str(&json) {
+                                        self.MSGAck_received(MSGAck_message);
                                     }
                                 }
                                 Err(_) => break,
@@ -550,10 +546,8 @@ impl<D,V> UrbNode<D,V>
                         // There is only one slot for GOSSIP messages.
                         match rx.try_recv() {
                             Ok(json) => {
-                                if json_is_GOSSIP_message(&json) {
-                                    if let Ok(GOSSIP_message) = serde_json::from_str(&json) {
-                                        self.GOSSIP_received(GOSSIP_message);
-                                    }
+                                if let Ok(GOSSIP_message) = serde_json::from_str(&json) {
+                                    self.GOSSIP_received(GOSSIP_message);
                                 }
                             },
                             Err(_) => continue,
@@ -621,40 +615,32 @@ impl<D,V> UrbNode<D,V>
         self.delegate().send_json_to(json, receiver_id);
     }
 
+    // The first field of every message is renamed to the name of the
+    // message, so a json only parses as the message it was made from.
     pub fn json_received(&self, json: &str) {
 
-        if json_is_HbfdMessage(&json) {
-            if let Ok(hbfd_message) = serde_json::from_str(&json) {
-                return self.hbfd.on_heartbeat(hbfd_message);
-            }
+        if let Ok(hbfd_message) = serde_json::from_str(&json) {
+            return self.hbfd.on_heartbeat(hbfd_message);
         }
 
-        if json_is_ThetafdMessage(&json) {
-            if let Ok(thetafd_message) = serde_json::from_str(&json) {
-                return self.thetafd.on_heartbeat(thetafd_message);
-            }
+        if let Ok(thetafd_message) = serde_json::from_str(&json) {
+            return self.thetafd.on_heartbeat(thetafd_message);
         }
 
         let mut msgs_buffer_txs = self.msgs_buffer_txs.lock().unwrap();
         match &mut *msgs_buffer_txs {
             Some(buffer_txs) => {
-                if json_is_MSG_message(&json) {
-                    if let Ok(MSG_message) = serde_json::from_str::<MSG<V>>(&json) {
-                        let _ = buffer_txs.get_mut(&MessageType::MSG).unwrap().get_mut(&MSG_message.sender).unwrap().send(json.to_owned());
-                        return;
-                    }
+                if let Ok(MSG_message) = serde_json::from_str::<MSG<V>>(&json) {
+                    let _ = buffer_txs.get_mut(&MessageType::MSG).unwrap().get_mut(&MSG_message.sender).unwrap().send(json.to_owned());
+                    return;
                 }
-                if json_is_MSGAck_message(&json) {
-                    if let Ok(MSGAck_message) = serde_json::from_str::<MSGAck>(&json) {
-                        let _ = buffer_txs.get_mut(&MessageType::MSGAck).unwrap().get_mut(&MSGAck_message.sender).unwrap().send(json.to_owned());
-                        return;
-                    }
+                if let Ok(MSGAck_message) = serde_json::from_str::<MSGAck>(&json) {
+                    let _ = buffer_txs.get_mut(&MessageType::MSGAck).unwrap().get_mut(&MSGAck_message.sender).unwrap().send(json.to_owned());
+                    return;
                 }
-                if json_is_GOSSIP_message(&json) {
-                    if let Ok(GOSSIP_message) = serde_json::from_str::<GOSSIP>(&json) {
-                        let _ = buffer_txs.get_mut(&MessageType::GOSSIP).unwrap().get_mut(&GOSSIP_message.sender).unwrap().send(json.to_owned());
-                        return;
-                    }
+                if let Ok(GOSSIP_message) = serde_json::from_str::<GOSSIP>(&json) {
+                    let _ = buffer_txs.get_mut(&MessageType::GOSSIP).unwrap().get_mut(&GOSSIP_message.sender).unwrap().send(json.to_owned());
+                    return;
                 }
             },
             None => return,
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use commons::types::{Int, NodeId};
+use commons::variant::Variant;
+use commons::workload::{Workload, LoadMode, OperationMix, PayloadSize};
+use crate::merge::client::{Client, RequestId, ClientRequest, ClientResponse, ClientError};
+use crate::merge::payload::AppMessage;
+
+// An operation without a response after this is given up on.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+// The longest the generator waits for a response before it checks the
+// clock again.
+const MAX_WAIT: Duration = Duration::from_millis(10);
+
+// What the generator measured after the warm-up.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WorkloadStats {
+    pub issued: u64,
+    pub completed: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    pub total_latency: Duration,
+}
+
+impl WorkloadStats {
+    pub fn average_latency(&self) -> Option<Duration> {
+        if self.completed == 0 {
+            return None;
+        }
+        Some(self.total_latency / self.completed as u32)
+    }
+}
+
+// Issues the operations of a workload to a node through its client.
+pub struct WorkloadGenerator {
+    mode: LoadMode,
+    mix: OperationMix,
+    payload_size: PayloadSize,
+    warm_up: Duration,
+    node_id: NodeId,
+    rng: StdRng,
+    writes: Int,
+    // Ids grow with time, so the oldest operations come first.
+    outstanding: BTreeMap<RequestId, Instant>,
+    pub stats: WorkloadStats,
+}
+
+impl WorkloadGenerator {
+    // None if the node has nothing to do in this workload.
+    //
+    // Without a mix, writers do what the variant is about and readers
+    // read the counter or take snapshots. Without a mode, twice as many
+    // operations are kept outstanding as fit in the window, so the node
+    // has the next ones at hand as soon as the window moves.
+    pub fn new(workload: &Workload, variant: Variant, node_id: NodeId, is_writer: bool, is_reader: bool, window_size: Int, seed: u64) -> Option<WorkloadGenerator> {
+        if !is_writer && !is_reader {
+            return None;
+        }
+        let mix = workload.mix.unwrap_or_else(|| default_mix(variant, is_writer, is_reader));
+        if mix.total() == 0 {
+            return None;
+        }
+        let mode = workload.mode.unwrap_or(LoadMode::CLOSED { outstanding: 2 * window_size.max(1) as u32 });
+
+        Some(WorkloadGenerator {
+            mode,
+            mix,
+            payload_size: workload.payload_size,
+            warm_up: Duration::from_secs(workload.warm_up_secs as u64),
+            node_id,
+            rng: StdRng::seed_from_u64(seed),
+            writes: 0,
+            outstanding: BTreeMap::new(),
+            stats: WorkloadStats::default(),
+        })
+    }
+
+    pub fn run(&mut self, client: &mut Client, run_length: Duration) {
+        let start = Instant::now();
+        let mut sent: u64 = 0;
+        while start.elapsed() < run_length {
+            match self.mode {
+                LoadMode::OPEN { ops_per_sec } => {
+                    let due = Duration::from_secs_f64(sent as f64 / ops_per_sec as f64);
+                    let elapsed = start.elapsed();
+                    if elapsed >= due {
+                        self.issue(client, start);
+                        sent += 1;
+                        self.receive(client, start, Duration::from_secs(0));
+                    } else {
+                        self.receive(client, start, (due - elapsed).min(MAX_WAIT));
+                    }
+                }
+                LoadMode::CLOSED { outstanding } => {
+                    if self.outstanding.len() < outstanding as usize {
+                        self.issue(client, start);
+                        self.receive(client, start, Duration::from_secs(0));
+                    } else {
+                        self.receive(client, start, MAX_WAIT);
+                    }
+                }
+            }
+            self.expire(start);
+        }
+    }
+
+    pub fn next_request(&mut self) -> ClientRequest {
+        let mut pick = self.rng.gen_range(0, self.mix.total());
+        let weights = [
+            self.mix.broadcast,
+            self.mix.increase,
+            self.mix.decrease,
+            self.mix.read,
+            self.mix.write,
+            self.mix.snapshot,
+        ];
+        let mut operation = 0;
+        while pick >= weights[operation] {
+            pick -= weights[operation];
+            operation += 1;
+        }
+        match operation {
+            0 => ClientRequest::BROADCAST(AppMessage::BROADCAST(self.payload())),
+            1 => ClientRequest::INCREASE,
+            2 => ClientRequest::DECREASE,
+            3 => ClientRequest::READ,
+            4 => {
+                let write = ClientRequest::WRITE { r: self.node_id + self.writes, v: self.writes + 1 };
+                self.writes += 1;
+                write
+            },
+            _ => ClientRequest::SNAPSHOT,
+        }
+    }
+
+    // The number of the broadcast, padded with zeros to the payload size.
+    fn payload(&mut self) -> String {
+        let size = match self.payload_size {
+            PayloadSize::FIXED(size) => size,
+            PayloadSize::UNIFORM { min, max } => self.rng.gen_range(min, max + 1),
+        };
+        format!("{:0>width$}", self.stats.issued, width = size as usize)
+    }
+
+    fn issue(&mut self, client: &mut Client, start: Instant) {
+        let request = self.next_request();
+        if let Ok(id) = client.send(request) {
+            self.outstanding.insert(id, Instant::now());
+            if start.elapsed() >= self.warm_up {
+                self.stats.issued += 1;
+            }
+        }
+    }
+
+    fn receive(&mut self, client: &mut Client, start: Instant, timeout: Duration) {
+        let mut timeout = timeout;
+        while let Ok((id, response)) = client.recv_timeout(timeout) {
+            timeout = Duration::from_secs(0);
+            let issued_at = match self.outstanding.remove(&id) {
+                Some(issued_at) => issued_at,
+                None => continue,
+            };
+            if start.elapsed() < self.warm_up {
+                continue;
+            }
+            match response {
+                ClientResponse::Error(ClientError::Timeout) => self.stats.timeouts += 1,
+                ClientResponse::Error(_) => self.stats.errors += 1,
+                _ => {
+                    self.stats.completed += 1;
+                    self.stats.total_latency += issued_at.elapsed();
+                }
+            }
+        }
+    }
+
+    fn expire(&mut self, start: Instant) {
+        while let Some((&id, issued_at)) = self.outstanding.iter().next() {
+            if issued_at.elapsed() < REQUEST_TIMEOUT {
+                break;
+            }
+            self.outstanding.remove(&id);
+            if start.elapsed() >= self.warm_up {
+                self.stats.timeouts += 1;
+            }
+        }
+    }
+}
+
+fn default_mix(variant: Variant, is_writer: bool, is_reader: bool) -> OperationMix {
+    let writes = if is_writer { 1 } else { 0 };
+    let reads = if is_reader { 1 } else { 0 };
+    match variant {
+        Variant::URB | Variant::SCD => OperationMix { broadcast: writes, ..OperationMix::default() },
+        Variant::COUNTER => OperationMix { increase: writes, read: reads, ..OperationMix::default() },
+        Variant::SNAPSHOT => OperationMix { write: writes, snapshot: reads, ..OperationMix::default() },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operations_are_drawn_by_their_weight_in_the_mix() {
+        let workload: Workload = "outstanding=4;mix=increase:90/read:10;size=16-32;warmup=2".parse().unwrap();
+        assert_eq!(workload.to_string().parse::<Workload>().unwrap(), workload);
+        assert_eq!(workload.mode, Some(LoadMode::CLOSED { outstanding: 4 }));
+
+        let mut generator = WorkloadGenerator::new(&workload, Variant::COUNTER, 1, true, false, 10, 7).unwrap();
+        let reads = (0..10000).filter(|_| generator.next_request() == ClientRequest::READ).count();
+        assert!(reads > 800 && reads < 1200, "{} reads", reads);
+
+        // A node that is neither writer nor reader is idle.
+        assert!(WorkloadGenerator::new(&Workload::default(), Variant::SCD, 1, false, false, 10, 7).is_none());
+    }
+}
@@ -9,6 +9,9 @@ use colored::Color::*;
 use crate::node_info::NodeInfo;
 use crate::types::{Int, NodeId};
 use crate::variant::Variant;
+use crate::encoding::Encoding;
+use crate::transport_kind::TransportKind;
+use crate::failure_detector_kind::FailureDetectorKind;
 
 pub fn hosts_file(help_text: &'static str) -> Arg<'static, 'static> {
     Arg::with_name("hosts-file")
@@ -75,7 +78,7 @@ pub fn node_infos_from_host_pool(matches: &ArgMatches<'static>) -> HashSet<NodeI
 pub fn node_infos_from_string(string: String) -> HashSet<NodeInfo> {
     let mut node_infos = HashSet::new();
 
-    for line in string.lines() {
+    for line in string.lines().filter(|line| !is_link_key_line(line)) {
         let components: Vec<&str> = line.split(",").collect();
         let node_id = components[0].parse().expect("Could not parse node id.");
         let socket_addr = components[1]
@@ -104,6 +107,41 @@ pub fn node_infos_from_string(string: String) -> HashSet<NodeInfo> {
     node_infos
 }
 
+// Besides the node lines, a hosts file may contain lines of the form
+// key,<node id>,<node id>,<hex secret> with the secret shared by two nodes.
+pub fn is_link_key_line(line: &str) -> bool {
+    line.starts_with("key,")
+}
+
+pub fn link_keys_from_string(string: &str, node_id: NodeId) -> HashMap<NodeId, Vec<u8>> {
+    let mut link_keys = HashMap::new();
+
+    for line in string.lines().filter(|line| is_link_key_line(line)) {
+        let components: Vec<&str> = line.split(",").collect();
+        let first: NodeId = components[1].parse().expect("Could not parse node id of a link key.");
+        let second: NodeId = components[2].parse().expect("Could not parse node id of a link key.");
+        let key = key_from_hex_string(components[3]);
+
+        if first == node_id {
+            link_keys.insert(second, key);
+        } else if second == node_id {
+            link_keys.insert(first, key);
+        }
+    }
+
+    link_keys
+}
+
+fn key_from_hex_string(hex: &str) -> Vec<u8> {
+    if hex.len() % 2 != 0 {
+        panic!("A link key must have an even number of hex digits.");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("Could not parse link key."))
+        .collect()
+}
+
 pub fn number_of_writers() -> Arg<'static, 'static> {
     Arg::with_name("number-of-writers")
         .required(false)
@@ -210,6 +248,135 @@ pub fn variant_from_matches(matches: &ArgMatches<'static>) -> Variant {
         .expect("Could not parse variant arg.")
 }
 
+pub fn encoding() -> Arg<'static, 'static> {
+    Arg::with_name("encoding")
+        .required(false)
+        .takes_value(true)
+        .possible_values(&["JSON", "BINARY"])
+        .default_value("JSON")
+        .long("encoding")
+        .help("How protocol messages are serialized on the wire.")
+}
+
+pub fn encoding_from_matches(matches: &ArgMatches<'static>) -> Encoding {
+    matches
+        .value_of("encoding")
+        .expect("Encoding arg not existing.")
+        .parse()
+        .expect("Could not parse encoding arg.")
+}
+
+pub fn transport() -> Arg<'static, 'static> {
+    Arg::with_name("transport")
+        .required(false)
+        .takes_value(true)
+        .possible_values(&["UDP", "TCP"])
+        .default_value("UDP")
+        .long("transport")
+        .help("Which links the nodes communicate over.")
+}
+
+pub fn transport_from_matches(matches: &ArgMatches<'static>) -> TransportKind {
+    matches
+        .value_of("transport")
+        .expect("Transport arg not existing.")
+        .parse()
+        .expect("Could not parse transport arg.")
+}
+
+pub fn failure_detector() -> Arg<'static, 'static> {
+    Arg::with_name("failure-detector")
+        .required(false)
+        .takes_value(true)
+        .possible_values(&["THETA", "PHI"])
+        .default_value("THETA")
+        .long("failure-detector")
+        .help("Which failure detector decides the trusted nodes.")
+}
+
+pub fn failure_detector_from_matches(matches: &ArgMatches<'static>) -> FailureDetectorKind {
+    matches
+        .value_of("failure-detector")
+        .expect("Failure detector arg not existing.")
+        .parse()
+        .expect("Could not parse failure detector arg.")
+}
+
+pub fn faults() -> Arg<'static, 'static> {
+    Arg::with_name("faults")
+        .required(false)
+        .takes_value(true)
+        .long("faults")
+        .help("Faults injected on every outgoing link, e.g. \"drop=0.1,duplicate=0.05,reorder=4,delay=20,jitter=5\". Delays are in milliseconds.")
+}
+
+pub fn faults_string_from_matches(matches: &ArgMatches<'static>) -> Option<String> {
+    matches.value_of("faults").map(String::from)
+}
+
+pub fn link_faults() -> Arg<'static, 'static> {
+    Arg::with_name("link-faults")
+        .required(false)
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .long("link-faults")
+        .help("Faults injected on the link to one node, e.g. \"3:drop=0.5\". Overrides --faults for that link, can be given several times.")
+}
+
+pub fn link_faults_strings_from_matches(matches: &ArgMatches<'static>) -> Vec<String> {
+    matches
+        .values_of("link-faults")
+        .map(|values| values.map(String::from).collect())
+        .unwrap_or_default()
+}
+
+pub fn workload() -> Arg<'static, 'static> {
+    Arg::with_name("workload")
+        .required(false)
+        .takes_value(true)
+        .long("workload")
+        .help("How the client generates operations, e.g. \"rate=2000;mix=increase:90/read:10;size=16-256;warmup=5\" or \"outstanding=8\". Keys left out depend on the variant. Without a rate or outstanding, each client keeps twice the window size of operations in flight; writers broadcast, increase or write and readers read or take snapshots.")
+}
+
+pub fn workload_string_from_matches(matches: &ArgMatches<'static>) -> Option<String> {
+    matches.value_of("workload").map(String::from)
+}
+
+pub fn fault_seed() -> Arg<'static, 'static> {
+    Arg::with_name("fault-seed")
+        .required(false)
+        .takes_value(true)
+        .default_value("0")
+        .long("fault-seed")
+        .help("Seed for the injected faults. Runs with the same seed inject the same faults.")
+}
+
+pub fn fault_seed_from_matches(matches: &ArgMatches<'static>) -> u64 {
+    matches
+        .value_of("fault-seed")
+        .expect("Fault seed arg not existing.")
+        .parse()
+        .expect("Could not parse fault seed.")
+}
+
+pub fn max_int() -> Arg<'static, 'static> {
+    Arg::with_name("max-int")
+        .required(false)
+        .takes_value(true)
+        .default_value("2147483647")
+        .long("max-int")
+        .help("The largest value of the seq, sn and heartbeat counters. Counters close to it trigger a global reset, a small value makes resets happen within seconds.")
+}
+
+pub fn max_int_from_matches(matches: &ArgMatches<'static>) -> Int {
+    matches
+        .value_of("max-int")
+        .expect("Max int arg not existing.")
+        .parse()
+        .expect("Could not parse max int.")
+}
+
 pub fn delta() -> Arg<'static, 'static> {
     Arg::with_name("delta")
         .required(false)
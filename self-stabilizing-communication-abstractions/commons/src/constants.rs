@@ -1,2 +1 @@
-pub const THETAFD_W: i32 = 5000;
 pub const WINDOW_SIZE: i32 = 10;
@@ -0,0 +1,25 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+// How protocol messages are serialized on the wire. JSON is readable when
+// debugging, BINARY is considerably smaller and faster to produce.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Copy, Hash)]
+pub enum Encoding {
+    JSON,
+    BINARY,
+}
+
+impl FromStr for Encoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "JSON" {
+            Ok(Encoding::JSON)
+        } else if s == "BINARY" {
+            Ok(Encoding::BINARY)
+        } else {
+            panic!("Unknown encoding.");
+        }
+    }
+}
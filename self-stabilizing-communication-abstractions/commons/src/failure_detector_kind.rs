@@ -0,0 +1,26 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+// Which failure detector decides the trusted nodes. THETA counts heartbeats
+// relative to the other nodes, PHI looks at how late a heartbeat is given
+// the inter-arrival times observed so far.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Copy, Hash)]
+pub enum FailureDetectorKind {
+    THETA,
+    PHI,
+}
+
+impl FromStr for FailureDetectorKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "THETA" {
+            Ok(FailureDetectorKind::THETA)
+        } else if s == "PHI" {
+            Ok(FailureDetectorKind::PHI)
+        } else {
+            panic!("Unknown failure detector.");
+        }
+    }
+}
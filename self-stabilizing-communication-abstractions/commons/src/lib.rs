@@ -8,3 +8,7 @@ pub mod run_result;
 pub mod types;
 pub mod variant;
 pub mod constants;
+pub mod encoding;
+pub mod transport_kind;
+pub mod failure_detector_kind;
+pub mod workload;
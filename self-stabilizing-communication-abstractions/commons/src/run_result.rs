@@ -22,6 +22,18 @@ pub struct RunResult {
     pub link_latency: HashMap<Int, f64>,
     pub recovery_time: Option<u128>,
     pub throughputs: Option<Vec<f64>>,
+    // Default so that results recorded before these were added still parse.
+    #[serde(default)]
+    pub bytes_sent: u64,
+    #[serde(default)]
+    pub bytes_received: u64,
+    #[serde(default)]
+    pub rejected_datagrams: u64,
+    #[serde(default)]
+    pub trust_changes: Vec<TrustChange>,
+    // Own broadcasts a global reset dropped before they were delivered.
+    #[serde(default)]
+    pub dropped_by_reset: Vec<Tag>,
 }
 
 impl RunResult {
@@ -39,7 +51,12 @@ impl RunResult {
             metadata: Metadata::new(),
             link_latency: HashMap::new(),
             recovery_time: None,
-            throughputs: None
+            throughputs: None,
+            bytes_sent: 0,
+            bytes_received: 0,
+            rejected_datagrams: 0,
+            trust_changes: Vec::new(),
+            dropped_by_reset: Vec::new(),
         }
     }
     #[allow(dead_code)]
@@ -72,6 +89,15 @@ impl MessageTypeResult {
     }
 }
 
+// A node becoming suspected or trusted again by the Θ failure detector,
+// micros after the start of the run.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrustChange {
+    pub node_id: NodeId,
+    pub trusted: bool,
+    pub micros: u128,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Metadata {
     pub node_id: NodeId,
@@ -0,0 +1,26 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+// Which links the nodes use. UDP relies on the self-stabilizing
+// retransmissions of the protocols, TCP gives reliable FIFO links as long
+// as a connection stays up.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Copy, Hash)]
+pub enum TransportKind {
+    UDP,
+    TCP,
+}
+
+impl FromStr for TransportKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "UDP" {
+            Ok(TransportKind::UDP)
+        } else if s == "TCP" {
+            Ok(TransportKind::TCP)
+        } else {
+            panic!("Unknown transport.");
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+// How the client of a node generates operations. The spec looks like
+// "rate=2000;mix=increase:90/read:10;size=16-256;warmup=5". The keys are
+// separated by ';' rather than ',' so that a spec fits in a scenario line.
+// Keys that are left out are chosen from the variant and the role of the
+// node, see the application's workload module.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Workload {
+    pub mode: Option<LoadMode>,
+    pub mix: Option<OperationMix>,
+    pub payload_size: PayloadSize,
+    // Operations completed during the first seconds are not counted.
+    pub warm_up_secs: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoadMode {
+    // Operations are issued at a fixed rate, whether or not the node
+    // keeps up ("rate=<ops per second>").
+    OPEN { ops_per_sec: u32 },
+    // A new operation is issued when one of the outstanding ones has
+    // completed ("outstanding=<n>").
+    CLOSED { outstanding: u32 },
+}
+
+// The weights of the operations, "mix=increase:90/read:10".
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OperationMix {
+    pub broadcast: u32,
+    pub increase: u32,
+    pub decrease: u32,
+    pub read: u32,
+    pub write: u32,
+    pub snapshot: u32,
+}
+
+// The length in bytes of broadcast payloads, "size=64" or "size=16-256"
+// for a uniform distribution.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PayloadSize {
+    FIXED(u32),
+    UNIFORM { min: u32, max: u32 },
+}
+
+impl Default for PayloadSize {
+    fn default() -> PayloadSize {
+        PayloadSize::FIXED(8)
+    }
+}
+
+impl OperationMix {
+    pub fn total(&self) -> u32 {
+        self.broadcast + self.increase + self.decrease + self.read + self.write + self.snapshot
+    }
+
+    fn weights(&self) -> [(&'static str, u32); 6] {
+        [
+            ("broadcast", self.broadcast),
+            ("increase", self.increase),
+            ("decrease", self.decrease),
+            ("read", self.read),
+            ("write", self.write),
+            ("snapshot", self.snapshot),
+        ]
+    }
+}
+
+impl FromStr for OperationMix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OperationMix, String> {
+        let mut mix = OperationMix::default();
+        for part in s.split('/').map(str::trim).filter(|part| !part.is_empty()) {
+            let mut name_weight = part.splitn(2, ':');
+            let name = name_weight.next().unwrap();
+            let weight = name_weight.next().ok_or(format!("Missing weight for operation '{}'", name))?;
+            let weight = weight.parse().map_err(|_| format!("Invalid weight '{}' for operation '{}'", weight, name))?;
+            match name {
+                "broadcast" => mix.broadcast = weight,
+                "increase" => mix.increase = weight,
+                "decrease" => mix.decrease = weight,
+                "read" => mix.read = weight,
+                "write" => mix.write = weight,
+                "snapshot" => mix.snapshot = weight,
+                _ => return Err(format!("Unknown operation '{}'", name)),
+            }
+        }
+        if mix.total() == 0 {
+            return Err("The operation mix has no weights".to_string());
+        }
+        Ok(mix)
+    }
+}
+
+impl fmt::Display for OperationMix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self.weights().iter()
+            .filter(|(_, weight)| *weight > 0)
+            .map(|(name, weight)| format!("{}:{}", name, weight))
+            .collect();
+        write!(f, "{}", parts.join("/"))
+    }
+}
+
+impl FromStr for PayloadSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PayloadSize, String> {
+        let invalid = || format!("Invalid payload size '{}'", s);
+        let mut bounds = s.splitn(2, '-');
+        let min = bounds.next().unwrap().trim().parse().map_err(|_| invalid())?;
+        match bounds.next() {
+            None => Ok(PayloadSize::FIXED(min)),
+            Some(max) => {
+                let max = max.trim().parse().map_err(|_| invalid())?;
+                if max < min {
+                    return Err(invalid());
+                }
+                Ok(PayloadSize::UNIFORM { min, max })
+            }
+        }
+    }
+}
+
+impl fmt::Display for PayloadSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadSize::FIXED(size) => write!(f, "{}", size),
+            PayloadSize::UNIFORM { min, max } => write!(f, "{}-{}", min, max),
+        }
+    }
+}
+
+impl FromStr for Workload {
+    type Err = String;
+
+    // Newlines separate keys as well, so a workload file can have one key
+    // per line. Lines starting with "//" are comments.
+    fn from_str(s: &str) -> Result<Workload, String> {
+        let mut workload = Workload::default();
+        let pairs = s.lines()
+            .filter(|line| !line.trim_start().starts_with("//"))
+            .flat_map(|line| line.split(';'))
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty());
+        for pair in pairs {
+            let mut key_value = pair.splitn(2, '=');
+            let key = key_value.next().unwrap().trim();
+            let value = key_value.next().ok_or(format!("Missing value for workload key '{}'", key))?.trim();
+            let invalid = || format!("Invalid value '{}' for workload key '{}'", value, key);
+
+            match key {
+                "rate" => workload.mode = Some(LoadMode::OPEN { ops_per_sec: value.parse().map_err(|_| invalid())? }),
+                "outstanding" => workload.mode = Some(LoadMode::CLOSED { outstanding: value.parse().map_err(|_| invalid())? }),
+                "mix" => workload.mix = Some(value.parse()?),
+                "size" => workload.payload_size = value.parse()?,
+                "warmup" => workload.warm_up_secs = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("Unknown workload key '{}'", key)),
+            }
+        }
+        match workload.mode {
+            Some(LoadMode::OPEN { ops_per_sec: 0 }) => Err("The rate must be above 0".to_string()),
+            Some(LoadMode::CLOSED { outstanding: 0 }) => Err("At least one operation must be outstanding".to_string()),
+            _ => Ok(workload),
+        }
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut pairs = Vec::new();
+        match self.mode {
+            Some(LoadMode::OPEN { ops_per_sec }) => pairs.push(format!("rate={}", ops_per_sec)),
+            Some(LoadMode::CLOSED { outstanding }) => pairs.push(format!("outstanding={}", outstanding)),
+            None => {}
+        }
+        if let Some(mix) = self.mix {
+            pairs.push(format!("mix={}", mix));
+        }
+        if self.payload_size != PayloadSize::default() {
+            pairs.push(format!("size={}", self.payload_size));
+        }
+        if self.warm_up_secs > 0 {
+            pairs.push(format!("warmup={}", self.warm_up_secs));
+        }
+        write!(f, "{}", pairs.join(";"))
+    }
+}
@@ -10,6 +10,8 @@ use commons::node_info::NodeInfo;
 use commons::run_result::RunResult;
 use commons::types::{NodeId, Int, Tag};
 use commons::variant::Variant;
+use commons::encoding::Encoding;
+use commons::failure_detector_kind::FailureDetectorKind;
 
 use crate::scenario::Scenario;
 use std::time::SystemTime;
@@ -372,6 +374,95 @@ pub fn experiment7(results: &Data, rounds: usize) {
     println!("*************Experiment7 Result END ***************");
 }
 
+pub fn experiment8(results: &Data, rounds: usize) {
+    let avg_bytes_sent = get_avg_bytes_sent_for_all_scenarios(results, rounds);
+    println!("*************Experiment8 Result START, rounds {} ***************", rounds);
+    for encoding in vec![Encoding::JSON, Encoding::BINARY] {
+        let avg_bytes_sent_for_encoding = avg_bytes_sent.iter()
+            .filter(|(scenario, _)| scenario.encoding == encoding)
+            .map(|(scenario, bytes)| (*scenario, *bytes))
+            .collect();
+        let title = format!("Experiment 8: Bytes sent per node and second, {:?} encoding", encoding);
+        print_result_values(avg_bytes_sent_for_encoding, "number of processes", &title);
+    }
+    println!("*************Experiment8 Result END ***************");
+}
+
+pub fn experiment9(results: &Data, rounds: usize) {
+    let avg_false_suspicions = get_avg_false_suspicions_for_all_scenarios(results);
+    println!("*************Experiment9 Result START, rounds {} ***************", rounds);
+    for failure_detector in vec![FailureDetectorKind::THETA, FailureDetectorKind::PHI] {
+        let avg_false_suspicions_for_detector = avg_false_suspicions.iter()
+            .filter(|(scenario, _)| scenario.failure_detector == failure_detector)
+            .map(|(scenario, suspicions)| (*scenario, *suspicions))
+            .collect();
+        let title = format!("Experiment 9: False suspicions per node and minute, {:?} failure detector", failure_detector);
+        print_result_values(avg_false_suspicions_for_detector, "number of processes", &title);
+    }
+    println!("*************Experiment9 Result END ***************");
+}
+
+// False suspicions. No node crashes during a run, so every suspicion
+// before the run ends is a false one.
+pub fn node_averaged_false_suspicions_for_scenario_round(data: &Data, scenario: &Scenario, round: usize) -> f64 {
+    let mut false_suspicions_sum = 0.0;
+
+    for node_id in 1..(scenario.number_of_nodes+1) {
+        let result = get_scenario_round_id(data, scenario, round, node_id);
+        if result.metadata.run_length > 0 {
+            let run_length_micros = result.metadata.run_length as u128 * 1_000_000;
+            let false_suspicions = result.trust_changes.iter()
+                .filter(|change| !change.trusted && change.micros < run_length_micros)
+                .count();
+            false_suspicions_sum += false_suspicions as f64 * 60.0 / result.metadata.run_length as f64;
+        }
+    }
+
+    false_suspicions_sum / scenario.number_of_nodes as f64
+}
+
+pub fn get_avg_false_suspicions_for_all_scenarios<'a>(data: &'a Data) -> HashMap<&'a Scenario, f64> {
+    let mut avg_false_suspicions_for_all_scenarios = HashMap::new();
+    for (scenario, results) in data {
+        let mut false_suspicions_sum = 0.0;
+        for round in 0..results.len() {
+            false_suspicions_sum += node_averaged_false_suspicions_for_scenario_round(data, scenario, round);
+        }
+        if !results.is_empty() {
+            avg_false_suspicions_for_all_scenarios.insert(scenario, false_suspicions_sum / results.len() as f64);
+        }
+    }
+    avg_false_suspicions_for_all_scenarios
+}
+
+// Bytes on wire
+pub fn node_averaged_bytes_sent_for_scenario_round(data: &Data, scenario: &Scenario, round: usize) -> f64 {
+    let mut bytes_sent_sum = 0.0;
+
+    for node_id in 1..(scenario.number_of_nodes+1) {
+        let result = get_scenario_round_id(data, scenario, round, node_id);
+        if result.metadata.run_length > 0 {
+            bytes_sent_sum += result.bytes_sent as f64 / result.metadata.run_length as f64;
+        }
+    }
+
+    bytes_sent_sum / scenario.number_of_nodes as f64
+}
+
+pub fn get_avg_bytes_sent_for_all_scenarios<'a>(data: &'a Data, rounds: usize) -> HashMap<&'a Scenario, f64> {
+    let mut avg_bytes_sent_for_all_scenarios = HashMap::new();
+    for (scenario, results) in data {
+        let mut bytes_sent_sum = 0.0;
+        for round in 0..results.len() {
+            bytes_sent_sum += node_averaged_bytes_sent_for_scenario_round(data, scenario, round);
+        }
+        if !results.is_empty() {
+            avg_bytes_sent_for_all_scenarios.insert(scenario, bytes_sent_sum / results.len() as f64);
+        }
+    }
+    avg_bytes_sent_for_all_scenarios
+}
+
 fn get_avg_ss_latency_for_all_scenarios<'a>(data: &'a Data, rounds: usize, op: &Operation) -> HashMap<&'a Scenario, f64> {
     let mut avg_latency_for_all_scenarios = HashMap::new();
     for (scenario, results) in data {
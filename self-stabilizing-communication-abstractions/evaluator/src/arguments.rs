@@ -95,6 +95,8 @@ pub enum Experiment {
     Experiment5,
     Experiment6,
     Experiment7,
+    Experiment8,
+    Experiment9,
 }
 
 pub struct AggregateArguments {
@@ -307,6 +309,8 @@ fn experiment_from_matches(
         5 => Experiment::Experiment5,
         6 => Experiment::Experiment6,
         7 => Experiment::Experiment7,
+        8 => Experiment::Experiment8,
+        9 => Experiment::Experiment9,
         _ => panic!("Unknown experiment!"),
     }
 }
@@ -69,7 +69,11 @@ fn run_install_subcommand(arguments: &InstallArguments) {
 
 fn randomize_hosts_file(file_path: &str) {
     let string = fs::read_to_string(file_path).expect("Unable to read the hosts file.");
-    let mut hosts_vec: Vec<String> = string.lines().map(|s| s.to_string()).collect();
+    let mut hosts_vec: Vec<String> = string.lines()
+        .filter(|s| !commons::arguments::is_link_key_line(s))
+        .map(|s| s.to_string())
+        .collect();
+    let link_key_lines = string.lines().filter(|s| commons::arguments::is_link_key_line(s));
     hosts_vec.shuffle(&mut thread_rng());
     for (i, node_string) in hosts_vec.iter_mut().enumerate() {
         let comma_offset = node_string.find(',').unwrap();
@@ -79,6 +83,9 @@ fn randomize_hosts_file(file_path: &str) {
     for host in hosts_vec {
         shuffled_string.push_str(&format!("{}\n", host));
     }
+    for link_key_line in link_key_lines {
+        shuffled_string.push_str(&format!("{}\n", link_key_line));
+    }
     fs::write(file_path, shuffled_string).unwrap();
 }
 
@@ -188,6 +195,10 @@ fn execute_command_for_scenario_and_arguments(scenario: &Scenario, arguments: &G
     if scenario.window_size.is_some() {
         command = format!("{} -s {}", command, scenario.window_size.unwrap());
     }
+    command = format!("{} --encoding {:?} --failure-detector {:?}", command, scenario.encoding, scenario.failure_detector);
+    if let Some(workload) = scenario.workload {
+        command = format!("{} --workload '{}'", command, workload);
+    }
 
     execution::execute_local_command(&command)
         .wait()
@@ -273,6 +284,8 @@ fn run_aggregate_subcommand(arguments: &AggregateArguments) {
         Experiment::Experiment5 => aggregation::experiment5(results, arguments.rounds),
         Experiment::Experiment6 => aggregation::experiment6(results, arguments.rounds),
         Experiment::Experiment7 => aggregation::experiment7(results, arguments.rounds),
+        Experiment::Experiment8 => aggregation::experiment8(results, arguments.rounds),
+        Experiment::Experiment9 => aggregation::experiment9(results, arguments.rounds),
     };
     // let s = serde_json::to_string(&results).unwrap();
     // println!("{}", s);
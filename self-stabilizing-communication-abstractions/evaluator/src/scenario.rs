@@ -2,6 +2,9 @@ use serde::{Deserialize, Serialize};
 
 use commons::types::Int;
 use commons::variant::Variant;
+use commons::encoding::Encoding;
+use commons::failure_detector_kind::FailureDetectorKind;
+use commons::workload::Workload;
 use std::collections::HashSet;
 use commons::node_info::NodeInfo;
 use commons::constants::WINDOW_SIZE;
@@ -17,6 +20,10 @@ pub struct Scenario {
     pub delta: Int,
     pub window_size: Option<Int>,
     pub variant: Variant,
+    pub encoding: Encoding,
+    pub failure_detector: FailureDetectorKind,
+    // None runs the default client of the variant.
+    pub workload: Option<Workload>,
 }
 // This struct is serialized in String because it's used as a key. And json only allows string keys.
 
@@ -37,6 +44,9 @@ impl Scenario {
             delta: delta,
             window_size: None,
             variant,
+            encoding: Encoding::JSON,
+            failure_detector: FailureDetectorKind::THETA,
+            workload: None,
         }
     }
     pub fn window_size(self) -> Int {
@@ -58,8 +68,24 @@ impl From<Scenario> for String {
             scenario.number_of_crashing_nodes,
             scenario.delta,
         );
+        let window_s = match scenario.window_size {
+            Some(window_size) => window_size.to_string(),
+            None => "".to_string(),
+        };
+        // The encoding, failure detector and workload are only written when
+        // they're not the default, so that older scenario strings keep their
+        // meaning.
+        if let Some(workload) = scenario.workload {
+            return format!("{},{},{:?},{:?},{}", s, window_s, scenario.encoding, scenario.failure_detector, workload);
+        }
+        if scenario.failure_detector != FailureDetectorKind::THETA {
+            return format!("{},{},{:?},{:?}", s, window_s, scenario.encoding, scenario.failure_detector);
+        }
+        if scenario.encoding != Encoding::JSON {
+            return format!("{},{},{:?}", s, window_s, scenario.encoding);
+        }
         if scenario.window_size.is_some() {
-           return format!("{},{}", s, scenario.window_size.unwrap());
+           return format!("{},{}", s, window_s);
         }
         s
     }
@@ -107,6 +133,21 @@ impl From<String> for Scenario {
             }
         }
 
+        let mut encoding = Encoding::JSON;
+        if components.len() > 8 {
+            encoding = components[8].parse().expect("Could not parse encoding");
+        }
+
+        let mut failure_detector = FailureDetectorKind::THETA;
+        if components.len() > 9 {
+            failure_detector = components[9].parse().expect("Could not parse failure detector");
+        }
+
+        let mut workload = None;
+        if components.len() > 10 && !components[10].is_empty() {
+            workload = Some(components[10].parse().expect("Could not parse workload"));
+        }
+
         if scenario_name != "Scenario" {
             panic!("Scenario name doesn't match.");
         }
@@ -118,7 +159,10 @@ impl From<String> for Scenario {
             number_of_writers,
             variant,
             window_size,
-            delta: delta
+            delta: delta,
+            encoding,
+            failure_detector,
+            workload,
         }
     }
 }
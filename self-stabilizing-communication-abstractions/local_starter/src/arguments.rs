@@ -3,6 +3,9 @@ use clap::{App, AppSettings, Arg, ArgMatches};
 use commons::arguments;
 use commons::types::Int;
 use commons::variant::Variant;
+use commons::encoding::Encoding;
+use commons::transport_kind::TransportKind;
+use commons::failure_detector_kind::FailureDetectorKind;
 
 lazy_static! {
     pub static ref ARGUMENTS: Arguments = Arguments::new();
@@ -19,7 +22,15 @@ pub struct Arguments {
     pub print_client_operations_string: String,
     pub run_length_string: String,
     pub record_evaluation_info_string: String,
-    pub variant: Variant
+    pub variant: Variant,
+    pub encoding: Encoding,
+    pub transport: TransportKind,
+    pub failure_detector: FailureDetectorKind,
+    pub faults_string: Option<String>,
+    pub link_faults_strings: Vec<String>,
+    pub workload_string: Option<String>,
+    pub fault_seed: u64,
+    pub max_int: Int,
 }
 
 impl Arguments {
@@ -42,6 +53,14 @@ impl Arguments {
                 &matches,
             ),
             variant: arguments::variant_from_matches(&matches),
+            encoding: arguments::encoding_from_matches(&matches),
+            transport: arguments::transport_from_matches(&matches),
+            failure_detector: arguments::failure_detector_from_matches(&matches),
+            faults_string: arguments::faults_string_from_matches(&matches),
+            link_faults_strings: arguments::link_faults_strings_from_matches(&matches),
+            workload_string: arguments::workload_string_from_matches(&matches),
+            fault_seed: arguments::fault_seed_from_matches(&matches),
+            max_int: arguments::max_int_from_matches(&matches),
         }
     }
 }
@@ -62,6 +81,14 @@ fn get_matches() -> ArgMatches<'static> {
         .arg(arguments::delta())
         .arg(arguments::window_size_argument())
         .arg(arguments::variant())
+        .arg(arguments::encoding())
+        .arg(arguments::transport())
+        .arg(arguments::failure_detector())
+        .arg(arguments::faults())
+        .arg(arguments::link_faults())
+        .arg(arguments::workload())
+        .arg(arguments::fault_seed())
+        .arg(arguments::max_int())
         .get_matches()
 }
 
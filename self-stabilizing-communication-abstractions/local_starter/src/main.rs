@@ -104,8 +104,21 @@ fn run_single_application_instance(node_id: NodeId, is_writer: bool, is_failing:
         window_s = format!("-s {}", ARGUMENTS.window_size.unwrap());
     }
 
+    let mut faults_s = format!("--fault-seed {}", ARGUMENTS.fault_seed);
+    if let Some(faults) = &ARGUMENTS.faults_string {
+        faults_s.push_str(&format!(" --faults {}", faults));
+    }
+    for link_faults in ARGUMENTS.link_faults_strings.iter() {
+        faults_s.push_str(&format!(" --link-faults {}", link_faults));
+    }
+    // The spec is quoted since its keys are separated by ';'.
+    let workload_s = match &ARGUMENTS.workload_string {
+        Some(workload) => format!("--workload '{}'", workload),
+        None => "".to_string(),
+    };
+
     let color = commons::arguments::color_from_node_id(node_id);
-    let command = format!("cargo run {} --manifest-path ../application/Cargo.toml -- {} hosts.txt -c {:?} -l {} {} {} {} {} {} -v {:?} {} -d {}",
+    let command = format!("cargo run {} --manifest-path ../application/Cargo.toml -- {} hosts.txt -c {:?} -l {} {} {} {} {} {} -v {:?} {} -d {} --encoding {:?} --transport {:?} --failure-detector {:?} --max-int {} {} {}",
         ARGUMENTS.release_mode_string,
         node_id,
         color,
@@ -118,6 +131,12 @@ fn run_single_application_instance(node_id: NodeId, is_writer: bool, is_failing:
         ARGUMENTS.variant,
         crashing_s,
         ARGUMENTS.delta,
+        ARGUMENTS.encoding,
+        ARGUMENTS.transport,
+        ARGUMENTS.failure_detector,
+        ARGUMENTS.max_int,
+        faults_s,
+        workload_s,
     );
 
     execution::execute_local_command(&command)
@@ -6,6 +6,9 @@ use commons::arguments;
 use commons::node_info::NodeInfo;
 use commons::types::Int;
 use commons::variant::Variant;
+use commons::encoding::Encoding;
+use commons::transport_kind::TransportKind;
+use commons::failure_detector_kind::FailureDetectorKind;
 
 lazy_static! {
     pub static ref ARGUMENTS: Arguments = Arguments::new();
@@ -22,6 +25,11 @@ pub struct Arguments {
     pub delta: Int,
     pub window_size: Option<Int>,
     pub variant: Variant,
+    pub encoding: Encoding,
+    pub transport: TransportKind,
+    pub failure_detector: FailureDetectorKind,
+    pub max_int: Int,
+    pub workload_string: Option<String>,
     pub release_mode_string: String,
     pub print_client_operations_string: String,
     pub run_length_string: String,
@@ -45,6 +53,11 @@ impl Arguments {
             delta: arguments::delta_from_matches(&matches),
             window_size: arguments::window_size_from_matches(&matches),
             variant: arguments::variant_from_matches(&matches),
+            encoding: arguments::encoding_from_matches(&matches),
+            transport: arguments::transport_from_matches(&matches),
+            failure_detector: arguments::failure_detector_from_matches(&matches),
+            max_int: arguments::max_int_from_matches(&matches),
+            workload_string: arguments::workload_string_from_matches(&matches),
             release_mode_string: arguments::release_mode_string_from_matches(&matches),
             print_client_operations_string: arguments::print_client_operations_string_from_matches(
                 &matches,
@@ -87,6 +100,11 @@ fn get_matches() -> ArgMatches<'static> {
         .arg(arguments::number_of_crashing())
         .arg(arguments::delta())
         .arg(arguments::variant())
+        .arg(arguments::encoding())
+        .arg(arguments::transport())
+        .arg(arguments::failure_detector())
+        .arg(arguments::max_int())
+        .arg(arguments::workload())
         .arg(arguments::run_length())
         .arg(arguments::record_evaluation_info())
         .arg(arguments::optimize())
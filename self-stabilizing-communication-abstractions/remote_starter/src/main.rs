@@ -223,6 +223,11 @@ fn create_and_upload_hosts_file(node_info: &NodeInfo) {
     for node in new_hosts {
         lines.push(format!("{},{},{},{},{}", node.node_id, node.socket_addr.to_string(), node.key_path, node.username, node.script_path));
     }
+    // The link keys are passed on as they are, they refer to node ids.
+    let hosts_string = fs::read_to_string(&ARGUMENTS.hosts_file).expect("Unable to read the hosts file.");
+    for line in hosts_string.lines().filter(|line| commons::arguments::is_link_key_line(line)) {
+        lines.push(line.to_string());
+    }
     fs::write(
         tmp_host,
         lines.join("\n"),
@@ -304,6 +309,11 @@ fn run_application_on_remote_computer(node_info: &NodeInfo) -> Child {
     if node_info.is_crashing {
         crashing_s = "-b";
     }
+    // The spec is quoted since its keys are separated by ';'.
+    let workload_s = match &ARGUMENTS.workload_string {
+        Some(workload) => format!("--workload '{}'", workload),
+        None => "".to_string(),
+    };
     let pi_path_fix = if ARGUMENTS.is_local_run {
         "PATH=$PATH:/home/pi/.cargo/bin"
     } else {
@@ -311,7 +321,7 @@ fn run_application_on_remote_computer(node_info: &NodeInfo) -> Child {
     };
 
     let command_string = format!(
-        "\"cd {}/application/; {} RUST_BACKTRACE=1 cargo run {} -- {} {} -l {} -c {:?} {} {} {} {} {} -v {:?} {} -d {}\"",
+        "\"cd {}/application/; {} RUST_BACKTRACE=1 cargo run {} -- {} {} -l {} -c {:?} {} {} {} {} {} -v {:?} {} -d {} --encoding {:?} --transport {:?} --failure-detector {:?} --max-int {} {}\"",
         REMOTE_DIRECTORY_NAME,
         pi_path_fix,
         ARGUMENTS.release_mode_string,
@@ -327,6 +337,11 @@ fn run_application_on_remote_computer(node_info: &NodeInfo) -> Child {
         ARGUMENTS.variant,
         crashing_s,
         ARGUMENTS.delta,
+        ARGUMENTS.encoding,
+        ARGUMENTS.transport,
+        ARGUMENTS.failure_detector,
+        ARGUMENTS.max_int,
+        workload_s,
     );
     println!("{}",command_string);
 